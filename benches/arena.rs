@@ -0,0 +1,86 @@
+//! Compares capturing records with `to_value` against capturing them into an `Arena`
+//!
+//! Run with `cargo bench --bench arena`, optionally followed by the number of records.
+
+use std::collections::BTreeMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+use serde_derive::Serialize;
+use serde_value::arena::Arena;
+
+#[derive(Serialize)]
+struct Record {
+    id: u64,
+    name: String,
+    tags: Vec<&'static str>,
+    attributes: BTreeMap<String, f64>,
+    status: Status,
+    parent: Option<u64>,
+}
+
+#[derive(Serialize)]
+enum Status {
+    Active,
+    Suspended { reason: String },
+}
+
+fn records(count: usize) -> Vec<Record> {
+    (0..count as u64)
+        .map(|id| Record {
+            id,
+            name: format!("record {id}"),
+            tags: vec!["alpha", "beta", "gamma"],
+            attributes: [("x".to_string(), id as f64), ("y".to_string(), 0.5)].into(),
+            status: if id % 2 == 0 { Status::Active } else { Status::Suspended { reason: "quota".to_string() } },
+            parent: id.checked_sub(1),
+        })
+        .collect()
+}
+
+/// The best time of a few runs of `f`
+fn bench(name: &str, count: usize, mut f: impl FnMut()) {
+    let best = (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or(Duration::ZERO);
+    let per_record = best.as_nanos() / count.max(1) as u128;
+    println!("{name:<24} {best:>12.2?} {per_record:>8} ns/record");
+}
+
+fn main() {
+    let count = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse().ok())
+        .unwrap_or(100_000);
+    let records = records(count);
+    println!("capturing {count} records");
+
+    bench("to_value", count, || {
+        let values: Vec<_> = records.iter().map(|record| serde_value::to_value(record).unwrap()).collect();
+        black_box(values);
+    });
+
+    bench("Arena::capture", count, || {
+        let mut arena = Arena::new();
+        let ids: Vec<_> = records.iter().map(|record| arena.capture(record).unwrap()).collect();
+        black_box((ids, arena));
+    });
+
+    let mut arena = Arena::new();
+    bench("Arena::capture (reused)", count, || {
+        arena.clear();
+        let ids: Vec<_> = records.iter().map(|record| arena.capture(record).unwrap()).collect();
+        black_box(ids);
+    });
+
+    arena.clear();
+    let ids: Vec<_> = records.iter().map(|record| arena.capture(record).unwrap()).collect();
+    bench("ArenaValue::to_value", count, || {
+        let values: Vec<_> = ids.iter().map(|id| arena.get(*id).to_value()).collect();
+        black_box(values);
+    });
+}
@@ -0,0 +1,482 @@
+//! Arena allocation of captured values
+//!
+//! Every `Vec` and `BTreeMap` of a [`Value`] is a separate heap allocation, which adds up when
+//! capturing hundreds of thousands of records. An [`Arena`] stores the nodes of any number of
+//! captured values in a few flat buffers instead, which grow by doubling and are reused after
+//! [`Arena::clear`]. Values are captured straight into the arena with [`Arena::capture`], or
+//! copied from a `Value` with [`Arena::alloc`], and read back as [`ArenaValue`] views.
+//!
+//! Unlike `Value` maps and named fields, arena maps and fields keep their serialization order
+//! and duplicates, which are sorted and deduplicated by [`ArenaValue::to_value`].
+//!
+//! ```
+//! use serde_derive::Serialize;
+//! use serde_value::arena::{Arena, ArenaValue};
+//!
+//! #[derive(Serialize)]
+//! struct Hit {
+//!     path: &'static str,
+//!     status: u16,
+//! }
+//!
+//! let mut arena = Arena::new();
+//! let ids: Vec<_> = (0..3).map(|_| arena.capture(&Hit { path: "/", status: 200 }).unwrap()).collect();
+//!
+//! let ArenaValue::NamedStruct(hit) = arena.get(ids[2]) else { panic!() };
+//! assert_eq!(hit.name, "Hit");
+//! assert!(matches!(hit.fields.get("path"), Some(ArenaValue::String("/"))));
+//! assert_eq!(arena.get(ids[0]).to_value(), serde_value::to_value(&Hit { path: "/", status: 200 }).unwrap());
+//! ```
+
+mod ser;
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use serde::Serialize;
+use crate::ser::Error;
+use crate::value::{NamedStruct, NamedVariant, Number, TupleStruct, TupleVariant, UnitStruct, UnitVariant, Value};
+
+pub use ser::{ArenaCompound, ArenaSerializer};
+
+/// The handle of a value stored in an [`Arena`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Hash)]
+pub struct ArenaId(u32);
+
+/// A range of one of the arena buffers
+#[derive(Copy, Clone, Debug)]
+struct Span {
+    start: u32,
+    len: u32,
+}
+
+impl Span {
+    fn range(self) -> std::ops::Range<usize> {
+        self.start as usize..(self.start + self.len) as usize
+    }
+}
+
+/// A stored node, whose children are spans of `Arena::children` or `Arena::fields`
+#[derive(Copy, Clone, Debug)]
+enum Node {
+    Unit,
+    Bool(bool),
+    Char(char),
+    Number(Number),
+    String(Span),
+    Seq(Span),
+    /// Keys and values, interleaved
+    Map(Span),
+    Tuple(Span),
+    UnitStruct(&'static str),
+    TupleStruct(&'static str, Span),
+    NamedStruct(&'static str, Span),
+    UnitVariant(&'static str, &'static str),
+    TupleVariant(&'static str, &'static str, Span),
+    NamedVariant(&'static str, &'static str, Span),
+}
+
+/// A bump-style store of captured values
+#[derive(Clone, Debug, Default)]
+pub struct Arena {
+    nodes: Vec<Node>,
+    children: Vec<u32>,
+    fields: Vec<(&'static str, u32)>,
+    text: String,
+    /// The children of the compounds being serialized
+    pending: Vec<u32>,
+    /// The field names of the structs being serialized, aligned with the end of `pending`
+    pending_names: Vec<&'static str>,
+}
+
+/// Converts a buffer length into an index
+///
+/// # Panics
+/// if an arena buffer outgrows `u32`
+fn index(len: usize) -> u32 {
+    u32::try_from(len).expect("arena buffers are limited to u32::MAX entries")
+}
+
+impl Arena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of nodes stored
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Removes every value, keeping the allocated buffers for the next captures
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.children.clear();
+        self.fields.clear();
+        self.text.clear();
+        self.pending.clear();
+        self.pending_names.clear();
+    }
+
+    /// Captures `value` into the arena, as [`to_value`](crate::to_value) would. If serialization
+    /// fails, the nodes captured so far are removed and the arena is left as it was
+    pub fn capture<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<ArenaId, Error> {
+        let nodes = self.nodes.len();
+        let children = self.children.len();
+        let fields = self.fields.len();
+        let text = self.text.len();
+        let pending = (self.pending.len(), self.pending_names.len());
+        let result = value.serialize(&mut ArenaSerializer { arena: self });
+        if result.is_err() {
+            self.nodes.truncate(nodes);
+            self.children.truncate(children);
+            self.fields.truncate(fields);
+            self.text.truncate(text);
+            self.pending.truncate(pending.0);
+            self.pending_names.truncate(pending.1);
+        }
+        result
+    }
+
+    /// Copies `value` into the arena
+    pub fn alloc(&mut self, value: &Value) -> ArenaId {
+        match value {
+            Value::Unit => self.push(Node::Unit),
+            Value::Bool(val) => self.push(Node::Bool(*val)),
+            Value::Char(val) => self.push(Node::Char(*val)),
+            Value::Number(val) => self.push(Node::Number(*val)),
+            Value::String(val) => self.push_str(val),
+            Value::Seq(values) => {
+                let span = self.alloc_elements(values);
+                self.push(Node::Seq(span))
+            }
+            Value::Map(map) => {
+                let ids: Vec<_> = map.iter()
+                    .flat_map(|(key, value)| [self.alloc(key).0, self.alloc(value).0])
+                    .collect();
+                let span = self.push_children(ids);
+                self.push(Node::Map(span))
+            }
+            Value::Tuple(values) => {
+                let span = self.alloc_elements(values);
+                self.push(Node::Tuple(span))
+            }
+            Value::UnitStruct(val) => self.push(Node::UnitStruct(val.name)),
+            Value::TupleStruct(val) => {
+                let span = self.alloc_elements(&val.values);
+                self.push(Node::TupleStruct(val.name, span))
+            }
+            Value::NamedStruct(val) => {
+                let span = self.alloc_fields(&val.fields);
+                self.push(Node::NamedStruct(val.name, span))
+            }
+            Value::UnitVariant(val) => self.push(Node::UnitVariant(val.name, val.variant)),
+            Value::TupleVariant(val) => {
+                let span = self.alloc_elements(&val.values);
+                self.push(Node::TupleVariant(val.name, val.variant, span))
+            }
+            Value::NamedVariant(val) => {
+                let span = self.alloc_fields(&val.fields);
+                self.push(Node::NamedVariant(val.name, val.variant, span))
+            }
+        }
+    }
+
+    fn alloc_elements(&mut self, values: &[Value]) -> Span {
+        let ids: Vec<_> = values.iter().map(|value| self.alloc(value).0).collect();
+        self.push_children(ids)
+    }
+
+    fn alloc_fields(&mut self, fields: &BTreeMap<&'static str, Value>) -> Span {
+        let fields: Vec<_> = fields.iter().map(|(name, value)| (*name, self.alloc(value).0)).collect();
+        let start = index(self.fields.len());
+        self.fields.extend(fields);
+        Span { start, len: index(self.fields.len()) - start }
+    }
+
+    fn push(&mut self, node: Node) -> ArenaId {
+        self.nodes.push(node);
+        ArenaId(index(self.nodes.len() - 1))
+    }
+
+    fn push_str(&mut self, s: &str) -> ArenaId {
+        let start = index(self.text.len());
+        self.text.push_str(s);
+        self.push(Node::String(Span { start, len: index(s.len()) }))
+    }
+
+    fn push_children(&mut self, ids: impl IntoIterator<Item=u32>) -> Span {
+        let start = index(self.children.len());
+        self.children.extend(ids);
+        Span { start, len: index(self.children.len()) - start }
+    }
+
+    /// The value of `id`
+    ///
+    /// # Panics
+    /// if `id` was not returned by this arena since it was last cleared
+    pub fn get(&self, id: ArenaId) -> ArenaValue<'_> {
+        let elements = |span: Span| Elements { arena: self, ids: &self.children[span.range()] };
+        let fields = |span: Span| Fields { arena: self, fields: &self.fields[span.range()] };
+
+        match self.nodes[id.0 as usize] {
+            Node::Unit => ArenaValue::Unit,
+            Node::Bool(val) => ArenaValue::Bool(val),
+            Node::Char(val) => ArenaValue::Char(val),
+            Node::Number(val) => ArenaValue::Number(val),
+            Node::String(span) => ArenaValue::String(&self.text[span.range()]),
+            Node::Seq(span) => ArenaValue::Seq(elements(span)),
+            Node::Map(span) => ArenaValue::Map(Entries { arena: self, ids: &self.children[span.range()] }),
+            Node::Tuple(span) => ArenaValue::Tuple(elements(span)),
+            Node::UnitStruct(name) => ArenaValue::UnitStruct(UnitStruct { name }),
+            Node::TupleStruct(name, span) => ArenaValue::TupleStruct(TupleStruct { name, values: elements(span) }),
+            Node::NamedStruct(name, span) => ArenaValue::NamedStruct(NamedStruct { name, fields: fields(span) }),
+            Node::UnitVariant(name, variant) => ArenaValue::UnitVariant(UnitVariant { name, variant }),
+            Node::TupleVariant(name, variant, span) => {
+                ArenaValue::TupleVariant(TupleVariant { name, variant, values: elements(span) })
+            }
+            Node::NamedVariant(name, variant, span) => {
+                ArenaValue::NamedVariant(NamedVariant { name, variant, fields: fields(span) })
+            }
+        }
+    }
+}
+
+/// A value stored in an [`Arena`], borrowing its strings and children from it
+#[derive(Copy, Clone, Debug)]
+pub enum ArenaValue<'arena> {
+    Unit,
+    Bool(bool),
+    Char(char),
+    Number(Number),
+    String(&'arena str),
+    Seq(Elements<'arena>),
+    Map(Entries<'arena>),
+    Tuple(Elements<'arena>),
+    UnitStruct(UnitStruct),
+    TupleStruct(TupleStruct<Elements<'arena>>),
+    NamedStruct(NamedStruct<Fields<'arena>>),
+    UnitVariant(UnitVariant),
+    TupleVariant(TupleVariant<Elements<'arena>>),
+    NamedVariant(NamedVariant<Fields<'arena>>),
+}
+
+impl ArenaValue<'_> {
+    /// Copies this value out of the arena
+    pub fn to_value(&self) -> Value {
+        let elements = |values: &Elements<'_>| values.iter().map(|value| value.to_value()).collect();
+        let fields = |fields: &Fields<'_>| fields.iter().map(|(name, value)| (name, value.to_value())).collect();
+
+        match self {
+            ArenaValue::Unit => Value::Unit,
+            ArenaValue::Bool(val) => Value::Bool(*val),
+            ArenaValue::Char(val) => Value::Char(*val),
+            ArenaValue::Number(val) => Value::Number(*val),
+            ArenaValue::String(val) => Value::from(*val),
+            ArenaValue::Seq(values) => Value::Seq(elements(values)),
+            ArenaValue::Map(entries) => Value::Map(entries.iter()
+                .map(|(key, value)| (key.to_value(), value.to_value()))
+                .collect()),
+            ArenaValue::Tuple(values) => Value::Tuple(elements(values)),
+            ArenaValue::UnitStruct(val) => Value::UnitStruct(*val),
+            ArenaValue::TupleStruct(val) => Value::TupleStruct(TupleStruct { name: val.name, values: elements(&val.values) }),
+            ArenaValue::NamedStruct(val) => Value::NamedStruct(NamedStruct { name: val.name, fields: fields(&val.fields) }),
+            ArenaValue::UnitVariant(val) => Value::UnitVariant(*val),
+            ArenaValue::TupleVariant(val) => Value::TupleVariant(TupleVariant {
+                name: val.name,
+                variant: val.variant,
+                values: elements(&val.values),
+            }),
+            ArenaValue::NamedVariant(val) => Value::NamedVariant(NamedVariant {
+                name: val.name,
+                variant: val.variant,
+                fields: fields(&val.fields),
+            }),
+        }
+    }
+}
+
+/// The elements of a sequence, tuple, tuple struct or tuple variant in an [`Arena`]
+#[derive(Copy, Clone)]
+pub struct Elements<'arena> {
+    arena: &'arena Arena,
+    ids: &'arena [u32],
+}
+
+impl<'arena> Elements<'arena> {
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<ArenaValue<'arena>> {
+        self.ids.get(index).map(|id| self.arena.get(ArenaId(*id)))
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item=ArenaValue<'arena>> + 'arena {
+        let arena = self.arena;
+        self.ids.iter().map(move |id| arena.get(ArenaId(*id)))
+    }
+}
+
+impl Debug for Elements<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+/// The entries of a map in an [`Arena`], in serialization order
+#[derive(Copy, Clone)]
+pub struct Entries<'arena> {
+    arena: &'arena Arena,
+    ids: &'arena [u32],
+}
+
+impl<'arena> Entries<'arena> {
+    pub fn len(&self) -> usize {
+        self.ids.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item=(ArenaValue<'arena>, ArenaValue<'arena>)> + 'arena {
+        let arena = self.arena;
+        self.ids.chunks_exact(2).map(move |pair| (arena.get(ArenaId(pair[0])), arena.get(ArenaId(pair[1]))))
+    }
+}
+
+impl Debug for Entries<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+/// The fields of a named struct or variant in an [`Arena`], in serialization order
+#[derive(Copy, Clone)]
+pub struct Fields<'arena> {
+    arena: &'arena Arena,
+    fields: &'arena [(&'static str, u32)],
+}
+
+impl<'arena> Fields<'arena> {
+    pub fn len(&self) -> usize {
+        self.fields.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fields.is_empty()
+    }
+
+    /// The value of the last field named `name`
+    pub fn get(&self, name: &str) -> Option<ArenaValue<'arena>> {
+        self.fields.iter()
+            .rev()
+            .find(|(field, _)| *field == name)
+            .map(|(_, id)| self.arena.get(ArenaId(*id)))
+    }
+
+    pub fn iter(&self) -> impl ExactSizeIterator<Item=(&'static str, ArenaValue<'arena>)> + 'arena {
+        let arena = self.arena;
+        self.fields.iter().map(move |(name, id)| (*name, arena.get(ArenaId(*id))))
+    }
+}
+
+impl Debug for Fields<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[test]
+fn test_arena() {
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize)]
+    struct Record {
+        id: u64,
+        name: String,
+        tags: Vec<&'static str>,
+        scores: BTreeMap<String, (u8, char)>,
+        kind: Kind,
+        parent: Option<Box<Record>>,
+        raw: serde_bytes_like::Bytes,
+    }
+
+    #[derive(Serialize)]
+    enum Kind {
+        Leaf,
+        Weighted(f32),
+        Linked { to: u64 },
+    }
+
+    mod serde_bytes_like {
+        pub struct Bytes(pub Vec<u8>);
+
+        impl serde::Serialize for Bytes {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(&self.0)
+            }
+        }
+    }
+
+    let record = |id, kind| Record {
+        id,
+        name: format!("record {id}"),
+        tags: vec!["a", "b"],
+        scores: [("x".to_string(), (1, 'y'))].into(),
+        kind,
+        parent: Some(Box::new(Record {
+            id: 0,
+            name: String::new(),
+            tags: vec![],
+            scores: BTreeMap::new(),
+            kind: Kind::Leaf,
+            parent: None,
+            raw: serde_bytes_like::Bytes(vec![]),
+        })),
+        raw: serde_bytes_like::Bytes(vec![1, 2]),
+    };
+
+    let mut arena = Arena::new();
+    let first = arena.capture(&record(1, Kind::Weighted(0.5))).unwrap();
+    let second = arena.capture(&record(2, Kind::Linked { to: 1 })).unwrap();
+    assert_eq!(arena.get(first).to_value(), to_value(&record(1, Kind::Weighted(0.5))).unwrap());
+    assert_eq!(arena.get(second).to_value(), to_value(&record(2, Kind::Linked { to: 1 })).unwrap());
+
+    let ArenaValue::NamedStruct(NamedStruct { fields, .. }) = arena.get(second) else { panic!() };
+    assert_eq!(fields.len(), 7);
+    assert!(matches!(fields.get("name"), Some(ArenaValue::String("record 2"))));
+    let Some(ArenaValue::Seq(tags)) = fields.get("tags") else { panic!() };
+    assert!(matches!(tags.get(1), Some(ArenaValue::String("b"))));
+    assert!(matches!(fields.get("kind"), Some(ArenaValue::NamedVariant(NamedVariant { variant: "Linked", .. }))));
+
+    let value = to_value(&record(3, Kind::Leaf)).unwrap();
+    let copied = arena.alloc(&value);
+    assert_eq!(arena.get(copied).to_value(), value);
+    assert!(arena.pending.is_empty() && arena.pending_names.is_empty());
+
+    struct Failing;
+    impl serde::Serialize for Failing {
+        fn serialize<S: serde::Serializer>(&self, _: S) -> Result<S::Ok, S::Error> {
+            Err(serde::ser::Error::custom("cannot capture"))
+        }
+    }
+    let before = format!("{arena:?}");
+    assert!(arena.capture(&("partial", vec![1u8, 2], Failing)).is_err());
+    assert_eq!(format!("{arena:?}"), before);
+
+    arena.clear();
+    assert!(arena.is_empty());
+    let leaf = arena.capture(&record(4, Kind::Leaf)).unwrap();
+    let mut copy = Arena::new();
+    copy.alloc(&arena.get(leaf).to_value());
+    assert_eq!(arena.len(), copy.len());
+}
@@ -0,0 +1,323 @@
+use serde::{ser, Serialize};
+use crate::ser::Error;
+use super::{index, Arena, ArenaId, Node, Span};
+
+/// A serializer capturing values into an [`Arena`], see [`Arena::capture`]
+pub struct ArenaSerializer<'arena> {
+    pub(super) arena: &'arena mut Arena,
+}
+
+impl<'arena> ArenaSerializer<'arena> {
+    pub fn new(arena: &'arena mut Arena) -> Self {
+        ArenaSerializer { arena }
+    }
+}
+
+/// The kind of compound being serialized
+enum Compound {
+    Seq,
+    Tuple,
+    Map,
+    TupleStruct(&'static str),
+    TupleVariant(&'static str, &'static str),
+    Struct(&'static str),
+    StructVariant(&'static str, &'static str),
+}
+
+/// The state of every compound serialized into an [`Arena`]
+///
+/// The ids of the children are kept on the arena's pending stack until the compound ends, when
+/// they are moved to its children or fields in a single contiguous span.
+pub struct ArenaCompound<'s, 'arena> {
+    serializer: &'s mut ArenaSerializer<'arena>,
+    start: usize,
+    names_start: usize,
+    kind: Compound,
+}
+
+impl<'s, 'arena> ArenaCompound<'s, 'arena> {
+    fn new(serializer: &'s mut ArenaSerializer<'arena>, kind: Compound) -> Self {
+        let start = serializer.arena.pending.len();
+        let names_start = serializer.arena.pending_names.len();
+        ArenaCompound { serializer, start, names_start, kind }
+    }
+
+    fn push<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        let id = value.serialize(&mut *self.serializer)?;
+        self.serializer.arena.pending.push(id.0);
+        Ok(())
+    }
+
+    fn push_field<T: ?Sized + Serialize>(&mut self, name: &'static str, value: &T) -> Result<(), Error> {
+        self.push(value)?;
+        self.serializer.arena.pending_names.push(name);
+        Ok(())
+    }
+
+    fn end(self) -> Result<ArenaId, Error> {
+        let arena = &mut *self.serializer.arena;
+        let node = match self.kind {
+            Compound::Struct(_) | Compound::StructVariant(..) => {
+                let start = index(arena.fields.len());
+                let names = arena.pending_names.drain(self.names_start..);
+                arena.fields.extend(names.zip(arena.pending.drain(self.start..)));
+                let span = Span { start, len: index(arena.fields.len()) - start };
+                match self.kind {
+                    Compound::Struct(name) => Node::NamedStruct(name, span),
+                    Compound::StructVariant(name, variant) => Node::NamedVariant(name, variant, span),
+                    _ => unreachable!(),
+                }
+            }
+            kind => {
+                let start = index(arena.children.len());
+                arena.children.extend(arena.pending.drain(self.start..));
+                let span = Span { start, len: index(arena.children.len()) - start };
+                match kind {
+                    Compound::Seq => Node::Seq(span),
+                    Compound::Tuple => Node::Tuple(span),
+                    Compound::Map => Node::Map(span),
+                    Compound::TupleStruct(name) => Node::TupleStruct(name, span),
+                    Compound::TupleVariant(name, variant) => Node::TupleVariant(name, variant, span),
+                    _ => unreachable!(),
+                }
+            }
+        };
+        Ok(arena.push(node))
+    }
+}
+
+impl<'s, 'arena> ser::Serializer for &'s mut ArenaSerializer<'arena> {
+    type Ok = ArenaId;
+    type Error = Error;
+    type SerializeSeq = ArenaCompound<'s, 'arena>;
+    type SerializeTuple = ArenaCompound<'s, 'arena>;
+    type SerializeTupleStruct = ArenaCompound<'s, 'arena>;
+    type SerializeTupleVariant = ArenaCompound<'s, 'arena>;
+    type SerializeMap = ArenaCompound<'s, 'arena>;
+    type SerializeStruct = ArenaCompound<'s, 'arena>;
+    type SerializeStructVariant = ArenaCompound<'s, 'arena>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Bool(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Number(v.into())))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Char(v)))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push_str(v))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, Self::Error> {
+        let start = self.arena.nodes.len();
+        for byte in v {
+            self.arena.push(Node::Number((*byte).into()));
+        }
+        let span = self.arena.push_children((start..self.arena.nodes.len()).map(index));
+        Ok(self.arena.push(Node::Seq(span)))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::UnitStruct("None")))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok, Self::Error> where T: ?Sized + Serialize {
+        self.serialize_newtype_struct("Some", value)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::Unit))
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::UnitStruct(name)))
+    }
+
+    fn serialize_unit_variant(self, name: &'static str, _variant_index: u32, variant: &'static str) -> Result<Self::Ok, Self::Error> {
+        Ok(self.arena.push(Node::UnitVariant(name, variant)))
+    }
+
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<Self::Ok, Self::Error> where T: ?Sized + Serialize {
+        let mut compound = ArenaCompound::new(self, Compound::TupleStruct(name));
+        compound.push(value)?;
+        compound.end()
+    }
+
+    fn serialize_newtype_variant<T>(self, name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<Self::Ok, Self::Error> where T: ?Sized + Serialize {
+        let mut compound = ArenaCompound::new(self, Compound::TupleVariant(name, variant));
+        compound.push(value)?;
+        compound.end()
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Ok(ArenaCompound::new(self, Compound::Seq))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Ok(ArenaCompound::new(self, Compound::Tuple))
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Ok(ArenaCompound::new(self, Compound::TupleStruct(name)))
+    }
+
+    fn serialize_tuple_variant(self, name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Ok(ArenaCompound::new(self, Compound::TupleVariant(name, variant)))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Ok(ArenaCompound::new(self, Compound::Map))
+    }
+
+    fn serialize_struct(self, name: &'static str, _len: usize) -> Result<Self::SerializeStruct, Self::Error> {
+        Ok(ArenaCompound::new(self, Compound::Struct(name)))
+    }
+
+    fn serialize_struct_variant(self, name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Ok(ArenaCompound::new(self, Compound::StructVariant(name, variant)))
+    }
+}
+
+impl ser::SerializeSeq for ArenaCompound<'_, '_> {
+    type Ok = ArenaId;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error> where T: ?Sized + Serialize {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ArenaCompound::end(self)
+    }
+}
+
+impl ser::SerializeTuple for ArenaCompound<'_, '_> {
+    type Ok = ArenaId;
+    type Error = Error;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error> where T: ?Sized + Serialize {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ArenaCompound::end(self)
+    }
+}
+
+impl ser::SerializeTupleStruct for ArenaCompound<'_, '_> {
+    type Ok = ArenaId;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error> where T: ?Sized + Serialize {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ArenaCompound::end(self)
+    }
+}
+
+impl ser::SerializeTupleVariant for ArenaCompound<'_, '_> {
+    type Ok = ArenaId;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error> where T: ?Sized + Serialize {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ArenaCompound::end(self)
+    }
+}
+
+impl ser::SerializeMap for ArenaCompound<'_, '_> {
+    type Ok = ArenaId;
+    type Error = Error;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error> where T: ?Sized + Serialize {
+        self.push(key)
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error> where T: ?Sized + Serialize {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ArenaCompound::end(self)
+    }
+}
+
+impl ser::SerializeStruct for ArenaCompound<'_, '_> {
+    type Ok = ArenaId;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error> where T: ?Sized + Serialize {
+        self.push_field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ArenaCompound::end(self)
+    }
+}
+
+impl ser::SerializeStructVariant for ArenaCompound<'_, '_> {
+    type Ok = ArenaId;
+    type Error = Error;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error> where T: ?Sized + Serialize {
+        self.push_field(key, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, Self::Error> {
+        ArenaCompound::end(self)
+    }
+}
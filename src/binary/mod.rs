@@ -0,0 +1,450 @@
+//! Binary encoding of [`Value`] trees
+//!
+//! [`Value::to_binary`] encodes a value as a compact, self-describing byte string, which keeps
+//! every detail of the value, unlike [JSON](crate::json): number kinds, characters, tuples and
+//! the names of structs and enums. [`Value::from_binary`] decodes it, with the names it may
+//! hold in a [`SymbolTable`](crate::SymbolTable).
+//!
+//! An encoding starts with the four bytes `SVB\x01`, followed by the encoded value. Lengths,
+//! counts and characters are unsigned LEB128 varints, names and strings are a varint length
+//! followed by UTF-8 bytes. Each value starts with a tag byte:
+//!
+//! | tag | value | followed by |
+//! |-----|-------|-------------|
+//! | 0 | `Unit` | |
+//! | 1, 2 | `Bool` `false`, `true` | |
+//! | 3 | `Char` | the character as a varint |
+//! | 4 | `Number` | the kind index in [`NumberKind::ALL`](crate::NumberKind::ALL), then the little-endian bytes |
+//! | 5 | `String` | the string |
+//! | 6 | `Seq` | the count, then the elements |
+//! | 7 | `Map` | the count, then each key followed by its value |
+//! | 8 | `Tuple` | the count, then the elements |
+//! | 9 | `UnitStruct` | the name |
+//! | 10 | `TupleStruct` | the name, the count, then the elements |
+//! | 11 | `NamedStruct` | the name, the count, then each field name followed by its value |
+//! | 12 | `UnitVariant` | the enum name and the variant name |
+//! | 13 | `TupleVariant` | the enum name, the variant name, the count, then the elements |
+//! | 14 | `NamedVariant` | the enum name, the variant name, the count, then the fields |
+//!
+//! ```
+//! use serde_value::Value;
+//!
+//! let bytes = Value::Seq(vec![Value::from(true), Value::from("hi")]).to_binary();
+//! assert_eq!(bytes, b"SVB\x01\x06\x02\x02\x05\x02hi");
+//! ```
+//!
+//! With a [name table](BinaryOptions::name_table), the encoding starts with `SVB\x02`, followed
+//! by the count of distinct names, each name once, then the encoded value, in which every name
+//! is the varint index of the name in the table. Values with many structs and enums of the same
+//! types are smaller.
+//!
+//! ```
+//! use serde_value::{SymbolTable, Value};
+//! use serde_value::binary::BinaryOptions;
+//!
+//! let value = serde_value::to_value(&vec![Some(1u8), Some(2), None]).unwrap();
+//! let options = BinaryOptions { name_table: true, ..BinaryOptions::default() };
+//! let bytes = value.to_binary_with(&options);
+//! assert_eq!(bytes[..15], *b"SVB\x02\x02\x04Some\x04None");
+//! assert!(bytes.len() < value.to_binary().len());
+//!
+//! let symbols: SymbolTable = ["None", "Some"].into_iter().collect();
+//! assert_eq!(Value::from_binary(&bytes, &symbols).unwrap(), value);
+//! ```
+//!
+//! With [shared subtrees](BinaryOptions::shared_subtrees), the version is 3, or 4 with a name
+//! table, and identical subtrees are written once. Strings and compound values, tags 5 to 14,
+//! are numbered from 0 in the order they start, and a value identical to one written before is
+//! written as the tag 15 followed by the varint number of that value. [`SharedValue`]s are
+//! written with their shared nodes as references, other values are
+//! [deduplicated](crate::dedup) first, and [`SharedValue::from_binary`] reads references back as
+//! shared nodes.
+//!
+//! ```
+//! use serde_value::{SharedValue, SymbolTable, Value};
+//! use serde_value::binary::BinaryOptions;
+//!
+//! let value = Value::from(vec!["a long string"; 100]);
+//! let options = BinaryOptions { shared_subtrees: true, ..BinaryOptions::default() };
+//! let bytes = value.to_binary_with(&options);
+//! assert_eq!(bytes[..8], *b"SVB\x03\x06\x64\x05\x0d");
+//! assert_eq!(bytes[21..], *[15, 1].repeat(99));
+//!
+//! let shared = SharedValue::from_binary(&bytes, &SymbolTable::new()).unwrap();
+//! assert!(SharedValue::ptr_eq(&shared[0], &shared[99]));
+//! assert_eq!(Value::from_binary(&bytes, &SymbolTable::new()).unwrap(), value);
+//! ```
+
+mod read;
+mod shared;
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::io::{Result, Write};
+use crate::value::{Number, SharedValue, SymbolTable, Value};
+
+/// The bytes starting every encoding with inline names, ending with the format version
+pub const MAGIC: &[u8; 4] = b"SVB\x01";
+
+/// The bytes starting every encoding with a name table, ending with the format version
+pub const MAGIC_TABLE: &[u8; 4] = b"SVB\x02";
+
+/// The tag of a reference to a value written before, with shared subtrees
+const SHARED_TAG: u8 = 15;
+
+/// How values are encoded
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct BinaryOptions {
+    /// Writes every distinct struct, field, enum and variant name once, in a table ahead of the
+    /// value, and refers to names by their index in the table
+    pub name_table: bool,
+    /// Writes identical strings and compound values once, and refers to them afterwards.
+    ///
+    /// A [`Value`] is first [deduplicated](Value::dedup) in full, which builds a second tree in
+    /// memory before the first byte is written, so such output is not incremental, even through
+    /// a [`StreamWriter`](crate::stream::StreamWriter). A [`SharedValue`] is written as it is.
+    pub shared_subtrees: bool,
+}
+
+impl BinaryOptions {
+    /// The header of encodings with these options: the magic bytes, ending with the version
+    fn header(&self) -> [u8; 4] {
+        let mut header = *MAGIC;
+        header[3] += self.name_table as u8 + 2 * self.shared_subtrees as u8;
+        header
+    }
+
+    /// The options of an encoding with `header`, if it is a known version
+    fn from_header(header: &[u8]) -> Option<Self> {
+        let version = match header {
+            [b'S', b'V', b'B', version @ 1..=4] => version - 1,
+            _ => return None,
+        };
+        Some(BinaryOptions { name_table: version & 1 != 0, shared_subtrees: version & 2 != 0 })
+    }
+}
+
+/// An error produced while reading the binary encoding
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BinaryError {
+    /// The offset of the error in bytes
+    pub offset: usize,
+    pub message: String,
+}
+
+impl std::error::Error for BinaryError {}
+
+impl Display for BinaryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.offset)
+    }
+}
+
+impl Value {
+    /// Encodes this value in the binary format, with inline names
+    pub fn to_binary(&self) -> Vec<u8> {
+        self.to_binary_with(&BinaryOptions::default())
+    }
+
+    /// Encodes this value in the binary format
+    pub fn to_binary_with(&self, options: &BinaryOptions) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write_binary(&mut bytes, self, options).expect("writing to a Vec cannot fail");
+        bytes
+    }
+
+    /// Decodes a value written by [`Value::to_binary`] or [`Value::to_binary_with`].
+    ///
+    /// Names are `&'static str`s, which are looked up in `symbols` rather than copied, so that
+    /// reading untrusted input does not grow memory, and decoding fails on names missing from
+    /// `symbols`.
+    ///
+    /// With shared subtrees, each reference is decoded as a copy of the value it refers to, and
+    /// references within copied values are copied as well. Decoding fails once the value
+    /// exceeds 2<sup>24</sup> nodes and string bytes, or the length of the input if larger.
+    /// [`SharedValue::from_binary`] keeps the subtrees shared and has no such limit.
+    ///
+    /// ```
+    /// use serde_value::{SymbolTable, Value};
+    ///
+    /// let symbols = SymbolTable::new();
+    /// let err = Value::from_binary(b"SVB\x01\x04\x02\x01", &symbols).unwrap_err();
+    /// assert_eq!(err.to_string(), "unexpected end of input at byte 7");
+    /// let err = Value::from_binary(b"SVB\x01\x09\x04None", &symbols).unwrap_err();
+    /// assert_eq!(err.to_string(), "unknown name at byte 5");
+    /// ```
+    pub fn from_binary(bytes: &[u8], symbols: &SymbolTable) -> std::result::Result<Value, BinaryError> {
+        Ok(read::Reader::new(bytes, symbols).expanding().read_document()?.into_value())
+    }
+}
+
+impl SharedValue {
+    /// Encodes this value in the binary format, with inline names
+    pub fn to_binary(&self) -> Vec<u8> {
+        self.to_binary_with(&BinaryOptions::default())
+    }
+
+    /// Encodes this value in the binary format. With [`BinaryOptions::shared_subtrees`], the
+    /// nodes shared within this value are written once, and identical nodes that are not shared
+    /// are written in full.
+    pub fn to_binary_with(&self, options: &BinaryOptions) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        shared::write_shared(&mut bytes, self, options).expect("writing to a Vec cannot fail");
+        bytes
+    }
+
+    /// Decodes a value as [`Value::from_binary`] does, with the subtrees written once as
+    /// shared nodes. Shared nodes still count towards the nesting limit of 128 levels at each
+    /// place they appear in, as walking or dropping the value descends into them there
+    pub fn from_binary(bytes: &[u8], symbols: &SymbolTable) -> std::result::Result<SharedValue, BinaryError> {
+        read::Reader::new(bytes, symbols).read_document()
+    }
+}
+
+/// Writes the binary encoding of `value` to `w`, starting with its header. Values are written as
+/// they are walked, except with shared subtrees, where the whole value is deduplicated first
+pub(crate) fn write_binary<W: Write + ?Sized>(w: &mut W, value: &Value, options: &BinaryOptions) -> Result<()> {
+    if options.shared_subtrees {
+        return shared::write_shared(w, &value.dedup().root, options);
+    }
+    let names = options.name_table.then(|| {
+        let mut names = SymbolTable::new();
+        names.collect(value);
+        names
+    });
+    write_header(w, options, names.as_ref())?;
+    write_value(w, value, names.as_ref())
+}
+
+/// Writes the header for `options`, followed by the name table if there is one
+fn write_header<W: Write + ?Sized>(w: &mut W, options: &BinaryOptions, names: Option<&SymbolTable>) -> Result<()> {
+    w.write_all(&options.header())?;
+    if let Some(names) = names {
+        write_varint(w, names.len() as u64)?;
+        names.names().iter().try_for_each(|name| write_str(w, name))?;
+    }
+    Ok(())
+}
+
+/// Writes a name inline, or as its index in `names`
+fn write_name<W: Write + ?Sized>(w: &mut W, name: &str, names: Option<&SymbolTable>) -> Result<()> {
+    match names {
+        Some(names) => write_varint(w, u64::from(names.get(name).expect("the names are collected").id())),
+        None => write_str(w, name),
+    }
+}
+
+pub(crate) fn write_varint<W: Write + ?Sized>(w: &mut W, mut n: u64) -> Result<()> {
+    loop {
+        let byte = (n & 0x7f) as u8;
+        n >>= 7;
+        if n == 0 {
+            return w.write_all(&[byte]);
+        }
+        w.write_all(&[byte | 0x80])?;
+    }
+}
+
+pub(crate) fn write_str<W: Write + ?Sized>(w: &mut W, s: &str) -> Result<()> {
+    write_varint(w, s.len() as u64)?;
+    w.write_all(s.as_bytes())
+}
+
+pub(crate) fn write_number<W: Write + ?Sized>(w: &mut W, number: &Number) -> Result<()> {
+    let kind = number.kind() as u8;
+    w.write_all(&[kind])?;
+    match number {
+        Number::U8(v) => w.write_all(&v.to_le_bytes()),
+        Number::U16(v) => w.write_all(&v.to_le_bytes()),
+        Number::U32(v) => w.write_all(&v.to_le_bytes()),
+        Number::U64(v) => w.write_all(&v.to_le_bytes()),
+        Number::U128(v) => w.write_all(&v.to_le_bytes()),
+        Number::I8(v) => w.write_all(&v.to_le_bytes()),
+        Number::I16(v) => w.write_all(&v.to_le_bytes()),
+        Number::I32(v) => w.write_all(&v.to_le_bytes()),
+        Number::I64(v) => w.write_all(&v.to_le_bytes()),
+        Number::I128(v) => w.write_all(&v.to_le_bytes()),
+        Number::F32(v) => w.write_all(&v.0.to_le_bytes()),
+        Number::F64(v) => w.write_all(&v.0.to_le_bytes()),
+    }
+}
+
+fn write_elements<W: Write + ?Sized>(w: &mut W, values: &[Value], names: Option<&SymbolTable>) -> Result<()> {
+    write_varint(w, values.len() as u64)?;
+    values.iter().try_for_each(|value| write_value(w, value, names))
+}
+
+fn write_fields<W: Write + ?Sized>(w: &mut W, fields: &BTreeMap<&'static str, Value>, names: Option<&SymbolTable>) -> Result<()> {
+    write_varint(w, fields.len() as u64)?;
+    for (name, value) in fields {
+        write_name(w, name, names)?;
+        write_value(w, value, names)?;
+    }
+    Ok(())
+}
+
+fn write_value<W: Write + ?Sized>(w: &mut W, value: &Value, names: Option<&SymbolTable>) -> Result<()> {
+    match value {
+        Value::Unit => w.write_all(&[0]),
+        Value::Bool(val) => w.write_all(&[1 + *val as u8]),
+        Value::Char(val) => {
+            w.write_all(&[3])?;
+            write_varint(w, *val as u64)
+        }
+        Value::Number(val) => {
+            w.write_all(&[4])?;
+            write_number(w, val)
+        }
+        Value::String(val) => {
+            w.write_all(&[5])?;
+            write_str(w, val)
+        }
+        Value::Seq(values) => {
+            w.write_all(&[6])?;
+            write_elements(w, values, names)
+        }
+        Value::Map(map) => {
+            w.write_all(&[7])?;
+            write_varint(w, map.len() as u64)?;
+            for (key, value) in map {
+                write_value(w, key, names)?;
+                write_value(w, value, names)?;
+            }
+            Ok(())
+        }
+        Value::Tuple(values) => {
+            w.write_all(&[8])?;
+            write_elements(w, values, names)
+        }
+        Value::UnitStruct(val) => {
+            w.write_all(&[9])?;
+            write_name(w, val.name, names)
+        }
+        Value::TupleStruct(val) => {
+            w.write_all(&[10])?;
+            write_name(w, val.name, names)?;
+            write_elements(w, &val.values, names)
+        }
+        Value::NamedStruct(val) => {
+            w.write_all(&[11])?;
+            write_name(w, val.name, names)?;
+            write_fields(w, &val.fields, names)
+        }
+        Value::UnitVariant(val) => {
+            w.write_all(&[12])?;
+            write_name(w, val.name, names)?;
+            write_name(w, val.variant, names)
+        }
+        Value::TupleVariant(val) => {
+            w.write_all(&[13])?;
+            write_name(w, val.name, names)?;
+            write_name(w, val.variant, names)?;
+            write_elements(w, &val.values, names)
+        }
+        Value::NamedVariant(val) => {
+            w.write_all(&[14])?;
+            write_name(w, val.name, names)?;
+            write_name(w, val.variant, names)?;
+            write_fields(w, &val.fields, names)
+        }
+    }
+}
+
+#[test]
+fn test_binary() {
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize)]
+    struct Point {
+        x: i16,
+        y: f32,
+    }
+
+    #[derive(Serialize)]
+    enum Shape {
+        Dot(Point),
+    }
+
+    let value = to_value(&Shape::Dot(Point { x: -2, y: 0.5 })).unwrap();
+    let mut expected = MAGIC.to_vec();
+    expected.extend_from_slice(b"\x0d\x05Shape\x03Dot\x01");
+    expected.extend_from_slice(b"\x0b\x05Point\x02");
+    expected.extend_from_slice(b"\x01x\x04\x06\xfe\xff");
+    expected.extend_from_slice(b"\x01y\x04\x0a");
+    expected.extend_from_slice(&0.5f32.to_le_bytes());
+    assert_eq!(value.to_binary(), expected);
+
+    let mut varint = Vec::new();
+    write_varint(&mut varint, 300).unwrap();
+    assert_eq!(varint, [0xac, 0x02]);
+    assert_eq!(Value::from('é').to_binary()[4..], [3, 0xe9, 0x01]);
+
+    #[derive(Serialize)]
+    struct Sample {
+        shapes: Vec<Shape>,
+        keys: BTreeMap<(u8, char), Option<String>>,
+        numbers: (u128, i64, f64, bool, ()),
+    }
+
+    let sample = to_value(&Sample {
+        shapes: vec![Shape::Dot(Point { x: 1, y: 2.0 }), Shape::Dot(Point { x: 3, y: f32::NAN })],
+        keys: [((1, 'a'), Some("é".to_string())), ((2, 'b'), None)].into(),
+        numbers: (u128::MAX, i64::MIN, -0.5, true, ()),
+    }).unwrap();
+    let inline = sample.to_binary();
+    let table = sample.to_binary_with(&BinaryOptions { name_table: true, ..BinaryOptions::default() });
+    assert!(table.len() < inline.len());
+    let mut symbols = SymbolTable::new();
+    symbols.collect(&sample);
+    assert_eq!(Value::from_binary(&inline, &symbols).unwrap(), sample);
+    assert_eq!(Value::from_binary(&table, &symbols).unwrap(), sample);
+    let Value::NamedStruct(read) = Value::from_binary(&table, &symbols).unwrap() else { unreachable!() };
+    let Value::NamedStruct(written) = &sample else { unreachable!() };
+    assert!(std::ptr::eq(read.name, written.name));
+    assert_eq!(Value::from_binary(&table, &SymbolTable::new()).unwrap_err().to_string(), "unknown name at byte 5");
+
+    let options = BinaryOptions { name_table: true, shared_subtrees: true };
+    let repeated = Value::Seq(vec![sample.clone(), sample.clone()]);
+    let shared = repeated.to_binary_with(&options);
+    assert_eq!(shared[3], 4);
+    assert!(shared.len() <= table.len() + 4);
+    assert_eq!(Value::from_binary(&shared, &symbols).unwrap(), repeated);
+    let dag = SharedValue::from_binary(&shared, &symbols).unwrap();
+    assert!(SharedValue::ptr_eq(&dag[0], &dag[1]));
+    assert_eq!(dag.to_binary_with(&options), shared);
+    assert_eq!(dag.to_binary(), repeated.to_binary());
+
+    let symbols: SymbolTable = ["a"].into_iter().collect();
+    let error = |bytes: &[u8]| Value::from_binary(bytes, &symbols).unwrap_err().to_string();
+    assert_eq!(error(b"SVB\x05\x00"), "unknown header at byte 0");
+    assert_eq!(error(b"SVB\x01\x0f"), "unknown tag at byte 4");
+    assert_eq!(error(b"SVB\x01\x00\x00"), "trailing bytes at byte 5");
+    assert_eq!(error(b"SVB\x01\x06\xff\xff\x03"), "count exceeds the input at byte 5");
+    assert_eq!(error(b"SVB\x01\x05\x01\xff"), "invalid UTF-8 at byte 6");
+    assert_eq!(error(b"SVB\x02\x01\x01a\x09\x01"), "name index out of range at byte 8");
+    assert_eq!(error(&[&MAGIC[..], &[6, 1].repeat(200)].concat()), "nested too deeply at byte 260");
+    assert_eq!(error(b"SVB\x03\x06\x02\x0f\x00\x00"), "reference to an unknown value at byte 7");
+    assert_eq!(error(b"SVB\x01\x06\x01\x0f\x00"), "unknown tag at byte 6");
+
+    // each sequence holds the previous one twice, doubling the expanded size
+    let mut chain = b"SVB\x03\x06\x29\x06\x00".to_vec();
+    for number in 1..=40 {
+        chain.extend([6, 2, SHARED_TAG, number, SHARED_TAG, number]);
+    }
+    assert_eq!(error(&chain), "expanded value too large at byte 144");
+    let dag = SharedValue::from_binary(&chain, &symbols).unwrap();
+    assert!(SharedValue::ptr_eq(&dag[40][0], &dag[39]));
+
+    // each element nests 120 sequences around a reference to the previous element
+    let mut deep = b"SVB\x03\x06\x03".to_vec();
+    for element in 0..3u8 {
+        deep.extend([6, 1].repeat(120));
+        match element {
+            0 => deep.push(0),
+            _ => deep.extend([SHARED_TAG, 1 + 120 * (element - 1)]),
+        }
+    }
+    assert_eq!(error(&deep), "nested too deeply at byte 488");
+    assert_eq!(SharedValue::from_binary(&deep, &symbols).unwrap_err().offset, 488);
+}
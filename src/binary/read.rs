@@ -0,0 +1,292 @@
+use std::collections::BTreeMap;
+use crate::value::{NamedStruct, NamedVariant, Number, NumberKind, SharedNode, SharedValue, SymbolTable, TupleStruct, TupleVariant, UnitStruct, UnitVariant};
+use super::{BinaryError, BinaryOptions, SHARED_TAG};
+
+/// How deep values may nest
+const MAX_DEPTH: usize = 128;
+
+/// How large the expanded value may be, in nodes and string bytes, when the input is smaller
+const MAX_EXPANDED: u64 = 1 << 24;
+
+pub(super) struct Reader<'b, 's> {
+    bytes: &'b [u8],
+    pos: usize,
+    /// The known names, which every name read must be one of
+    symbols: &'s SymbolTable,
+    /// The names of the name table, with a name table
+    names: Option<Vec<&'static str>>,
+    /// The numbered values with their expanded size and depth, with shared subtrees, which are
+    /// `None` until read in full
+    shared: Option<Vec<Option<(SharedValue, u64, usize)>>>,
+    depth: usize,
+    /// The deepest level reached so far once shared subtrees are copied, which references can
+    /// take past `depth`
+    deepest: usize,
+    /// The size of the value read so far once its shared subtrees are copied, in nodes and
+    /// string bytes
+    expanded: u64,
+    /// The largest expanded size accepted, when the value is expanded
+    max_expanded: Option<u64>,
+}
+
+impl<'b, 's> Reader<'b, 's> {
+    pub(super) fn new(bytes: &'b [u8], symbols: &'s SymbolTable) -> Self {
+        Reader { bytes, pos: 0, symbols, names: None, shared: None, depth: 0, deepest: 0, expanded: 0, max_expanded: None }
+    }
+
+    /// Limits the size of the value once its shared subtrees are copied, which can grow
+    /// exponentially with the input when subtrees refer to each other
+    pub(super) fn expanding(mut self) -> Self {
+        self.max_expanded = Some(MAX_EXPANDED.max(self.bytes.len() as u64));
+        self
+    }
+
+    /// Reads the header and a single value, with no bytes left over
+    pub(super) fn read_document(mut self) -> Result<SharedValue, BinaryError> {
+        let Some(options) = BinaryOptions::from_header(self.take(4)?) else {
+            self.pos = 0;
+            return self.error("unknown header");
+        };
+        if options.name_table {
+            let count = self.count()?;
+            let mut names = Vec::with_capacity(count);
+            for _ in 0..count {
+                names.push(self.known_name()?);
+            }
+            self.names = Some(names);
+        }
+        if options.shared_subtrees {
+            self.shared = Some(Vec::new());
+        }
+        let value = self.value()?;
+        if self.pos < self.bytes.len() {
+            return self.error("trailing bytes");
+        }
+        Ok(value)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, BinaryError> {
+        Err(BinaryError { offset: self.pos, message: message.into() })
+    }
+
+    /// Adds `size` to the expanded size of the value
+    fn expand(&mut self, size: u64) -> Result<(), BinaryError> {
+        self.expanded = self.expanded.saturating_add(size);
+        match self.max_expanded {
+            Some(max) if self.expanded > max => self.error("expanded value too large"),
+            _ => Ok(()),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Result<&'b [u8], BinaryError> {
+        match self.bytes.get(self.pos..self.pos.saturating_add(len)) {
+            Some(bytes) => {
+                self.pos += len;
+                Ok(bytes)
+            }
+            None => {
+                self.pos = self.bytes.len();
+                self.error("unexpected end of input")
+            }
+        }
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], BinaryError> {
+        Ok(self.take(N)?.try_into().expect("took N bytes"))
+    }
+
+    fn byte(&mut self) -> Result<u8, BinaryError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn varint(&mut self) -> Result<u64, BinaryError> {
+        let start = self.pos;
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            n |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+        }
+        self.pos = start;
+        self.error("varint too long")
+    }
+
+    /// A count of items, each taking at least one byte
+    fn count(&mut self) -> Result<usize, BinaryError> {
+        let start = self.pos;
+        match usize::try_from(self.varint()?) {
+            Ok(count) if count <= self.bytes.len() - self.pos => Ok(count),
+            _ => {
+                self.pos = start;
+                self.error("count exceeds the input")
+            }
+        }
+    }
+
+    fn str(&mut self) -> Result<&'b str, BinaryError> {
+        let len = self.count()?;
+        let start = self.pos;
+        let bytes = self.take(len)?;
+        std::str::from_utf8(bytes).or_else(|err| {
+            self.pos = start + err.valid_up_to();
+            self.error("invalid UTF-8")
+        })
+    }
+
+    fn name(&mut self) -> Result<&'static str, BinaryError> {
+        if self.names.is_none() {
+            return self.known_name();
+        }
+        let start = self.pos;
+        let index = self.varint()?;
+        match self.names.as_ref().and_then(|names| names.get(index as usize)) {
+            Some(name) => Ok(name),
+            None => {
+                self.pos = start;
+                self.error("name index out of range")
+            }
+        }
+    }
+
+    /// A name written in full, looked up in the known names
+    fn known_name(&mut self) -> Result<&'static str, BinaryError> {
+        let start = self.pos;
+        let name = self.str()?;
+        match self.symbols.get(name) {
+            Some(symbol) => Ok(self.symbols.name(symbol)),
+            None => {
+                self.pos = start;
+                self.error("unknown name")
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Number, BinaryError> {
+        let Some(&kind) = NumberKind::ALL.get(self.byte()? as usize) else {
+            self.pos -= 1;
+            return self.error("unknown number kind");
+        };
+        Ok(match kind {
+            NumberKind::U8 => Number::from(u8::from_le_bytes(self.take_array()?)),
+            NumberKind::U16 => Number::from(u16::from_le_bytes(self.take_array()?)),
+            NumberKind::U32 => Number::from(u32::from_le_bytes(self.take_array()?)),
+            NumberKind::U64 => Number::from(u64::from_le_bytes(self.take_array()?)),
+            NumberKind::U128 => Number::from(u128::from_le_bytes(self.take_array()?)),
+            NumberKind::I8 => Number::from(i8::from_le_bytes(self.take_array()?)),
+            NumberKind::I16 => Number::from(i16::from_le_bytes(self.take_array()?)),
+            NumberKind::I32 => Number::from(i32::from_le_bytes(self.take_array()?)),
+            NumberKind::I64 => Number::from(i64::from_le_bytes(self.take_array()?)),
+            NumberKind::I128 => Number::from(i128::from_le_bytes(self.take_array()?)),
+            NumberKind::F32 => Number::from(f32::from_le_bytes(self.take_array()?)),
+            NumberKind::F64 => Number::from(f64::from_le_bytes(self.take_array()?)),
+        })
+    }
+
+    fn elements(&mut self) -> Result<Vec<SharedValue>, BinaryError> {
+        let count = self.count()?;
+        (0..count).map(|_| self.value()).collect()
+    }
+
+    fn fields(&mut self) -> Result<BTreeMap<&'static str, SharedValue>, BinaryError> {
+        let count = self.count()?;
+        let mut fields = BTreeMap::new();
+        for _ in 0..count {
+            let name = self.name()?;
+            fields.insert(name, self.value()?);
+        }
+        Ok(fields)
+    }
+
+    fn value(&mut self) -> Result<SharedValue, BinaryError> {
+        if self.depth == MAX_DEPTH {
+            return self.error("nested too deeply");
+        }
+        let tag = self.byte()?;
+        let Some(shared) = self.shared.as_mut().filter(|_| (5..=SHARED_TAG).contains(&tag)) else {
+            return self.nested(tag);
+        };
+        if tag == SHARED_TAG {
+            let start = self.pos;
+            let number = self.varint()?;
+            let shared = self.shared.as_ref().expect("reading shared subtrees");
+            let Some(Some((value, size, depth))) = shared.get(number as usize).cloned() else {
+                self.pos = start;
+                return self.error("reference to an unknown value");
+            };
+            // the referenced value nests below this one, even when it is kept shared
+            if self.depth + depth > MAX_DEPTH {
+                self.pos = start;
+                return self.error("nested too deeply");
+            }
+            self.deepest = self.deepest.max(self.depth + depth);
+            self.expand(size)?;
+            return Ok(value);
+        }
+        let number = shared.len();
+        shared.push(None);
+        let start = self.expanded;
+        let deepest = std::mem::replace(&mut self.deepest, self.depth);
+        let value = self.nested(tag)?;
+        let size = self.expanded - start;
+        let depth = self.deepest - self.depth;
+        self.deepest = self.deepest.max(deepest);
+        self.shared.as_mut().expect("reading shared subtrees")[number] = Some((value.clone(), size, depth));
+        Ok(value)
+    }
+
+    fn nested(&mut self, tag: u8) -> Result<SharedValue, BinaryError> {
+        self.expand(1)?;
+        self.depth += 1;
+        self.deepest = self.deepest.max(self.depth);
+        let node = self.tagged(tag);
+        self.depth -= 1;
+        Ok(SharedValue::new(node?))
+    }
+
+    fn tagged(&mut self, tag: u8) -> Result<SharedNode, BinaryError> {
+        Ok(match tag {
+            0 => SharedNode::Unit,
+            1 => SharedNode::Bool(false),
+            2 => SharedNode::Bool(true),
+            3 => {
+                let start = self.pos;
+                match u32::try_from(self.varint()?).ok().and_then(char::from_u32) {
+                    Some(c) => SharedNode::Char(c),
+                    None => {
+                        self.pos = start;
+                        return self.error("invalid character");
+                    }
+                }
+            }
+            4 => SharedNode::Number(self.number()?),
+            5 => {
+                let val = self.str()?;
+                self.expand(val.len() as u64)?;
+                SharedNode::String(val.to_string())
+            }
+            6 => SharedNode::Seq(self.elements()?),
+            7 => {
+                let count = self.count()?;
+                let mut map = BTreeMap::new();
+                for _ in 0..count {
+                    let key = self.value()?;
+                    map.insert(key, self.value()?);
+                }
+                SharedNode::Map(map)
+            }
+            8 => SharedNode::Tuple(self.elements()?),
+            9 => SharedNode::UnitStruct(UnitStruct { name: self.name()? }),
+            10 => SharedNode::TupleStruct(TupleStruct { name: self.name()?, values: self.elements()? }),
+            11 => SharedNode::NamedStruct(NamedStruct { name: self.name()?, fields: self.fields()? }),
+            12 => SharedNode::UnitVariant(UnitVariant { name: self.name()?, variant: self.name()? }),
+            13 => SharedNode::TupleVariant(TupleVariant { name: self.name()?, variant: self.name()?, values: self.elements()? }),
+            14 => SharedNode::NamedVariant(NamedVariant { name: self.name()?, variant: self.name()?, fields: self.fields()? }),
+            _ => {
+                self.pos -= 1;
+                return self.error("unknown tag");
+            }
+        })
+    }
+}
@@ -0,0 +1,117 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{Result, Write};
+use crate::value::{NamedStruct, NamedVariant, SharedNode, SharedValue, SymbolTable, TupleStruct, TupleVariant, UnitStruct, UnitVariant};
+use super::{write_header, write_name, write_number, write_str, write_varint, BinaryOptions, SHARED_TAG};
+
+/// Writes the binary encoding of a shared `value` to `w`, starting with its header
+pub(super) fn write_shared<W: Write + ?Sized>(w: &mut W, value: &SharedValue, options: &BinaryOptions) -> Result<()> {
+    let names = options.name_table.then(|| {
+        let mut names = SymbolTable::new();
+        names.collect_shared(value, &mut HashSet::new());
+        names
+    });
+    write_header(w, options, names.as_ref())?;
+    let mut writer = SharedWriter { names: names.as_ref(), numbers: options.shared_subtrees.then(HashMap::new) };
+    writer.write_value(w, value)
+}
+
+struct SharedWriter<'n> {
+    names: Option<&'n SymbolTable>,
+    /// The numbers of the nodes written so far, with shared subtrees
+    numbers: Option<HashMap<*const SharedNode, u64>>,
+}
+
+impl SharedWriter<'_> {
+    fn write_elements<W: Write + ?Sized>(&mut self, w: &mut W, values: &[SharedValue]) -> Result<()> {
+        write_varint(w, values.len() as u64)?;
+        values.iter().try_for_each(|value| self.write_value(w, value))
+    }
+
+    fn write_fields<W: Write + ?Sized>(&mut self, w: &mut W, fields: &BTreeMap<&'static str, SharedValue>) -> Result<()> {
+        write_varint(w, fields.len() as u64)?;
+        for (name, value) in fields {
+            write_name(w, name, self.names)?;
+            self.write_value(w, value)?;
+        }
+        Ok(())
+    }
+
+    fn write_value<W: Write + ?Sized>(&mut self, w: &mut W, value: &SharedValue) -> Result<()> {
+        let node = value.node();
+        let numbered = !matches!(node, SharedNode::Unit | SharedNode::Bool(_) | SharedNode::Char(_) | SharedNode::Number(_));
+        if let (true, Some(numbers)) = (numbered, &mut self.numbers) {
+            let next = numbers.len() as u64;
+            let number = *numbers.entry(value.as_ptr()).or_insert(next);
+            if number != next {
+                w.write_all(&[SHARED_TAG])?;
+                return write_varint(w, number);
+            }
+        }
+
+        let names = self.names;
+        match node {
+            SharedNode::Unit => w.write_all(&[0]),
+            SharedNode::Bool(val) => w.write_all(&[1 + *val as u8]),
+            SharedNode::Char(val) => {
+                w.write_all(&[3])?;
+                write_varint(w, *val as u64)
+            }
+            SharedNode::Number(val) => {
+                w.write_all(&[4])?;
+                write_number(w, val)
+            }
+            SharedNode::String(val) => {
+                w.write_all(&[5])?;
+                write_str(w, val)
+            }
+            SharedNode::Seq(values) => {
+                w.write_all(&[6])?;
+                self.write_elements(w, values)
+            }
+            SharedNode::Map(map) => {
+                w.write_all(&[7])?;
+                write_varint(w, map.len() as u64)?;
+                for (key, value) in map {
+                    self.write_value(w, key)?;
+                    self.write_value(w, value)?;
+                }
+                Ok(())
+            }
+            SharedNode::Tuple(values) => {
+                w.write_all(&[8])?;
+                self.write_elements(w, values)
+            }
+            SharedNode::UnitStruct(UnitStruct { name }) => {
+                w.write_all(&[9])?;
+                write_name(w, name, names)
+            }
+            SharedNode::TupleStruct(TupleStruct { name, values }) => {
+                w.write_all(&[10])?;
+                write_name(w, name, names)?;
+                self.write_elements(w, values)
+            }
+            SharedNode::NamedStruct(NamedStruct { name, fields }) => {
+                w.write_all(&[11])?;
+                write_name(w, name, names)?;
+                self.write_fields(w, fields)
+            }
+            SharedNode::UnitVariant(UnitVariant { name, variant }) => {
+                w.write_all(&[12])?;
+                write_name(w, name, names)?;
+                write_name(w, variant, names)
+            }
+            SharedNode::TupleVariant(TupleVariant { name, variant, values }) => {
+                w.write_all(&[13])?;
+                write_name(w, name, names)?;
+                write_name(w, variant, names)?;
+                self.write_elements(w, values)
+            }
+            SharedNode::NamedVariant(NamedVariant { name, variant, fields }) => {
+                w.write_all(&[14])?;
+                write_name(w, name, names)?;
+                write_name(w, variant, names)?;
+                self.write_fields(w, fields)
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! Deduplication of identical subtrees
+//!
+//! [`Value::dedup`] hash-conses a value into a [`SharedValue`] DAG, in which every distinct
+//! subtree is stored once and shared by all the places it appears in. Values with many repeated
+//! sub-structures, such as captured configurations, take a fraction of their memory as a DAG,
+//! and [encode](crate::binary::BinaryOptions::shared_subtrees) to a fraction of their size.
+//!
+//! ```
+//! use serde_derive::Serialize;
+//! use serde_value::{SharedValue, Value};
+//!
+//! #[derive(Serialize)]
+//! struct Limits {
+//!     cpu: u32,
+//!     memory: u32,
+//! }
+//!
+//! let limits: Vec<Limits> = (0..1000).map(|_| Limits { cpu: 2, memory: 512 }).collect();
+//! let value = serde_value::to_value(&limits).unwrap();
+//!
+//! let dag = value.dedup();
+//! assert_eq!(dag.stats.total, 3001);
+//! assert_eq!(dag.stats.unique, 4);
+//! assert!(SharedValue::ptr_eq(&dag.root[0], &dag.root[999]));
+//! assert_eq!(dag.root.to_value(), value);
+//! ```
+
+use std::collections::HashMap;
+use std::mem::Discriminant;
+use crate::value::{NamedStruct, NamedVariant, SharedNode, SharedValue, TupleStruct, TupleVariant, UnitStruct, UnitVariant, Value};
+
+/// A value with its identical subtrees shared, see [`Value::dedup`]
+#[derive(Clone, Debug)]
+pub struct Dedup {
+    /// The root of the DAG
+    pub root: SharedValue,
+    pub stats: DedupStats,
+}
+
+/// The node counts of a deduplicated value, where map keys are nodes as well
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub struct DedupStats {
+    /// The nodes of the value as a tree
+    pub total: usize,
+    /// The distinct nodes, stored once in the DAG
+    pub unique: usize,
+}
+
+impl DedupStats {
+    /// The nodes that are shared rather than stored
+    pub fn shared(&self) -> usize {
+        self.total - self.unique
+    }
+}
+
+impl Value {
+    /// Builds a DAG of this value in which identical subtrees are a single shared node,
+    /// comparing subtrees as the derived `Eq` of `Value` does
+    pub fn dedup(&self) -> Dedup {
+        let mut interner = Interner::default();
+        let root = interner.intern(self);
+        let stats = DedupStats { total: interner.total, unique: interner.nodes.len() };
+        Dedup { root, stats }
+    }
+}
+
+/// A node identified by its contents, with its children identified by address, as they are
+/// interned already
+#[derive(Eq, PartialEq, Hash)]
+enum Key {
+    Leaf(Value),
+    Node {
+        kind: Discriminant<SharedNode>,
+        names: Vec<&'static str>,
+        children: Vec<*const SharedNode>,
+    },
+}
+
+#[derive(Default)]
+struct Interner {
+    /// The interned nodes, which keep the addresses in keys alive
+    nodes: HashMap<Key, SharedValue>,
+    total: usize,
+}
+
+impl Interner {
+    fn intern(&mut self, value: &Value) -> SharedValue {
+        self.total += 1;
+        let node = match value {
+            Value::Unit | Value::Bool(_) | Value::Char(_) | Value::Number(_) | Value::String(_) => {
+                return self.nodes.entry(Key::Leaf(value.clone()))
+                    .or_insert_with(|| SharedValue::from(value.clone()))
+                    .clone();
+            }
+            Value::Seq(values) => SharedNode::Seq(self.intern_all(values)),
+            Value::Map(map) => SharedNode::Map(map.iter().map(|(k, v)| (self.intern(k), self.intern(v))).collect()),
+            Value::Tuple(values) => SharedNode::Tuple(self.intern_all(values)),
+            Value::UnitStruct(val) => SharedNode::UnitStruct(*val),
+            Value::TupleStruct(val) => SharedNode::TupleStruct(TupleStruct { name: val.name, values: self.intern_all(&val.values) }),
+            Value::NamedStruct(val) => SharedNode::NamedStruct(NamedStruct {
+                name: val.name,
+                fields: val.fields.iter().map(|(name, value)| (*name, self.intern(value))).collect(),
+            }),
+            Value::UnitVariant(val) => SharedNode::UnitVariant(*val),
+            Value::TupleVariant(val) => SharedNode::TupleVariant(TupleVariant {
+                name: val.name,
+                variant: val.variant,
+                values: self.intern_all(&val.values),
+            }),
+            Value::NamedVariant(val) => SharedNode::NamedVariant(NamedVariant {
+                name: val.name,
+                variant: val.variant,
+                fields: val.fields.iter().map(|(name, value)| (*name, self.intern(value))).collect(),
+            }),
+        };
+        self.nodes.entry(key(&node)).or_insert_with(|| SharedValue::new(node)).clone()
+    }
+
+    fn intern_all(&mut self, values: &[Value]) -> Vec<SharedValue> {
+        values.iter().map(|value| self.intern(value)).collect()
+    }
+}
+
+fn key(node: &SharedNode) -> Key {
+    let mut names = Vec::new();
+    let mut children = Vec::new();
+    match node {
+        SharedNode::Unit
+        | SharedNode::Bool(_)
+        | SharedNode::Char(_)
+        | SharedNode::Number(_)
+        | SharedNode::String(_) => {}
+        SharedNode::Seq(values) | SharedNode::Tuple(values) => children.extend(values.iter().map(SharedValue::as_ptr)),
+        SharedNode::Map(map) => children.extend(map.iter().flat_map(|(k, v)| [k.as_ptr(), v.as_ptr()])),
+        SharedNode::UnitStruct(UnitStruct { name }) => names.push(*name),
+        SharedNode::TupleStruct(TupleStruct { name, values }) => {
+            names.push(*name);
+            children.extend(values.iter().map(SharedValue::as_ptr));
+        }
+        SharedNode::NamedStruct(NamedStruct { name, fields }) => {
+            names.push(*name);
+            names.extend(fields.keys());
+            children.extend(fields.values().map(SharedValue::as_ptr));
+        }
+        SharedNode::UnitVariant(UnitVariant { name, variant }) => names.extend([*name, *variant]),
+        SharedNode::TupleVariant(TupleVariant { name, variant, values }) => {
+            names.extend([*name, *variant]);
+            children.extend(values.iter().map(SharedValue::as_ptr));
+        }
+        SharedNode::NamedVariant(NamedVariant { name, variant, fields }) => {
+            names.extend([*name, *variant]);
+            names.extend(fields.keys());
+            children.extend(fields.values().map(SharedValue::as_ptr));
+        }
+    }
+    Key::Node { kind: std::mem::discriminant(node), names, children }
+}
+
+#[test]
+fn test_dedup() {
+    use std::collections::BTreeMap;
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize, Clone)]
+    enum Probe {
+        Http { path: String, port: u16 },
+        Tcp(u16),
+    }
+
+    #[derive(Serialize, Clone)]
+    struct Service {
+        name: String,
+        probes: Vec<Probe>,
+        labels: BTreeMap<String, String>,
+    }
+
+    let template = Service {
+        name: "api".to_string(),
+        probes: vec![Probe::Http { path: "/health".to_string(), port: 80 }, Probe::Tcp(80)],
+        labels: [("tier".to_string(), "api".to_string())].into(),
+    };
+    let mut services = vec![template.clone(); 50];
+    services[7].probes[1] = Probe::Tcp(81);
+    let value = to_value(&services).unwrap();
+
+    let dag = value.dedup();
+    assert_eq!(dag.root.to_value(), value);
+    assert_eq!(dag.stats.total, 1 + 50 * 11);
+    assert_eq!(dag.stats.shared(), dag.stats.total - dag.stats.unique);
+    assert!(dag.stats.unique < 20);
+    assert!(SharedValue::ptr_eq(&dag.root[0], &dag.root[49]));
+    assert!(!SharedValue::ptr_eq(&dag.root[0], &dag.root[7]));
+    assert!(SharedValue::ptr_eq(&dag.root[0]["probes"][0], &dag.root[7]["probes"][0]));
+    assert!(SharedValue::ptr_eq(&dag.root[0]["name"], &dag.root[0]["labels"]["tier"]));
+
+    let leaf = Value::from(1u8).dedup();
+    assert_eq!(leaf.stats, DedupStats { total: 1, unique: 1 });
+}
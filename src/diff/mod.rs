@@ -0,0 +1,428 @@
+//! Structural differences between two [`Value`] trees
+//!
+//! [`Value::diff`] walks two values side by side and reports a [`Change`] for every node that
+//! differs:
+//!
+//! * `NamedStruct` and `NamedVariant` fields are compared by name, and `Map` entries by key
+//! * `Seq`, `Tuple`, `TupleStruct` and `TupleVariant` elements are aligned with a longest
+//!   common subsequence, so inserting an element reports a single [`ChangeKind::Added`]
+//!   instead of changing every following element
+//! * a struct or enum whose name changed is reported as [`ChangeKind::Renamed`] and its
+//!   contents are still compared, while switching to another variant of the same enum is
+//!   reported as [`ChangeKind::VariantSwitched`]
+//! * numbers that are equal but of different widths are reported as
+//!   [`ChangeKind::NumberWidthChanged`], separately from [`ChangeKind::Changed`] values
+//!
+//! Changes are listed so that they can be replayed in order to turn `a` into `b`: paths address
+//! `a`, except for the last segment of an [`ChangeKind::Added`] element of a sequence, which is
+//! its index in `b`. Within a sequence, changes to aligned elements come first, then removals
+//! in descending order and additions in ascending order.
+//!
+//! [`Value::diff_merkle`] computes the same changes from the [Merkle trees](crate::merkle) of
+//! both values, comparing subtrees by hash rather than walking identical ones.
+//!
+//! ```
+//! use serde_value::Value;
+//! use serde_value::diff::ChangeKind;
+//!
+//! let a = Value::from(vec![1u8, 2, 3]);
+//! let b = Value::from(vec![1u8, 3, 4]);
+//!
+//! let changes = Value::diff(&a, &b);
+//! assert_eq!(changes.len(), 2);
+//! assert_eq!(changes[0].path.to_string(), "/1");
+//! assert_eq!(changes[0].kind, ChangeKind::Removed(Value::from(2u8)));
+//! assert_eq!(changes[1].path.to_string(), "/2");
+//! assert_eq!(changes[1].kind, ChangeKind::Added(Value::from(4u8)));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use crate::merkle::Merkle;
+use crate::value::{Number, Path, Segment, Value};
+use crate::value::path::key_segment;
+
+/// A single difference between two values, see the [module documentation](self)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Change {
+    pub path: Path,
+    pub kind: ChangeKind,
+}
+
+/// The kind of a [`Change`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ChangeKind {
+    /// A field, map entry or element only present in the new value
+    Added(Value),
+    /// A field, map entry or element only present in the old value
+    Removed(Value),
+    /// A scalar of the same variant and width changed
+    Changed {
+        from: Value,
+        to: Value,
+    },
+    /// The node changed to another [`Value`] variant, or between kinds of struct or variant
+    TypeChanged {
+        from: Value,
+        to: Value,
+    },
+    /// A number kept its value but changed width
+    NumberWidthChanged {
+        from: Number,
+        to: Number,
+    },
+    /// A struct or enum changed name, its contents are compared separately
+    Renamed {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// An enum value switched to another variant
+    VariantSwitched {
+        from: Value,
+        to: Value,
+    },
+}
+
+impl Display for Change {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() { "/".to_string() } else { self.path.to_string() };
+        match &self.kind {
+            ChangeKind::Added(value) => write!(f, "{path}: added {value:?}"),
+            ChangeKind::Removed(value) => write!(f, "{path}: removed {value:?}"),
+            ChangeKind::Changed { from, to } => write!(f, "{path}: changed {from:?} to {to:?}"),
+            ChangeKind::TypeChanged { from, to } => write!(f, "{path}: type changed from {from:?} to {to:?}"),
+            ChangeKind::NumberWidthChanged { from, to } => write!(f, "{path}: number width changed from {from:?} to {to:?}"),
+            ChangeKind::Renamed { from, to } => write!(f, "{path}: renamed {from} to {to}"),
+            ChangeKind::VariantSwitched { from, to } => write!(f, "{path}: variant switched from {from:?} to {to:?}"),
+        }
+    }
+}
+
+/// Above this many element comparisons, sequences are aligned by position instead of with a
+/// longest common subsequence
+const LCS_LIMIT: usize = 1 << 22;
+
+impl Value {
+    /// Computes the changes turning `a` into `b`, see [`diff`](crate::diff)
+    pub fn diff(a: &Value, b: &Value) -> Vec<Change> {
+        let mut changes = Vec::new();
+        diff_value(&mut Path::root(), a, b, Trees(None), &mut changes);
+        changes
+    }
+
+    /// Computes the changes turning `a` into `b` as [`Value::diff`] does, comparing nodes by
+    /// their hashes in `a_tree` and `b_tree`, the [Merkle trees](crate::merkle) of `a` and `b`,
+    /// so that identical subtrees are skipped without being walked.
+    ///
+    /// The trees must be those of the values as they are now: a [`Merkle`] tree is not updated
+    /// when its value is edited. The changes are unspecified if a tree is stale, and nodes
+    /// missing from a stale tree are compared structurally.
+    pub fn diff_merkle(a: &Value, a_tree: &Merkle, b: &Value, b_tree: &Merkle) -> Vec<Change> {
+        let mut changes = Vec::new();
+        diff_value(&mut Path::root(), a, b, Trees(Some((a_tree, b_tree))), &mut changes);
+        changes
+    }
+}
+
+/// The Merkle trees of two nodes being compared, if known
+#[derive(Copy, Clone)]
+struct Trees<'t>(Option<(&'t Merkle, &'t Merkle)>);
+
+impl Trees<'_> {
+    /// Returns true if both nodes are equal, comparing their hashes if their trees are known
+    fn same(self, a: &Value, b: &Value) -> bool {
+        match self.0 {
+            Some((x, y)) => x.same(y),
+            None => a == b,
+        }
+    }
+
+    /// The trees of the `i`th child of the first node and the `j`th child of the second, if both
+    /// trees have them
+    fn child(self, i: usize, j: usize) -> Self {
+        Trees(self.0.and_then(|(x, y)| Some((x.children().get(i)?, y.children().get(j)?))))
+    }
+}
+
+fn push(changes: &mut Vec<Change>, path: &Path, kind: ChangeKind) {
+    changes.push(Change { path: path.clone(), kind });
+}
+
+fn diff_value(path: &mut Path, a: &Value, b: &Value, trees: Trees<'_>, changes: &mut Vec<Change>) {
+    if trees.same(a, b) {
+        return;
+    }
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => {
+            let same_width = std::mem::discriminant(x) == std::mem::discriminant(y);
+            let kind = if !same_width && x.numeric_cmp(y) == Some(std::cmp::Ordering::Equal) {
+                ChangeKind::NumberWidthChanged { from: *x, to: *y }
+            } else {
+                ChangeKind::Changed { from: a.clone(), to: b.clone() }
+            };
+            push(changes, path, kind);
+        }
+        (Value::Bool(_), Value::Bool(_))
+        | (Value::Char(_), Value::Char(_))
+        | (Value::String(_), Value::String(_)) => {
+            push(changes, path, ChangeKind::Changed { from: a.clone(), to: b.clone() });
+        }
+        (Value::Seq(x), Value::Seq(y)) | (Value::Tuple(x), Value::Tuple(y)) => {
+            diff_elements(path, x, y, trees, changes);
+        }
+        (Value::Map(x), Value::Map(y)) => diff_entries(path, x, y, key_segment, trees, changes),
+        (Value::UnitStruct(x), Value::UnitStruct(y)) => {
+            push(changes, path, ChangeKind::Renamed { from: x.name, to: y.name });
+        }
+        (Value::TupleStruct(x), Value::TupleStruct(y)) => {
+            if x.name != y.name {
+                push(changes, path, ChangeKind::Renamed { from: x.name, to: y.name });
+            }
+            diff_elements(path, &x.values, &y.values, trees, changes);
+        }
+        (Value::NamedStruct(x), Value::NamedStruct(y)) => {
+            if x.name != y.name {
+                push(changes, path, ChangeKind::Renamed { from: x.name, to: y.name });
+            }
+            diff_entries(path, &x.fields, &y.fields, field_segment, trees, changes);
+        }
+        (Value::UnitVariant(x), Value::UnitVariant(y)) => {
+            if x.variant != y.variant {
+                push(changes, path, switched(a, b));
+            } else {
+                push(changes, path, ChangeKind::Renamed { from: x.name, to: y.name });
+            }
+        }
+        (Value::TupleVariant(x), Value::TupleVariant(y)) => {
+            if x.variant != y.variant {
+                push(changes, path, switched(a, b));
+                return;
+            }
+            if x.name != y.name {
+                push(changes, path, ChangeKind::Renamed { from: x.name, to: y.name });
+            }
+            diff_elements(path, &x.values, &y.values, trees, changes);
+        }
+        (Value::NamedVariant(x), Value::NamedVariant(y)) => {
+            if x.variant != y.variant {
+                push(changes, path, switched(a, b));
+                return;
+            }
+            if x.name != y.name {
+                push(changes, path, ChangeKind::Renamed { from: x.name, to: y.name });
+            }
+            diff_entries(path, &x.fields, &y.fields, field_segment, trees, changes);
+        }
+        _ => push(changes, path, switched(a, b)),
+    }
+}
+
+/// Reports a switch between two variants of the same enum, or a type change otherwise
+fn switched(a: &Value, b: &Value) -> ChangeKind {
+    match (enum_of(a), enum_of(b)) {
+        (Some(x), Some(y)) if x == y => ChangeKind::VariantSwitched { from: a.clone(), to: b.clone() },
+        _ => ChangeKind::TypeChanged { from: a.clone(), to: b.clone() },
+    }
+}
+
+/// Returns the enum name of a variant
+fn enum_of(value: &Value) -> Option<&'static str> {
+    match value {
+        Value::UnitVariant(val) => Some(val.name),
+        Value::TupleVariant(val) => Some(val.name),
+        Value::NamedVariant(val) => Some(val.name),
+        _ => None,
+    }
+}
+
+fn field_segment(name: &&'static str) -> Segment {
+    Segment::from(*name)
+}
+
+/// Compares the entries of two maps, or the fields of two structs or variants, by key
+fn diff_entries<K: Ord>(
+    path: &mut Path,
+    a: &BTreeMap<K, Value>,
+    b: &BTreeMap<K, Value>,
+    segment: fn(&K) -> Segment,
+    trees: Trees<'_>,
+    changes: &mut Vec<Change>,
+) {
+    let mut others = b.iter().enumerate().peekable();
+    let mut added = Vec::new();
+    for (i, (key, x)) in a.iter().enumerate() {
+        added.extend(std::iter::from_fn(|| others.next_if(|(_, (other, _))| *other < key)));
+        path.push(segment(key));
+        match others.next_if(|(_, (other, _))| *other == key) {
+            Some((j, (_, y))) => diff_value(path, x, y, trees.child(i, j), changes),
+            None => push(changes, path, ChangeKind::Removed(x.clone())),
+        }
+        path.pop();
+    }
+
+    for (_, (key, y)) in added.into_iter().chain(others) {
+        path.push(segment(key));
+        push(changes, path, ChangeKind::Added(y.clone()));
+        path.pop();
+    }
+}
+
+fn diff_elements(path: &mut Path, a: &[Value], b: &[Value], trees: Trees<'_>, changes: &mut Vec<Change>) {
+    let mut removed = Vec::new();
+    let mut added = Vec::new();
+
+    for (x, y) in align(a.len(), b.len(), |i, j| trees.child(i, j).same(&a[i], &b[j])) {
+        match (x, y) {
+            (Some(i), Some(j)) => {
+                path.push(Segment::Index(i));
+                diff_value(path, &a[i], &b[j], trees.child(i, j), changes);
+                path.pop();
+            }
+            (Some(i), None) => removed.push(i),
+            (None, Some(j)) => added.push(j),
+            (None, None) => {}
+        }
+    }
+
+    for i in removed.into_iter().rev() {
+        path.push(Segment::Index(i));
+        push(changes, path, ChangeKind::Removed(a[i].clone()));
+        path.pop();
+    }
+
+    for j in added {
+        path.push(Segment::Index(j));
+        push(changes, path, ChangeKind::Added(b[j].clone()));
+        path.pop();
+    }
+}
+
+/// Aligns two sequences of lengths `n` and `m`, returning pairs of their indices, where
+/// `same(i, j)` tells whether the `i`th element of the first equals the `j`th of the second.
+///
+/// Equal elements are matched with a longest common subsequence. Between two matches, the
+/// remaining elements are paired by position so that they are compared recursively, and the
+/// leftovers are unmatched.
+fn align(n: usize, m: usize, same: impl Fn(usize, usize) -> bool) -> Vec<(Option<usize>, Option<usize>)> {
+    let prefix = (0..n.min(m)).take_while(|&k| same(k, k)).count();
+    let suffix = (0..(n - prefix).min(m - prefix))
+        .take_while(|&k| same(n - 1 - k, m - 1 - k))
+        .count();
+
+    let middle_n = n - prefix - suffix;
+    let middle_m = m - prefix - suffix;
+
+    let anchors = if middle_n.saturating_mul(middle_m) <= LCS_LIMIT {
+        lcs(middle_n, middle_m, |i, j| same(prefix + i, prefix + j))
+    } else {
+        Vec::new()
+    };
+
+    let mut pairs: Vec<_> = (0..prefix).map(|i| (Some(i), Some(i))).collect();
+    let (mut i, mut j) = (0, 0);
+    for (x, y) in anchors.into_iter().chain(std::iter::once((middle_n, middle_m))) {
+        let gap = (x - i).min(y - j);
+        for k in 0..gap {
+            pairs.push((Some(prefix + i + k), Some(prefix + j + k)));
+        }
+        for k in i + gap..x {
+            pairs.push((Some(prefix + k), None));
+        }
+        for k in j + gap..y {
+            pairs.push((None, Some(prefix + k)));
+        }
+        if x < middle_n {
+            pairs.push((Some(prefix + x), Some(prefix + y)));
+        }
+        (i, j) = (x + 1, y + 1);
+    }
+
+    let offset_n = n - suffix;
+    let offset_m = m - suffix;
+    pairs.extend((0..suffix).map(|k| (Some(offset_n + k), Some(offset_m + k))));
+    pairs
+}
+
+/// Returns the index pairs of a longest common subsequence of two sequences of lengths `n` and
+/// `m`, where `same(i, j)` tells whether their `i`th and `j`th elements are equal
+fn lcs(n: usize, m: usize, same: impl Fn(usize, usize) -> bool) -> Vec<(usize, usize)> {
+    let width = m + 1;
+    let mut table = vec![0u32; (n + 1) * width];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[i * width + j] = if same(i, j) {
+                table[(i + 1) * width + j + 1] + 1
+            } else {
+                table[(i + 1) * width + j].max(table[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if same(i, j) {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        } else if table[(i + 1) * width + j] >= table[i * width + j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+#[test]
+fn test_diff() {
+    use crate::value::{NamedStruct, NamedVariant, TupleStruct, UnitVariant};
+
+    fn named(name: &'static str, fields: Vec<(&'static str, Value)>) -> Value {
+        Value::NamedStruct(NamedStruct { name, fields: fields.into_iter().collect() })
+    }
+
+    let describe = |a: &Value, b: &Value| -> Vec<String> {
+        Value::diff(a, b).iter().map(ToString::to_string).collect()
+    };
+
+    let a = named("Config", vec![
+        ("port", Value::from(80u16)),
+        ("retries", Value::from(3u8)),
+        ("hosts", Value::from(vec!["a", "b", "c", "d"])),
+        ("mode", Value::UnitVariant(UnitVariant { name: "Mode", variant: "Fast" })),
+        ("old", Value::Unit),
+    ]);
+    let b = named("Settings", vec![
+        ("port", Value::from(8080u16)),
+        ("retries", Value::from(3u32)),
+        ("hosts", Value::from(vec!["x", "a", "c", "e"])),
+        ("mode", Value::NamedVariant(NamedVariant { name: "Mode", variant: "Slow", fields: BTreeMap::new() })),
+        ("new", Value::from('n')),
+    ]);
+
+    assert_eq!(describe(&a, &b), [
+        "/: renamed Config to Settings",
+        "/hosts/3: changed \"d\" to \"e\"",
+        "/hosts/1: removed \"b\"",
+        "/hosts/0: added \"x\"",
+        "/mode: variant switched from Mode::Fast to Mode::Slow",
+        "/old: removed ()",
+        "/port: changed 80u16 to 8080u16",
+        "/retries: number width changed from 3u8 to 3u32",
+        "/new: added 'n'",
+    ]);
+
+    let a = Value::from(vec![Value::from(1u8), Value::TupleStruct(TupleStruct { name: "P", values: vec![Value::from(1u8)] })]);
+    let b = Value::from(vec![Value::from("1"), Value::from(vec![1u8])]);
+    assert_eq!(describe(&a, &b), [
+        "/0: type changed from 1u8 to \"1\"",
+        "/1: type changed from P(1u8) to [1u8]",
+    ]);
+
+    assert!(Value::diff(&a, &a).is_empty());
+
+    let a = Value::UnitVariant(UnitVariant { name: "Mode", variant: "Fast" });
+    let b = Value::UnitVariant(UnitVariant { name: "Speed", variant: "Slow" });
+    assert_eq!(describe(&a, &b), ["/: type changed from Mode::Fast to Speed::Slow"]);
+}
@@ -0,0 +1,317 @@
+//! Stable content digests of [`Value`] trees
+//!
+//! [`Value::digest`] hashes a value with SHA-256 into a [`Digest`] that only depends on the
+//! contents of the value, not on the platform, the process or the Rust version, unlike the
+//! derived `Hash` of `Value`. Digests can be used as cache keys shared across processes.
+//!
+//! The hashed bytes are the [binary encoding](crate::binary) of the value with inline names,
+//! `SVB\x01` followed by the value, so the default digest of a value is the SHA-256 of
+//! [`Value::to_binary`]. [`DigestOptions`] change the encoding of numbers and of entries:
+//!
+//! - with [`ignore_widths`](DigestOptions::ignore_widths), integers are encoded as `i128`
+//!   numbers, or `u128` numbers above `i128::MAX`, and floats as `f64` numbers, with a single
+//!   NaN
+//! - with [`ignore_order`](DigestOptions::ignore_order), the entries of maps and the fields of
+//!   structs and variants are encoded as the count followed by the SHA-256 hashes of each entry,
+//!   in ascending order, rather than each key or field name followed by its value. The hash of
+//!   an entry is that of the encoded key, or of the field name as a string, followed by the
+//!   encoded value. Entries thus contribute to the digest regardless of their order, which
+//!   otherwise follows the order of keys. When only widths are ignored, entries are encoded in
+//!   the order of their encoded keys, so that maps whose keys only differ by width, and thus
+//!   may be ordered differently, digest equally.
+//!
+//! ```
+//! use serde_derive::Serialize;
+//! use serde_value::Value;
+//! use serde_value::digest::DigestOptions;
+//!
+//! #[derive(Serialize)]
+//! struct Query {
+//!     table: String,
+//!     limit: u32,
+//! }
+//!
+//! let value = serde_value::to_value(&Query { table: "users".to_string(), limit: 10 }).unwrap();
+//! assert_eq!(
+//!     value.digest().to_string(),
+//!     "93204b77f81dd51281fe63c841ed0edc7ccfcabc1f5381eb8047531e56ed7827",
+//! );
+//!
+//! let options = DigestOptions { ignore_widths: true, ..DigestOptions::default() };
+//! let wide = Value::from(vec![10u64, 20]);
+//! assert_eq!(Value::from(vec![10u8, 20]).digest_with(&options), wide.digest_with(&options));
+//! assert_ne!(Value::from(vec![10u8, 20]).digest(), wide.digest());
+//! ```
+
+mod sha256;
+
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::io::{Result, Write};
+use crate::binary::{write_number, write_str, write_varint, MAGIC};
+use crate::value::{Number, Value};
+
+pub(crate) use sha256::Sha256;
+
+/// How values are digested
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct DigestOptions {
+    /// Digests maps, structs and variants regardless of the order of their entries
+    pub ignore_order: bool,
+    /// Digests numbers of the same value equally, regardless of their kinds, as long as both
+    /// are integers or both are floats
+    pub ignore_widths: bool,
+}
+
+/// A SHA-256 hash of a [`Value`], see the [module documentation](self)
+///
+/// Displays as 64 lowercase hexadecimal digits.
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Digest(pub(crate) [u8; 32]);
+
+impl Digest {
+    /// The hash bytes
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+
+    /// The SHA-256 hash of `bytes`
+    pub(crate) fn of(bytes: &[u8]) -> Self {
+        let mut hasher = Sha256::new();
+        hasher.update(bytes);
+        Digest(hasher.finish())
+    }
+}
+
+impl From<Digest> for [u8; 32] {
+    fn from(digest: Digest) -> Self {
+        digest.0
+    }
+}
+
+impl Display for Digest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+impl Debug for Digest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Digest({self})")
+    }
+}
+
+impl Value {
+    /// The SHA-256 digest of this value, with the default options
+    pub fn digest(&self) -> Digest {
+        self.digest_with(&DigestOptions::default())
+    }
+
+    /// The SHA-256 digest of this value
+    pub fn digest_with(&self, options: &DigestOptions) -> Digest {
+        let mut hasher = Sha256::new();
+        hasher.update(MAGIC);
+        Digester { options }.write_value(&mut hasher, self).expect("hashing cannot fail");
+        Digest(hasher.finish())
+    }
+}
+
+/// Writes the digested encoding of values
+struct Digester<'o> {
+    options: &'o DigestOptions,
+}
+
+impl Digester<'_> {
+    fn write_number<W: Write + ?Sized>(&self, w: &mut W, number: &Number) -> Result<()> {
+        if !self.options.ignore_widths {
+            return write_number(w, number);
+        }
+        let widened = match *number {
+            Number::U8(v) => Number::from(i128::from(v)),
+            Number::U16(v) => Number::from(i128::from(v)),
+            Number::U32(v) => Number::from(i128::from(v)),
+            Number::U64(v) => Number::from(i128::from(v)),
+            Number::U128(v) => i128::try_from(v).map_or(Number::from(v), Number::from),
+            Number::I8(v) => Number::from(i128::from(v)),
+            Number::I16(v) => Number::from(i128::from(v)),
+            Number::I32(v) => Number::from(i128::from(v)),
+            Number::I64(v) => Number::from(i128::from(v)),
+            Number::I128(v) => Number::from(v),
+            Number::F32(v) if v.0.is_nan() => Number::from(f64::NAN),
+            Number::F32(v) => Number::from(f64::from(v.0)),
+            Number::F64(v) if v.0.is_nan() => Number::from(f64::NAN),
+            Number::F64(v) => Number::from(v.0),
+        };
+        write_number(w, &widened)
+    }
+
+    fn write_elements<W: Write + ?Sized>(&self, w: &mut W, values: &[Value]) -> Result<()> {
+        write_varint(w, values.len() as u64)?;
+        values.iter().try_for_each(|value| self.write_value(w, value))
+    }
+
+    /// Writes the entries of a map or the fields of a struct, each made of a key and a value
+    fn write_entries<'v, W: Write + ?Sized>(
+        &self,
+        w: &mut W,
+        entries: impl ExactSizeIterator<Item = (Key<'v>, &'v Value)>,
+    ) -> Result<()> {
+        write_varint(w, entries.len() as u64)?;
+        if !self.options.ignore_order && !self.options.ignore_widths {
+            return entries.into_iter().try_for_each(|(key, value)| {
+                self.write_key(w, key)?;
+                self.write_value(w, value)
+            });
+        }
+        if !self.options.ignore_order {
+            let mut encoded = entries
+                .map(|(key, value)| {
+                    let (mut key_bytes, mut value_bytes) = (Vec::new(), Vec::new());
+                    self.write_key(&mut key_bytes, key)?;
+                    self.write_value(&mut value_bytes, value)?;
+                    Ok((key_bytes, value_bytes))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            encoded.sort_unstable();
+            return encoded.iter().try_for_each(|(key, value)| {
+                w.write_all(key)?;
+                w.write_all(value)
+            });
+        }
+        let mut hashes = entries
+            .map(|(key, value)| {
+                let mut hasher = Sha256::new();
+                self.write_key(&mut hasher, key)?;
+                self.write_value(&mut hasher, value)?;
+                Ok(hasher.finish())
+            })
+            .collect::<Result<Vec<_>>>()?;
+        hashes.sort_unstable();
+        hashes.iter().try_for_each(|hash| w.write_all(hash))
+    }
+
+    fn write_key<W: Write + ?Sized>(&self, w: &mut W, key: Key<'_>) -> Result<()> {
+        match key {
+            Key::Value(key) => self.write_value(w, key),
+            Key::Field(name) => write_str(w, name),
+        }
+    }
+
+    fn write_fields<W: Write + ?Sized>(&self, w: &mut W, fields: &BTreeMap<&'static str, Value>) -> Result<()> {
+        self.write_entries(w, fields.iter().map(|(name, value)| (Key::Field(name), value)))
+    }
+
+    fn write_value<W: Write + ?Sized>(&self, w: &mut W, value: &Value) -> Result<()> {
+        match value {
+            Value::Unit => w.write_all(&[0]),
+            Value::Bool(val) => w.write_all(&[1 + *val as u8]),
+            Value::Char(val) => {
+                w.write_all(&[3])?;
+                write_varint(w, *val as u64)
+            }
+            Value::Number(val) => {
+                w.write_all(&[4])?;
+                self.write_number(w, val)
+            }
+            Value::String(val) => {
+                w.write_all(&[5])?;
+                write_str(w, val)
+            }
+            Value::Seq(values) => {
+                w.write_all(&[6])?;
+                self.write_elements(w, values)
+            }
+            Value::Map(map) => {
+                w.write_all(&[7])?;
+                self.write_entries(w, map.iter().map(|(key, value)| (Key::Value(key), value)))
+            }
+            Value::Tuple(values) => {
+                w.write_all(&[8])?;
+                self.write_elements(w, values)
+            }
+            Value::UnitStruct(val) => {
+                w.write_all(&[9])?;
+                write_str(w, val.name)
+            }
+            Value::TupleStruct(val) => {
+                w.write_all(&[10])?;
+                write_str(w, val.name)?;
+                self.write_elements(w, &val.values)
+            }
+            Value::NamedStruct(val) => {
+                w.write_all(&[11])?;
+                write_str(w, val.name)?;
+                self.write_fields(w, &val.fields)
+            }
+            Value::UnitVariant(val) => {
+                w.write_all(&[12])?;
+                write_str(w, val.name)?;
+                write_str(w, val.variant)
+            }
+            Value::TupleVariant(val) => {
+                w.write_all(&[13])?;
+                write_str(w, val.name)?;
+                write_str(w, val.variant)?;
+                self.write_elements(w, &val.values)
+            }
+            Value::NamedVariant(val) => {
+                w.write_all(&[14])?;
+                write_str(w, val.name)?;
+                write_str(w, val.variant)?;
+                self.write_fields(w, &val.fields)
+            }
+        }
+    }
+}
+
+/// The key of an entry: a map key or a field name
+#[derive(Copy, Clone)]
+enum Key<'v> {
+    Value(&'v Value),
+    Field(&'v str),
+}
+
+#[test]
+fn test_digest() {
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    assert_eq!(Digest::of(b"").to_string(), "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    assert_eq!(Digest::of(b"abc").to_string(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    let long = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+    assert_eq!(Digest::of(long).to_string(), "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+    assert_eq!(Digest::of(&[b'a'; 1000]).to_string(), "41edece42d63e8d9bf515a9ba6932e1c20cbc9f5a5d134645adb5db1b9737ea3");
+
+    #[derive(Serialize)]
+    struct Entry {
+        id: u64,
+        weight: f32,
+        tags: BTreeMap<u8, String>,
+    }
+
+    let value = to_value(&Entry { id: 300, weight: 0.5, tags: [(1, "a".to_string()), (200, "b".to_string())].into() }).unwrap();
+    assert_eq!(value.digest(), Digest::of(&value.to_binary()));
+    assert_eq!(value.digest(), value.clone().digest());
+    assert_eq!(format!("{:?}", value.digest()), format!("Digest({})", value.digest()));
+
+    let mut wide = value.clone();
+    wide["id"] = Value::from(300i16);
+    wide["weight"] = Value::from(0.5f64);
+    wide["tags"] = Value::Map([(Value::from(1u16), Value::from("a")), (Value::from(200i64), Value::from("b"))].into());
+    assert_ne!(value.digest(), wide.digest());
+
+    let loose = DigestOptions { ignore_order: true, ignore_widths: true };
+    assert_eq!(value.digest_with(&loose), wide.digest_with(&loose));
+    let widths = DigestOptions { ignore_widths: true, ..DigestOptions::default() };
+    assert_eq!(value.digest_with(&widths), wide.digest_with(&widths));
+
+    let keys = Value::Map([(Value::from(1u8), Value::Unit), (Value::from(2u16), Value::Unit)].into());
+    let swapped = Value::Map([(Value::from(1u16), Value::Unit), (Value::from(2u8), Value::Unit)].into());
+    assert_eq!(keys.digest_with(&widths), swapped.digest_with(&widths));
+    assert_ne!(keys.digest(), swapped.digest());
+    assert_eq!(keys.digest_with(&loose), swapped.digest_with(&loose));
+
+    assert_ne!(Value::from(1u8).digest_with(&loose), Value::from(1.0f64).digest_with(&loose));
+    assert_eq!(Value::from(f32::NAN).digest_with(&loose), Value::from(-f64::NAN).digest_with(&loose));
+    assert_ne!(wide.digest_with(&loose), Value::Unit.digest_with(&loose));
+}
@@ -0,0 +1,316 @@
+//! Owned transformation of [`Value`] trees
+//!
+//! [`Fold`] consumes a [`Value`] and rebuilds it bottom-up. Every hook returns an
+//! `Option`: returning `None` from a child hook removes that child from its parent, and any hook
+//! may return a value of a different variant than the one it was given. As with
+//! [`visit`](crate::visit), each hook has a default implementation calling the free function of
+//! the same name in this module, which folds the children and rebuilds the node.
+//!
+//! [`Value::transform`] is a shorthand for transforming every node with a closure along with
+//! its [`Path`], either top-down or bottom-up.
+//!
+//! ```
+//! use serde_value::{TupleStruct, Value};
+//! use serde_value::fold::Fold;
+//!
+//! /// Converts tuple structs into plain sequences
+//! struct Flatten;
+//!
+//! impl Fold for Flatten {
+//!     fn fold_tuple_struct(&mut self, value: TupleStruct) -> Option<Value> {
+//!         self.fold_seq(value.values)
+//!     }
+//! }
+//!
+//! let value = Value::from(Some(3u8));
+//! assert_eq!(Flatten.fold_value(value), Some(Value::from(vec![3u8])));
+//! ```
+
+use std::collections::BTreeMap;
+use crate::value::{NamedStruct, NamedVariant, Number, Path, Segment, TupleStruct, TupleVariant, UnitStruct, UnitVariant, Value};
+use crate::value::path::key_segment;
+
+/// An owned, rebuilding traversal of a [`Value`] tree, see the [module documentation](self)
+pub trait Fold {
+    fn fold_value(&mut self, value: Value) -> Option<Value> {
+        fold_value(self, value)
+    }
+
+    fn fold_unit(&mut self) -> Option<Value> {
+        Some(Value::Unit)
+    }
+
+    fn fold_bool(&mut self, value: bool) -> Option<Value> {
+        Some(Value::Bool(value))
+    }
+
+    fn fold_char(&mut self, value: char) -> Option<Value> {
+        Some(Value::Char(value))
+    }
+
+    fn fold_number(&mut self, value: Number) -> Option<Value> {
+        Some(Value::Number(value))
+    }
+
+    fn fold_string(&mut self, value: String) -> Option<Value> {
+        Some(Value::String(value))
+    }
+
+    fn fold_seq(&mut self, values: Vec<Value>) -> Option<Value> {
+        fold_seq(self, values)
+    }
+
+    fn fold_map(&mut self, map: BTreeMap<Value, Value>) -> Option<Value> {
+        fold_map(self, map)
+    }
+
+    fn fold_tuple(&mut self, values: Vec<Value>) -> Option<Value> {
+        fold_tuple(self, values)
+    }
+
+    fn fold_unit_struct(&mut self, value: UnitStruct) -> Option<Value> {
+        Some(Value::UnitStruct(value))
+    }
+
+    fn fold_tuple_struct(&mut self, value: TupleStruct) -> Option<Value> {
+        fold_tuple_struct(self, value)
+    }
+
+    fn fold_named_struct(&mut self, value: NamedStruct) -> Option<Value> {
+        fold_named_struct(self, value)
+    }
+
+    fn fold_unit_variant(&mut self, value: UnitVariant) -> Option<Value> {
+        Some(Value::UnitVariant(value))
+    }
+
+    fn fold_tuple_variant(&mut self, value: TupleVariant) -> Option<Value> {
+        fold_tuple_variant(self, value)
+    }
+
+    fn fold_named_variant(&mut self, value: NamedVariant) -> Option<Value> {
+        fold_named_variant(self, value)
+    }
+
+    /// Folds an element of a `Seq`, `Tuple`, `TupleStruct` or `TupleVariant` at its original
+    /// `index`
+    fn fold_element(&mut self, _index: usize, value: Value) -> Option<Value> {
+        self.fold_value(value)
+    }
+
+    /// Folds a field of a `NamedStruct` or `NamedVariant`, the returned name renames the field
+    fn fold_field(&mut self, name: &'static str, value: Value) -> Option<(&'static str, Value)> {
+        Some((name, self.fold_value(value)?))
+    }
+
+    /// Folds an entry of a `Map`, by default only the value is folded
+    fn fold_entry(&mut self, key: Value, value: Value) -> Option<(Value, Value)> {
+        Some((key, self.fold_value(value)?))
+    }
+}
+
+pub fn fold_value<F: Fold + ?Sized>(f: &mut F, value: Value) -> Option<Value> {
+    match value {
+        Value::Unit => f.fold_unit(),
+        Value::Bool(val) => f.fold_bool(val),
+        Value::Char(val) => f.fold_char(val),
+        Value::Number(val) => f.fold_number(val),
+        Value::String(val) => f.fold_string(val),
+        Value::Seq(values) => f.fold_seq(values),
+        Value::Map(map) => f.fold_map(map),
+        Value::Tuple(values) => f.fold_tuple(values),
+        Value::UnitStruct(val) => f.fold_unit_struct(val),
+        Value::TupleStruct(val) => f.fold_tuple_struct(val),
+        Value::NamedStruct(val) => f.fold_named_struct(val),
+        Value::UnitVariant(val) => f.fold_unit_variant(val),
+        Value::TupleVariant(val) => f.fold_tuple_variant(val),
+        Value::NamedVariant(val) => f.fold_named_variant(val),
+    }
+}
+
+/// Folds every element, dropping the removed ones
+pub fn fold_elements<F: Fold + ?Sized>(f: &mut F, values: Vec<Value>) -> Vec<Value> {
+    values.into_iter()
+        .enumerate()
+        .filter_map(|(index, value)| f.fold_element(index, value))
+        .collect()
+}
+
+/// Folds every field, dropping the removed ones
+pub fn fold_fields<F: Fold + ?Sized>(f: &mut F, fields: BTreeMap<&'static str, Value>) -> BTreeMap<&'static str, Value> {
+    fields.into_iter()
+        .filter_map(|(name, value)| f.fold_field(name, value))
+        .collect()
+}
+
+pub fn fold_seq<F: Fold + ?Sized>(f: &mut F, values: Vec<Value>) -> Option<Value> {
+    Some(Value::Seq(fold_elements(f, values)))
+}
+
+pub fn fold_map<F: Fold + ?Sized>(f: &mut F, map: BTreeMap<Value, Value>) -> Option<Value> {
+    Some(Value::Map(map.into_iter()
+        .filter_map(|(key, value)| f.fold_entry(key, value))
+        .collect()))
+}
+
+pub fn fold_tuple<F: Fold + ?Sized>(f: &mut F, values: Vec<Value>) -> Option<Value> {
+    Some(Value::Tuple(fold_elements(f, values)))
+}
+
+pub fn fold_tuple_struct<F: Fold + ?Sized>(f: &mut F, value: TupleStruct) -> Option<Value> {
+    Some(Value::TupleStruct(TupleStruct {
+        name: value.name,
+        values: fold_elements(f, value.values),
+    }))
+}
+
+pub fn fold_named_struct<F: Fold + ?Sized>(f: &mut F, value: NamedStruct) -> Option<Value> {
+    Some(Value::NamedStruct(NamedStruct {
+        name: value.name,
+        fields: fold_fields(f, value.fields),
+    }))
+}
+
+pub fn fold_tuple_variant<F: Fold + ?Sized>(f: &mut F, value: TupleVariant) -> Option<Value> {
+    Some(Value::TupleVariant(TupleVariant {
+        name: value.name,
+        variant: value.variant,
+        values: fold_elements(f, value.values),
+    }))
+}
+
+pub fn fold_named_variant<F: Fold + ?Sized>(f: &mut F, value: NamedVariant) -> Option<Value> {
+    Some(Value::NamedVariant(NamedVariant {
+        name: value.name,
+        variant: value.variant,
+        fields: fold_fields(f, value.fields),
+    }))
+}
+
+/// The order in which [`Value::transform`] visits nodes
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Order {
+    /// A node is transformed before its children, and the children of the transformed node are
+    /// visited afterwards
+    TopDown,
+    /// A node is transformed after its children have been transformed
+    BottomUp,
+}
+
+impl Value {
+    /// Transforms every node of this value with `f`, in the given [`Order`].
+    ///
+    /// `f` receives the path of each node from the root and returns its replacement, or `None`
+    /// to remove the node from its parent. Removing the root yields `Value::Unit`. Paths are
+    /// positions in the tree being transformed, so an element keeps its original index even
+    /// when earlier siblings are removed.
+    ///
+    /// ```
+    /// use serde_value::Value;
+    /// use serde_value::fold::Order;
+    ///
+    /// let value = Value::from(vec![1u8, 2, 3, 4]);
+    /// let odd = value.transform(Order::BottomUp, |_, node| match node.as_u8() {
+    ///     Some(n) if n % 2 == 0 => None,
+    ///     _ => Some(node),
+    /// });
+    /// assert_eq!(odd, Value::from(vec![1u8, 3]));
+    /// ```
+    pub fn transform<F>(self, order: Order, f: F) -> Value
+        where F: FnMut(&Path, Value) -> Option<Value> {
+        let mut transformer = Transformer { path: Path::root(), order, f };
+        transformer.fold_value(self).unwrap_or(Value::Unit)
+    }
+}
+
+struct Transformer<F> {
+    path: Path,
+    order: Order,
+    f: F,
+}
+
+impl<F: FnMut(&Path, Value) -> Option<Value>> Transformer<F> {
+    fn child<T>(&mut self, segment: Segment, fold: impl FnOnce(&mut Self) -> T) -> T {
+        self.path.push(segment);
+        let result = fold(self);
+        self.path.pop();
+        result
+    }
+}
+
+impl<F: FnMut(&Path, Value) -> Option<Value>> Fold for Transformer<F> {
+    fn fold_value(&mut self, value: Value) -> Option<Value> {
+        match self.order {
+            Order::TopDown => {
+                let value = (self.f)(&self.path, value)?;
+                fold_value(self, value)
+            }
+            Order::BottomUp => {
+                let value = fold_value(self, value)?;
+                (self.f)(&self.path, value)
+            }
+        }
+    }
+
+    fn fold_element(&mut self, index: usize, value: Value) -> Option<Value> {
+        self.child(Segment::Index(index), |this| this.fold_value(value))
+    }
+
+    fn fold_field(&mut self, name: &'static str, value: Value) -> Option<(&'static str, Value)> {
+        self.child(Segment::Field(name.to_string()), |this| Some((name, this.fold_value(value)?)))
+    }
+
+    fn fold_entry(&mut self, key: Value, value: Value) -> Option<(Value, Value)> {
+        self.child(key_segment(&key), |this| Some((key, this.fold_value(value)?)))
+    }
+}
+
+#[test]
+fn test_fold() {
+    struct Export;
+
+    impl Fold for Export {
+        fn fold_unit_variant(&mut self, value: UnitVariant) -> Option<Value> {
+            Some(Value::from(value.variant))
+        }
+
+        fn fold_field(&mut self, name: &'static str, value: Value) -> Option<(&'static str, Value)> {
+            match name {
+                "password" => None,
+                "user_name" => Some(("userName", self.fold_value(value)?)),
+                _ => Some((name, self.fold_value(value)?)),
+            }
+        }
+    }
+
+    let mut fields = BTreeMap::new();
+    fields.insert("user_name", Value::from("root"));
+    fields.insert("password", Value::from("hunter2"));
+    fields.insert("roles", Value::Seq(vec![Value::UnitVariant(UnitVariant { name: "Role", variant: "Admin" })]));
+    let value = Value::NamedStruct(NamedStruct { name: "User", fields });
+
+    let exported = Export.fold_value(value.clone()).unwrap();
+    assert_eq!(exported["userName"], Value::from("root"));
+    assert_eq!(exported["roles"], Value::from(vec!["Admin"]));
+    assert!(exported.get("password").is_none());
+    assert!(exported.get("user_name").is_none());
+
+    let mut seen = Vec::new();
+    let redacted = value.clone().transform(Order::TopDown, |path, node| {
+        seen.push(path.to_string());
+        if path.last() == Some(&Segment::from("password")) {
+            Some(Value::from("***"))
+        } else {
+            Some(node)
+        }
+    });
+    assert_eq!(redacted["password"], Value::from("***"));
+    assert_eq!(seen, ["", "/password", "/roles", "/roles/0", "/user_name"]);
+
+    let seq = value.transform(Order::BottomUp, |_, node| match node {
+        Value::NamedStruct(s) => Some(Value::Seq(s.fields.into_values().collect())),
+        Value::UnitVariant(_) => None,
+        node => Some(node),
+    });
+    assert_eq!(seq, Value::from(vec![Value::from("hunter2"), Value::Seq(vec![]), Value::from("root")]));
+}
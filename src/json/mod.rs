@@ -0,0 +1,211 @@
+//! JSON text for [`Value`] trees
+//!
+//! [`Value::to_json_string`] writes the [plain projection](crate::plain) of a value as JSON
+//! text, and [`Value::from_json_str`] reads JSON text back into plain data, without depending on
+//! a JSON library. Integers are written and read exactly, including `u128` and `i128` values
+//! that most JSON libraries round to floats.
+//!
+//! ```
+//! use serde_derive::Serialize;
+//! use serde_value::Value;
+//! use serde_value::json::JsonOptions;
+//!
+//! #[derive(Serialize)]
+//! struct Account {
+//!     id: u128,
+//!     name: String,
+//! }
+//!
+//! let value = serde_value::to_value(&Account { id: u128::MAX, name: "a\"b".to_string() }).unwrap();
+//! let json = value.to_json_string(&JsonOptions::default());
+//! assert_eq!(json, r#"{"id":340282366920938463463374607431768211455,"name":"a\"b"}"#);
+//!
+//! let read = Value::from_json_str(&json, &JsonOptions::default()).unwrap();
+//! assert_eq!(read["id"], Value::from(u128::MAX));
+//! ```
+
+mod read;
+mod write;
+
+use std::fmt::{Display, Formatter};
+use crate::plain::PlainOptions;
+use crate::value::Value;
+
+pub(crate) use write::write_json;
+
+/// How JSON text is written and read
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct JsonOptions {
+    /// How values are projected into plain data before being written
+    pub plain: PlainOptions,
+    /// Pretty prints the text, indenting nested values by this many spaces
+    pub indent: Option<usize>,
+    /// Escapes every character outside of ASCII as `\uXXXX`
+    pub ascii: bool,
+    /// The number kinds of the numbers read
+    pub numbers: NumberWidth,
+}
+
+/// The number kinds chosen by [`Value::from_json_str`]
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum NumberWidth {
+    /// Integers are `u64`, or `i64` when negative, widened to `u128` and `i128` when they do
+    /// not fit, other numbers are `f64`
+    #[default]
+    Wide,
+    /// Integers are the smallest kind they fit in, preferring unsigned kinds, other numbers are
+    /// `f32` when lossless and `f64` otherwise
+    Smallest,
+    /// Every number is `f64`, which rounds large integers
+    Float,
+}
+
+/// An error produced while reading JSON text
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonError {
+    /// The line of the error, starting at 1
+    pub line: usize,
+    /// The column of the error in characters, starting at 1
+    pub column: usize,
+    pub message: String,
+}
+
+impl std::error::Error for JsonError {}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at line {} column {}", self.message, self.line, self.column)
+    }
+}
+
+impl Value {
+    /// Writes this value as JSON text, projected into plain data with [`JsonOptions::plain`] as
+    /// [`Value::to_plain`] does, without copying the tree. See [`stream`](crate::stream) to
+    /// write the text to an `io::Write`.
+    ///
+    /// Floats are written in their shortest form that reads back to the same value, and keep a
+    /// fractional part or exponent. [`PlainOptions::non_finite`] should be plain data itself,
+    /// it is projected as well otherwise.
+    pub fn to_json_string(&self, options: &JsonOptions) -> String {
+        let mut json = String::new();
+        write_json(&mut json, self, options).expect("writing to a String cannot fail");
+        json
+    }
+
+    /// Reads JSON text into plain data: `null` becomes `Unit`, arrays `Seq`s and objects
+    /// `Map`s with `String` keys, keeping the last entry of duplicate keys. Numbers follow
+    /// [`JsonOptions::numbers`], other options are ignored.
+    ///
+    /// ```
+    /// use serde_value::{Number, Value};
+    /// use serde_value::json::{JsonOptions, NumberWidth};
+    ///
+    /// let options = JsonOptions { numbers: NumberWidth::Smallest, ..JsonOptions::default() };
+    /// let value = Value::from_json_str("[200, -1, 0.5, null]", &options).unwrap();
+    /// assert_eq!(value, Value::Seq(vec![
+    ///     Value::from(200u8),
+    ///     Value::from(-1i8),
+    ///     Value::from(0.5f32),
+    ///     Value::Unit,
+    /// ]));
+    ///
+    /// let err = Value::from_json_str("{\"a\": tru}", &options).unwrap_err();
+    /// assert_eq!(err.to_string(), "expected `true` at line 1 column 7");
+    /// ```
+    pub fn from_json_str(json: &str, options: &JsonOptions) -> Result<Value, JsonError> {
+        read::Reader::new(json, options.numbers).read_document()
+    }
+}
+
+#[test]
+fn test_json() {
+    use std::collections::BTreeMap;
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize)]
+    struct Sample {
+        text: String,
+        small: i8,
+        big: i128,
+        ratio: f64,
+        tags: Vec<char>,
+        nested: Option<Box<Sample>>,
+    }
+
+    let sample = Sample {
+        text: "tab\t quote\" é \u{1}".to_string(),
+        small: -3,
+        big: i128::MIN,
+        ratio: 1e300,
+        tags: vec!['x'],
+        nested: Some(Box::new(Sample {
+            text: String::new(),
+            small: 0,
+            big: 0,
+            ratio: 2.0,
+            tags: vec![],
+            nested: None,
+        })),
+    };
+    let value = to_value(&sample).unwrap();
+    let (plain, _) = value.to_plain(&PlainOptions::default());
+
+    let compact = value.to_json_string(&JsonOptions::default());
+    assert!(compact.contains(r#""text":"tab\t quote\" é \u0001""#));
+    assert!(compact.contains(r#""big":-170141183460469231731687303715884105728"#));
+    assert!(compact.contains(r#""ratio":1e300"#));
+    assert!(compact.contains(r#""ratio":2.0"#));
+    let read = Value::from_json_str(&compact, &JsonOptions::default()).unwrap();
+    assert_eq!(read["big"], Value::from(i128::MIN));
+    assert_eq!(read["small"], Value::from(-3i64));
+    assert_eq!(read["nested"]["tags"], Value::Seq(vec![]));
+
+    let options = JsonOptions { indent: Some(2), ascii: true, numbers: NumberWidth::Smallest, ..JsonOptions::default() };
+    let pretty = value.to_json_string(&options);
+    assert!(pretty.starts_with("{\n  \"big\": -170141183460469231731687303715884105728,\n"));
+    assert!(pretty.contains("\"tags\": []"));
+    assert!(pretty.contains("\\u00e9"));
+    let read = Value::from_json_str(&pretty, &options).unwrap();
+    assert_eq!(read["small"], Value::from(-3i8));
+    assert_eq!(read["ratio"], plain["ratio"]);
+    assert_eq!(read["nested"]["ratio"], Value::from(2.0f32));
+    assert_eq!(read["text"], plain["text"]);
+
+    let mut empty = BTreeMap::new();
+    empty.insert(Value::from("a"), Value::Map(BTreeMap::new()));
+    assert_eq!(Value::Map(empty).to_json_string(&options), "{\n  \"a\": {}\n}");
+
+    #[derive(Serialize)]
+    enum Shape {
+        Dot,
+        Pair(u8, u8),
+        Wrapped(Option<Box<Shape>>),
+        Named { r#type: u8, keys: BTreeMap<(u8, u8), u8> },
+    }
+
+    let shapes = to_value(&vec![
+        Shape::Dot,
+        Shape::Pair(1, 2),
+        Shape::Wrapped(Some(Box::new(Shape::Named { r#type: 1, keys: [((1, 2), 3)].into() }))),
+        Shape::Wrapped(Some(Box::new(Shape::Dot))),
+        Shape::Wrapped(None),
+    ]).unwrap();
+    let reprs = [
+        crate::repr::EnumRepr::External,
+        crate::repr::EnumRepr::Internal { tag: "type".to_string() },
+        crate::repr::EnumRepr::Adjacent { tag: "t".to_string(), content: "t".to_string() },
+        crate::repr::EnumRepr::Untagged,
+    ];
+    for enums in reprs {
+        for keys in [crate::plain::KeyPolicy::Stringify, crate::plain::KeyPolicy::Pairs] {
+            let options = JsonOptions { plain: PlainOptions { enums: enums.clone(), keys, ..PlainOptions::default() }, ..JsonOptions::default() };
+            let (plain, _) = shapes.to_plain(&options.plain);
+            assert_eq!(shapes.to_json_string(&options), plain.to_json_string(&options), "{enums:?} {keys:?}");
+        }
+    }
+
+    let non_finite = PlainOptions { non_finite: Value::Seq(vec![Value::from(f64::NAN)]), ..PlainOptions::default() };
+    let options = JsonOptions { plain: non_finite, ..JsonOptions::default() };
+    assert_eq!(Value::from(vec![f64::INFINITY]).to_json_string(&options), "[[null]]");
+}
@@ -0,0 +1,289 @@
+use std::collections::BTreeMap;
+use crate::value::{Number, NumberKind, Value};
+use super::{JsonError, NumberWidth};
+
+/// How deep arrays and objects may nest
+const MAX_DEPTH: usize = 128;
+
+pub(super) struct Reader<'s> {
+    text: &'s str,
+    pos: usize,
+    numbers: NumberWidth,
+    depth: usize,
+}
+
+impl<'s> Reader<'s> {
+    pub(super) fn new(text: &'s str, numbers: NumberWidth) -> Self {
+        Reader { text, pos: 0, numbers, depth: 0 }
+    }
+
+    /// Reads a single value followed by whitespace only
+    pub(super) fn read_document(mut self) -> Result<Value, JsonError> {
+        let value = self.value()?;
+        self.whitespace();
+        match self.peek() {
+            None => Ok(value),
+            Some(_) => self.error("trailing characters"),
+        }
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, JsonError> {
+        let before = &self.text[..self.pos];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+        Err(JsonError { line, column, message: message.into() })
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.text.as_bytes().get(self.pos).copied()
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        self.whitespace();
+        match self.peek() {
+            Some(found) if found == byte => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => self.error(format!("expected `{}`", byte as char)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'n') => self.literal("null", Value::Unit),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b'[') => self.nested(|this| {
+                let mut values = Vec::new();
+                this.entries(b']', |this| {
+                    values.push(this.value()?);
+                    Ok(())
+                })?;
+                Ok(Value::Seq(values))
+            }),
+            Some(b'{') => self.nested(|this| {
+                let mut map = BTreeMap::new();
+                this.entries(b'}', |this| {
+                    this.whitespace();
+                    if this.peek() != Some(b'"') {
+                        return this.error("expected a string key");
+                    }
+                    let key = this.string()?;
+                    this.expect(b':')?;
+                    map.insert(Value::String(key), this.value()?);
+                    Ok(())
+                })?;
+                Ok(Value::Map(map))
+            }),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => self.error("expected a value"),
+            None => self.error("unexpected end of text"),
+        }
+    }
+
+    fn literal(&mut self, literal: &str, value: Value) -> Result<Value, JsonError> {
+        if !self.text[self.pos..].starts_with(literal) {
+            return self.error(format!("expected `{literal}`"));
+        }
+        self.pos += literal.len();
+        Ok(value)
+    }
+
+    fn nested(&mut self, read: impl FnOnce(&mut Self) -> Result<Value, JsonError>) -> Result<Value, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return self.error("nested too deeply");
+        }
+        self.depth += 1;
+        self.pos += 1;
+        let value = read(self)?;
+        self.depth -= 1;
+        Ok(value)
+    }
+
+    /// Reads comma separated entries up to `close`, after the opening bracket
+    fn entries(&mut self, close: u8, mut entry: impl FnMut(&mut Self) -> Result<(), JsonError>) -> Result<(), JsonError> {
+        self.whitespace();
+        if self.peek() == Some(close) {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            entry(self)?;
+            self.whitespace();
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(found) if found == close => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                _ => return self.error(format!("expected `,` or `{}`", close as char)),
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.pos += 1;
+        let mut string = String::new();
+        loop {
+            let rest = &self.text[self.pos..];
+            let Some(c) = rest.chars().next() else {
+                return self.error("unterminated string");
+            };
+            match c {
+                '"' => {
+                    self.pos += 1;
+                    return Ok(string);
+                }
+                '\\' => {
+                    self.pos += 1;
+                    string.push(self.escape()?);
+                }
+                c if c < ' ' => return self.error("control character in string"),
+                c => {
+                    self.pos += c.len_utf8();
+                    string.push(c);
+                }
+            }
+        }
+    }
+
+    fn escape(&mut self) -> Result<char, JsonError> {
+        let c = match self.peek() {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => {
+                self.pos += 1;
+                let high = self.hex()?;
+                if !(0xD800..0xDC00).contains(&high) {
+                    return char::from_u32(high as u32).map_or_else(|| self.error("unpaired surrogate"), Ok);
+                }
+                if !self.text[self.pos..].starts_with("\\u") {
+                    return self.error("unpaired surrogate");
+                }
+                self.pos += 2;
+                let low = self.hex()?;
+                if !(0xDC00..0xE000).contains(&low) {
+                    return self.error("unpaired surrogate");
+                }
+                let c = 0x10000 + ((high as u32 - 0xD800) << 10) + (low as u32 - 0xDC00);
+                return Ok(char::from_u32(c).unwrap());
+            }
+            _ => return self.error("invalid escape"),
+        };
+        self.pos += 1;
+        Ok(c)
+    }
+
+    fn hex(&mut self) -> Result<u16, JsonError> {
+        let digits = self.text.get(self.pos..self.pos + 4)
+            .filter(|digits| digits.bytes().all(|b| b.is_ascii_hexdigit()));
+        match digits {
+            Some(digits) => {
+                self.pos += 4;
+                Ok(u16::from_str_radix(digits, 16).unwrap())
+            }
+            None => self.error("expected 4 hexadecimal digits"),
+        }
+    }
+
+    fn digits(&mut self) -> usize {
+        let start = self.pos;
+        while let Some(b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.pos;
+        let negative = self.peek() == Some(b'-');
+        if negative {
+            self.pos += 1;
+        }
+        let int_start = self.pos;
+        let int_len = self.digits();
+        if int_len == 0 || (int_len > 1 && self.text.as_bytes()[int_start] == b'0') {
+            self.pos = int_start;
+            return self.error("invalid number");
+        }
+        let mut integer = true;
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            integer = false;
+            if self.digits() == 0 {
+                return self.error("expected digits after `.`");
+            }
+        }
+        if let Some(b'e' | b'E') = self.peek() {
+            self.pos += 1;
+            integer = false;
+            if let Some(b'+' | b'-') = self.peek() {
+                self.pos += 1;
+            }
+            if self.digits() == 0 {
+                return self.error("expected exponent digits");
+            }
+        }
+
+        let text = &self.text[start..self.pos];
+        let float = || Number::from(text.parse::<f64>().unwrap());
+        let number = match (self.numbers, integer) {
+            (NumberWidth::Float, _) | (_, false) => float(),
+            (width, true) if negative => match text.parse::<i128>() {
+                Ok(val) if width == NumberWidth::Smallest => smallest(val),
+                Ok(val) => i64::try_from(val).map_or(Number::from(val), Number::from),
+                Err(_) => float(),
+            },
+            (width, true) => match text.parse::<u128>() {
+                Ok(val) if width == NumberWidth::Smallest => i128::try_from(val).map_or(Number::from(val), smallest),
+                Ok(val) => u64::try_from(val).map_or(Number::from(val), Number::from),
+                Err(_) => float(),
+            },
+        };
+        let number = match number {
+            Number::F64(val) if self.numbers == NumberWidth::Smallest && NumberKind::F32.fits(&number) => {
+                Number::from(val.0 as f32)
+            }
+            number => number,
+        };
+        Ok(Value::Number(number))
+    }
+}
+
+/// The smallest kind holding `val`, preferring unsigned kinds
+fn smallest(val: i128) -> Number {
+    if let Ok(val) = u8::try_from(val) {
+        Number::from(val)
+    } else if let Ok(val) = i8::try_from(val) {
+        Number::from(val)
+    } else if let Ok(val) = u16::try_from(val) {
+        Number::from(val)
+    } else if let Ok(val) = i16::try_from(val) {
+        Number::from(val)
+    } else if let Ok(val) = u32::try_from(val) {
+        Number::from(val)
+    } else if let Ok(val) = i32::try_from(val) {
+        Number::from(val)
+    } else if let Ok(val) = u64::try_from(val) {
+        Number::from(val)
+    } else if let Ok(val) = i64::try_from(val) {
+        Number::from(val)
+    } else {
+        Number::from(val)
+    }
+}
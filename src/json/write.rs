@@ -0,0 +1,128 @@
+use std::borrow::Cow;
+use std::fmt::{Error, Result, Write};
+use crate::plain::{walk, Sink};
+use crate::value::{Number, Value};
+use super::JsonOptions;
+
+/// Writes the plain projection of `value` as JSON text to `w`.
+///
+/// The projection is walked node by node as the text is written, following the rules of
+/// [`Value::to_plain`], so that the tree is never copied.
+pub(crate) fn write_json<W: Write + ?Sized>(w: &mut W, value: &Value, options: &JsonOptions) -> Result {
+    walk(value, &options.plain, None, &mut Writer { w, options, depth: 0 })
+}
+
+struct Writer<'w, 'o, W: ?Sized> {
+    w: &'w mut W,
+    options: &'o JsonOptions,
+    depth: usize,
+}
+
+impl<W: Write + ?Sized> Writer<'_, '_, W> {
+    fn write_string(&mut self, string: &str) -> Result {
+        self.w.write_char('"')?;
+        for c in string.chars() {
+            match c {
+                '"' => self.w.write_str("\\\"")?,
+                '\\' => self.w.write_str("\\\\")?,
+                '\n' => self.w.write_str("\\n")?,
+                '\r' => self.w.write_str("\\r")?,
+                '\t' => self.w.write_str("\\t")?,
+                '\u{8}' => self.w.write_str("\\b")?,
+                '\u{c}' => self.w.write_str("\\f")?,
+                c if c < ' ' || (self.options.ascii && !c.is_ascii()) => {
+                    let mut units = [0; 2];
+                    for unit in c.encode_utf16(&mut units) {
+                        write!(self.w, "\\u{unit:04x}")?;
+                    }
+                }
+                c => self.w.write_char(c)?,
+            }
+        }
+        self.w.write_char('"')
+    }
+
+    /// Opens an array or object
+    fn open(&mut self, open: char, len: usize) -> Result {
+        if len > 0 {
+            self.depth += 1;
+        }
+        self.w.write_char(open)
+    }
+
+    /// Starts the entry at `index` of an array or object
+    fn entry(&mut self, index: usize) -> Result {
+        if index > 0 {
+            self.w.write_char(',')?;
+        }
+        self.newline()
+    }
+
+    /// Closes an array or object
+    fn close(&mut self, close: char, len: usize) -> Result {
+        if len > 0 {
+            self.depth -= 1;
+            self.newline()?;
+        }
+        self.w.write_char(close)
+    }
+
+    fn newline(&mut self) -> Result {
+        if let Some(indent) = self.options.indent {
+            write!(self.w, "\n{:1$}", "", indent * self.depth)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write + ?Sized> Sink for Writer<'_, '_, W> {
+    type Error = Error;
+
+    fn null(&mut self) -> Result {
+        self.w.write_str("null")
+    }
+
+    fn bool(&mut self, val: bool) -> Result {
+        write!(self.w, "{val}")
+    }
+
+    fn number(&mut self, number: &Number) -> Result {
+        match number {
+            Number::F32(val) if !val.0.is_finite() => self.null(),
+            Number::F64(val) if !val.0.is_finite() => self.null(),
+            Number::F32(val) => write!(self.w, "{:?}", val.0),
+            Number::F64(val) => write!(self.w, "{:?}", val.0),
+            number => write!(self.w, "{number}"),
+        }
+    }
+
+    fn string(&mut self, val: Cow<'_, str>) -> Result {
+        self.write_string(&val)
+    }
+
+    fn begin_array(&mut self, len: usize) -> Result {
+        self.open('[', len)
+    }
+
+    fn element(&mut self, index: usize) -> Result {
+        self.entry(index)
+    }
+
+    fn end_array(&mut self, len: usize) -> Result {
+        self.close(']', len)
+    }
+
+    fn begin_object(&mut self, len: usize) -> Result {
+        self.open('{', len)
+    }
+
+    fn key(&mut self, index: usize, key: Cow<'_, str>) -> Result {
+        self.entry(index)?;
+        self.write_string(&key)?;
+        self.w.write_str(if self.options.indent.is_some() { ": " } else { ":" })
+    }
+
+    fn end_object(&mut self, len: usize) -> Result {
+        self.close('}', len)
+    }
+}
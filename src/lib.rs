@@ -32,7 +32,24 @@
 use serde::Serialize;
 use crate::ser::{Error, Serializer};
 
+pub mod arena;
+pub mod binary;
+pub mod dedup;
+pub mod diff;
+pub mod digest;
+pub mod fold;
+pub mod json;
+pub mod merge;
+pub mod merkle;
+pub mod patch;
+pub mod plain;
+pub mod query;
+pub mod redact;
+pub mod repr;
+pub mod schema;
 pub mod ser;
+pub mod stream;
+pub mod visit;
 mod value;
 
 pub use value::*;
@@ -0,0 +1,361 @@
+//! Deep merging of [`Value`] trees
+//!
+//! [`Value::merge`] layers one value over another, which suits configuration captured with
+//! [`to_value`](crate::to_value) from defaults, environment overrides and user settings:
+//!
+//! * `Map` entries and the fields of `NamedStruct`s, or `NamedVariant`s of the same variant,
+//!   are merged recursively, and entries only present on the right are added
+//! * the elements of `Tuple`s, `TupleStruct`s and `TupleVariant`s are merged by position
+//! * `Seq`s follow the [`SeqStrategy`]
+//! * any other pair of values of the same kind and type name is replaced by the right side,
+//!   as are an enum switching variants and an `Option` switching between `Some` and `None`
+//! * values of different kinds, or structs and enums of different names, are a conflict
+//!   resolved by the [`ConflictPolicy`]
+//!
+//! With [`MergeStrategy::merge_patch`], a `None` on the right removes the entry or field on
+//! the left instead of being merged, as in RFC 7396.
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//! use serde_value::Value;
+//! use serde_value::merge::MergeStrategy;
+//!
+//! let mut defaults = BTreeMap::new();
+//! defaults.insert(Value::from("port"), Value::from(80u16));
+//! defaults.insert(Value::from("hosts"), Value::from(vec!["a"]));
+//! let mut config = Value::Map(defaults);
+//!
+//! let mut overrides = BTreeMap::new();
+//! overrides.insert(Value::from("port"), Value::from(8080u16));
+//! config.merge(Value::Map(overrides), &MergeStrategy::default()).unwrap();
+//!
+//! assert_eq!(config["port"], Value::from(8080u16));
+//! assert_eq!(config["hosts"], Value::from(vec!["a"]));
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use crate::value::{Path, Segment, Value};
+use crate::value::path::key_segment;
+
+/// How [`Value::merge`] combines two trees
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MergeStrategy {
+    pub seq: SeqStrategy,
+    pub conflict: ConflictPolicy,
+    /// Whether a `None` on the right removes the map entry or field it is merged into
+    pub merge_patch: bool,
+}
+
+/// How two `Seq` values are merged
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum SeqStrategy {
+    /// The right sequence replaces the left one
+    #[default]
+    Replace,
+    /// The right elements are appended to the left ones
+    Append,
+    /// Elements at the same position are merged, extra right elements are appended
+    MergeByIndex,
+    /// Elements whose field or `String` map key of the given name are equal are merged, other
+    /// right elements are appended
+    MergeByKey(String),
+}
+
+/// How [`Value::merge`] resolves two values of different kinds
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum ConflictPolicy {
+    /// Fail with a [`MergeError`]
+    #[default]
+    Error,
+    /// Keep the left value
+    PreferLeft,
+    /// Take the right value
+    PreferRight,
+}
+
+/// A conflict between two values that could not be merged
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MergeError {
+    pub path: Path,
+    pub left: Box<Value>,
+    pub right: Box<Value>,
+}
+
+impl std::error::Error for MergeError {}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "cannot merge {:?} into {:?} at {:?}", self.right, self.left, self.path.to_string())
+    }
+}
+
+impl MergeStrategy {
+    /// RFC 7396 merge-patch: sequences are replaced, the right side wins conflicts and `None`
+    /// removes entries
+    pub fn merge_patch() -> Self {
+        Self {
+            seq: SeqStrategy::Replace,
+            conflict: ConflictPolicy::PreferRight,
+            merge_patch: true,
+        }
+    }
+}
+
+impl Value {
+    /// Merges `other` into this value, see [`merge`](crate::merge).
+    ///
+    /// If a conflict fails the merge, this value is left unchanged.
+    pub fn merge(&mut self, other: Value, strategy: &MergeStrategy) -> Result<(), MergeError> {
+        let mut merged = self.clone();
+        Merger { strategy, path: Path::root() }.merge(&mut merged, other)?;
+        *self = merged;
+        Ok(())
+    }
+}
+
+struct Merger<'s> {
+    strategy: &'s MergeStrategy,
+    path: Path,
+}
+
+/// Returns true for a `None`, encoded as a unit struct by [`to_value`](crate::to_value) or as an
+/// empty tuple struct by `Value::from`
+fn is_none(value: &Value) -> bool {
+    match value {
+        Value::UnitStruct(val) => val.name == "None",
+        Value::TupleStruct(val) => val.name == "None" && val.values.is_empty(),
+        _ => false,
+    }
+}
+
+fn is_option(value: &Value) -> bool {
+    match value {
+        Value::TupleStruct(val) if val.name == "Some" => val.values.len() == 1,
+        _ => is_none(value),
+    }
+}
+
+impl Merger<'_> {
+    fn merge(&mut self, left: &mut Value, right: Value) -> Result<(), MergeError> {
+        match (&mut *left, right) {
+            (Value::Map(l), Value::Map(r)) => {
+                for (key, value) in r {
+                    self.path.push(key_segment(&key));
+                    if self.strategy.merge_patch && is_none(&value) {
+                        l.remove(&key);
+                    } else {
+                        match l.get_mut(&key) {
+                            Some(existing) => self.merge(existing, value)?,
+                            None => {
+                                l.insert(key, value);
+                            }
+                        }
+                    }
+                    self.path.pop();
+                }
+                Ok(())
+            }
+            (Value::NamedStruct(l), Value::NamedStruct(r)) if l.name == r.name => {
+                self.merge_fields(&mut l.fields, r.fields)
+            }
+            (Value::NamedVariant(l), Value::NamedVariant(r)) if l.name == r.name && l.variant == r.variant => {
+                self.merge_fields(&mut l.fields, r.fields)
+            }
+            (Value::Tuple(l), Value::Tuple(r)) if l.len() == r.len() => self.merge_by_index(l, r),
+            (Value::TupleStruct(l), Value::TupleStruct(r)) if l.name == r.name && l.values.len() == r.values.len() => {
+                self.merge_by_index(&mut l.values, r.values)
+            }
+            (Value::TupleVariant(l), Value::TupleVariant(r)) if l.name == r.name && l.variant == r.variant && l.values.len() == r.values.len() => {
+                self.merge_by_index(&mut l.values, r.values)
+            }
+            (Value::Seq(l), Value::Seq(r)) => match &self.strategy.seq {
+                SeqStrategy::Replace => {
+                    *l = r;
+                    Ok(())
+                }
+                SeqStrategy::Append => {
+                    l.extend(r);
+                    Ok(())
+                }
+                SeqStrategy::MergeByIndex => self.merge_by_index(l, r),
+                SeqStrategy::MergeByKey(field) => self.merge_by_key(l, r, field),
+            },
+            (l, r) if same_type(l, &r) => {
+                *l = r;
+                Ok(())
+            }
+            (l, r) => match self.strategy.conflict {
+                ConflictPolicy::Error => Err(MergeError {
+                    path: self.path.clone(),
+                    left: Box::new(l.clone()),
+                    right: Box::new(r),
+                }),
+                ConflictPolicy::PreferLeft => Ok(()),
+                ConflictPolicy::PreferRight => {
+                    *l = r;
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    fn merge_fields(&mut self, left: &mut BTreeMap<&'static str, Value>, right: BTreeMap<&'static str, Value>) -> Result<(), MergeError> {
+        for (name, value) in right {
+            self.path.push(Segment::from(name));
+            if self.strategy.merge_patch && is_none(&value) {
+                left.remove(name);
+            } else {
+                match left.get_mut(name) {
+                    Some(existing) => self.merge(existing, value)?,
+                    None => {
+                        left.insert(name, value);
+                    }
+                }
+            }
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    fn merge_by_index(&mut self, left: &mut Vec<Value>, right: Vec<Value>) -> Result<(), MergeError> {
+        for (index, value) in right.into_iter().enumerate() {
+            match left.get_mut(index) {
+                Some(existing) => {
+                    self.path.push(Segment::Index(index));
+                    self.merge(existing, value)?;
+                    self.path.pop();
+                }
+                None => left.push(value),
+            }
+        }
+        Ok(())
+    }
+
+    fn merge_by_key(&mut self, left: &mut Vec<Value>, right: Vec<Value>, field: &str) -> Result<(), MergeError> {
+        for value in right {
+            let position = value.get(field)
+                .and_then(|key| left.iter().position(|existing| existing.get(field) == Some(key)));
+            match position {
+                Some(index) => {
+                    self.path.push(Segment::Index(index));
+                    self.merge(&mut left[index], value)?;
+                    self.path.pop();
+                }
+                None => left.push(value),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if `right` can replace `left` without a conflict: both are of the same kind
+/// and type name, variants of the same enum, or `Option`s
+fn same_type(left: &Value, right: &Value) -> bool {
+    fn enum_name(value: &Value) -> Option<&'static str> {
+        match value {
+            Value::UnitVariant(val) => Some(val.name),
+            Value::TupleVariant(val) => Some(val.name),
+            Value::NamedVariant(val) => Some(val.name),
+            _ => None,
+        }
+    }
+
+    fn struct_name(value: &Value) -> Option<&'static str> {
+        match value {
+            Value::UnitStruct(val) => Some(val.name),
+            Value::TupleStruct(val) => Some(val.name),
+            Value::NamedStruct(val) => Some(val.name),
+            _ => None,
+        }
+    }
+
+    if is_option(left) && is_option(right) {
+        return true;
+    }
+
+    if let (Some(l), Some(r)) = (enum_name(left), enum_name(right)) {
+        return l == r;
+    }
+
+    std::mem::discriminant(left) == std::mem::discriminant(right)
+        && struct_name(left) == struct_name(right)
+}
+
+#[test]
+fn test_merge() {
+    use crate::value::{NamedStruct, TupleVariant, UnitStruct, UnitVariant};
+
+    fn named(fields: Vec<(&'static str, Value)>) -> Value {
+        Value::NamedStruct(NamedStruct { name: "Config", fields: fields.into_iter().collect() })
+    }
+
+    fn server(name: &str, port: u16) -> Value {
+        let mut map = BTreeMap::new();
+        map.insert(Value::from("name"), Value::from(name));
+        map.insert(Value::from("port"), Value::from(port));
+        Value::Map(map)
+    }
+
+    let none = Value::UnitStruct(UnitStruct { name: "None" });
+    let defaults = named(vec![
+        ("debug", Value::from(false)),
+        ("servers", Value::Seq(vec![server("a", 80), server("b", 81)])),
+        ("proxy", Value::from(Some("p"))),
+        ("level", Value::from(1u8)),
+    ]);
+    let overrides = named(vec![
+        ("debug", Value::from(true)),
+        ("servers", Value::Seq(vec![server("b", 8081), server("c", 82)])),
+        ("proxy", none.clone()),
+    ]);
+
+    let mut merged = defaults.clone();
+    merged.merge(overrides.clone(), &MergeStrategy::default()).unwrap();
+    assert_eq!(merged["debug"], Value::from(true));
+    assert_eq!(merged["servers"], Value::Seq(vec![server("b", 8081), server("c", 82)]));
+    assert_eq!(merged["proxy"], none);
+    assert_eq!(merged["level"], Value::from(1u8));
+
+    let mut merged = defaults.clone();
+    let strategy = MergeStrategy { seq: SeqStrategy::MergeByKey("name".to_string()), ..MergeStrategy::merge_patch() };
+    merged.merge(overrides.clone(), &strategy).unwrap();
+    assert_eq!(merged["servers"], Value::Seq(vec![server("a", 80), server("b", 8081), server("c", 82)]));
+    assert!(merged.get("proxy").is_none());
+
+    let mut merged = defaults.clone();
+    let strategy = MergeStrategy { seq: SeqStrategy::Append, ..MergeStrategy::default() };
+    merged.merge(overrides.clone(), &strategy).unwrap();
+    assert_eq!(merged["servers"].as_seq().map(<[Value]>::len), Some(4));
+
+    let mut merged = defaults.clone();
+    let strategy = MergeStrategy { seq: SeqStrategy::MergeByIndex, ..MergeStrategy::default() };
+    merged.merge(overrides, &strategy).unwrap();
+    assert_eq!(merged["servers"], Value::Seq(vec![server("b", 8081), server("c", 82)]));
+
+    let mut level = named(vec![("mode", Value::UnitVariant(UnitVariant { name: "Mode", variant: "Fast" }))]);
+    let switched = named(vec![("mode", Value::TupleVariant(TupleVariant { name: "Mode", variant: "Slow", values: vec![Value::Unit] }))]);
+    level.merge(switched.clone(), &MergeStrategy::default()).unwrap();
+    assert_eq!(level, switched);
+
+    let conflicting = named(vec![("level", Value::from("high")), ("debug", Value::from(true))]);
+    let mut merged = defaults.clone();
+    let err = merged.merge(conflicting.clone(), &MergeStrategy::default()).unwrap_err();
+    assert_eq!(err.path, Path::parse("/level").unwrap());
+    assert_eq!(merged, defaults);
+
+    let strategy = MergeStrategy { conflict: ConflictPolicy::PreferLeft, ..MergeStrategy::default() };
+    merged.merge(conflicting.clone(), &strategy).unwrap();
+    assert_eq!((&merged["level"], &merged["debug"]), (&Value::from(1u8), &Value::from(true)));
+
+    let strategy = MergeStrategy { conflict: ConflictPolicy::PreferRight, ..MergeStrategy::default() };
+    merged.merge(conflicting, &strategy).unwrap();
+    assert_eq!(merged["level"], Value::from("high"));
+
+    let mut merged = defaults.clone();
+    merged.merge(named(vec![("proxy", Value::from(None::<u8>))]), &MergeStrategy::merge_patch()).unwrap();
+    assert!(merged.get("proxy").is_none());
+    let mut option = Value::from(Some(1u8));
+    option.merge(Value::from(None::<u8>), &MergeStrategy::default()).unwrap();
+    assert_eq!(option, Value::from(None::<u8>));
+}
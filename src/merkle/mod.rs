@@ -0,0 +1,217 @@
+//! Merkle trees of per-node hashes
+//!
+//! [`Value::merkle`] hashes every node of a value into a [`Merkle`] tree with the structure of
+//! the value: the children of a `Merkle` node are the hashes of the elements, map values or
+//! field values of the value node, in order. Two nodes have the same hash exactly when they are
+//! equal, barring SHA-256 collisions, so comparing the trees of two values only needs to descend
+//! into the nodes whose hashes differ. [`Value::diff_merkle`] computes a [diff](crate::diff)
+//! this way, in time proportional to the changed parts of large trees, once their Merkle trees
+//! are known.
+//!
+//! The hash of a node is the SHA-256 of its [binary encoding](crate::binary) with inline names,
+//! without header, in which every child is replaced by its 32-byte hash: the elements of
+//! sequences, tuples, tuple structs and tuple variants, the keys and values of maps, and the
+//! field values of structs and variants. The hash of a scalar is thus the SHA-256 of its
+//! encoding, and the hash of a root differs from its [digest](Value::digest).
+//!
+//! A `Merkle` tree is a snapshot: it is not updated when its value is edited, and has to be
+//! recomputed with [`Value::merkle`] before it is compared again.
+//!
+//! ```
+//! use serde_value::Value;
+//!
+//! let a = Value::from(vec![vec![1u8; 100], vec![2u8; 100]]);
+//! let mut b = a.clone();
+//! b[1][50] = Value::from(3u8);
+//!
+//! let (tree_a, tree_b) = (a.merkle(), b.merkle());
+//! assert_ne!(tree_a.hash(), tree_b.hash());
+//! assert_eq!(tree_a.children()[0].hash(), tree_b.children()[0].hash());
+//!
+//! let changes = Value::diff_merkle(&a, &tree_a, &b, &tree_b);
+//! assert_eq!(changes.len(), 1);
+//! assert_eq!(changes[0].path.to_string(), "/1/50");
+//! ```
+
+use std::io::{Result, Write};
+use crate::binary::{write_number, write_str, write_varint};
+use crate::digest::{Digest, Sha256};
+use crate::value::Value;
+
+/// The hashes of the nodes of a [`Value`], see the [module documentation](self)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Merkle {
+    hash: Digest,
+    children: Vec<Merkle>,
+}
+
+impl Merkle {
+    /// The hash of the node
+    pub fn hash(&self) -> Digest {
+        self.hash
+    }
+
+    /// The trees of the elements, map values or field values of the node, in order
+    pub fn children(&self) -> &[Merkle] {
+        &self.children
+    }
+
+    /// Returns true if the nodes of both trees are equal, comparing their hashes
+    pub fn same(&self, other: &Merkle) -> bool {
+        self.hash == other.hash
+    }
+}
+
+impl Value {
+    /// Hashes every node of this value into a [`Merkle`] tree
+    pub fn merkle(&self) -> Merkle {
+        let mut builder = Builder { hasher: Sha256::new(), children: Vec::new() };
+        builder.write_value(self).expect("hashing cannot fail");
+        let Builder { hasher, children } = builder;
+        Merkle { hash: Digest(hasher.finish()), children }
+    }
+}
+
+/// Hashes a node, building the trees of its children
+struct Builder {
+    hasher: Sha256,
+    children: Vec<Merkle>,
+}
+
+impl Builder {
+    /// Hashes a child in place of its encoding, keeping its tree unless it is a map key
+    fn write_child(&mut self, value: &Value, keep: bool) {
+        let tree = value.merkle();
+        self.hasher.update(tree.hash.as_bytes());
+        if keep {
+            self.children.push(tree);
+        }
+    }
+
+    fn write_elements(&mut self, values: &[Value]) -> Result<()> {
+        write_varint(&mut self.hasher, values.len() as u64)?;
+        values.iter().for_each(|value| self.write_child(value, true));
+        Ok(())
+    }
+
+    fn write_fields<'v>(&mut self, fields: impl ExactSizeIterator<Item = (&'v &'static str, &'v Value)>) -> Result<()> {
+        write_varint(&mut self.hasher, fields.len() as u64)?;
+        for (name, value) in fields {
+            write_str(&mut self.hasher, name)?;
+            self.write_child(value, true);
+        }
+        Ok(())
+    }
+
+    fn write_value(&mut self, value: &Value) -> Result<()> {
+        let w = &mut self.hasher;
+        match value {
+            Value::Unit => w.write_all(&[0]),
+            Value::Bool(val) => w.write_all(&[1 + *val as u8]),
+            Value::Char(val) => {
+                w.write_all(&[3])?;
+                write_varint(w, *val as u64)
+            }
+            Value::Number(val) => {
+                w.write_all(&[4])?;
+                write_number(w, val)
+            }
+            Value::String(val) => {
+                w.write_all(&[5])?;
+                write_str(w, val)
+            }
+            Value::Seq(values) => {
+                w.write_all(&[6])?;
+                self.write_elements(values)
+            }
+            Value::Map(map) => {
+                w.write_all(&[7])?;
+                write_varint(w, map.len() as u64)?;
+                for (key, value) in map {
+                    self.write_child(key, false);
+                    self.write_child(value, true);
+                }
+                Ok(())
+            }
+            Value::Tuple(values) => {
+                w.write_all(&[8])?;
+                self.write_elements(values)
+            }
+            Value::UnitStruct(val) => {
+                w.write_all(&[9])?;
+                write_str(w, val.name)
+            }
+            Value::TupleStruct(val) => {
+                w.write_all(&[10])?;
+                write_str(w, val.name)?;
+                self.write_elements(&val.values)
+            }
+            Value::NamedStruct(val) => {
+                w.write_all(&[11])?;
+                write_str(w, val.name)?;
+                self.write_fields(val.fields.iter())
+            }
+            Value::UnitVariant(val) => {
+                w.write_all(&[12])?;
+                write_str(w, val.name)?;
+                write_str(w, val.variant)
+            }
+            Value::TupleVariant(val) => {
+                w.write_all(&[13])?;
+                write_str(w, val.name)?;
+                write_str(w, val.variant)?;
+                self.write_elements(&val.values)
+            }
+            Value::NamedVariant(val) => {
+                w.write_all(&[14])?;
+                write_str(w, val.name)?;
+                write_str(w, val.variant)?;
+                self.write_fields(val.fields.iter())
+            }
+        }
+    }
+}
+
+#[test]
+fn test_merkle() {
+    use std::collections::BTreeMap;
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize, Clone)]
+    struct Node {
+        id: u32,
+        labels: BTreeMap<String, String>,
+        children: Vec<Node>,
+    }
+
+    let leaf = |id| Node { id, labels: [("kind".to_string(), "leaf".to_string())].into(), children: vec![] };
+    let tree = Node { id: 0, labels: BTreeMap::new(), children: (1..=20).map(leaf).collect() };
+    let a = to_value(&tree).unwrap();
+    let merkle = a.merkle();
+    assert_eq!(merkle, a.clone().merkle());
+    assert_eq!(merkle.children().len(), 3);
+    assert_eq!(merkle.children()[0].children().len(), 20);
+    assert_eq!(Value::from(5u8).merkle().hash(), Digest::of(&[4, 0, 5]));
+
+    let mut edited = tree.clone();
+    edited.children[4].labels.insert("kind".to_string(), "root".to_string());
+    edited.children.push(leaf(21));
+    let b = to_value(&edited).unwrap();
+    let changes = Value::diff_merkle(&a, &merkle, &b, &b.merkle());
+    assert_eq!(changes, Value::diff(&a, &b));
+    assert_eq!(changes.iter().map(ToString::to_string).collect::<Vec<_>>(), [
+        "/children/4/labels/kind: changed \"leaf\" to \"root\"",
+        "/children/20: added Node { children: [], id: 21u32, labels: {\"kind\": \"leaf\"} }",
+    ]);
+
+    let keys = Value::Map([(Value::from(1u8), Value::Unit)].into());
+    let other = Value::Map([(Value::from(2u8), Value::Unit)].into());
+    assert!(!keys.merkle().same(&other.merkle()));
+    assert!(keys.merkle().children()[0].same(&other.merkle().children()[0]));
+    assert!(Value::diff_merkle(&a, &merkle, &a, &merkle).is_empty());
+
+    let stale = Value::Unit.merkle();
+    assert_eq!(Value::diff_merkle(&a, &stale, &b, &b.merkle()), Value::diff(&a, &b));
+    assert_eq!(Value::diff_merkle(&b, &merkle, &a, &stale), Value::diff(&b, &a));
+}
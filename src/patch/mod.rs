@@ -0,0 +1,303 @@
+//! RFC 6902-style patches for [`Value`] trees
+//!
+//! A [`Patch`] is a list of [`Operation`]s addressed by [`Path`]. Operations follow the
+//! semantics of JSON Patch, extended to every compound [`Value`] variant through
+//! [`Value::insert_at`] and [`Value::remove_at`]: struct and variant fields behave like object
+//! members, and the elements of tuples and tuple structs or variants behave like array
+//! elements. Since serde type names are part of a [`Value`], [`Operation::Rename`] is added to
+//! change the name of a struct or enum in place.
+//!
+//! A patch can be built from a [`diff`](crate::diff) with [`Patch::diff`], and is applied
+//! atomically by [`Value::apply_patch`]:
+//!
+//! ```
+//! use serde_value::Value;
+//! use serde_value::patch::Patch;
+//!
+//! let before = Value::from(vec!["a", "b", "c"]);
+//! let after = Value::from(vec!["b", "c", "d"]);
+//!
+//! let patch = Patch::diff(&before, &after);
+//! let mut value = before.clone();
+//! value.apply_patch(&patch).unwrap();
+//! assert_eq!(value, after);
+//! ```
+
+use std::fmt::{Display, Formatter};
+use crate::diff::{Change, ChangeKind};
+use crate::value::{Path, PathError, Value};
+
+/// A single patch operation
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Operation {
+    /// Inserts `value` at `path`, see [`Value::insert_at`]
+    Add {
+        path: Path,
+        value: Value,
+    },
+    /// Removes the value at `path`, see [`Value::remove_at`]
+    Remove {
+        path: Path,
+    },
+    /// Replaces the existing value at `path`
+    Replace {
+        path: Path,
+        value: Value,
+    },
+    /// Removes the value at `from` and adds it at `path`
+    Move {
+        from: Path,
+        path: Path,
+    },
+    /// Adds a copy of the value at `from` at `path`
+    Copy {
+        from: Path,
+        path: Path,
+    },
+    /// Checks that the value at `path` equals `value`
+    Test {
+        path: Path,
+        value: Value,
+    },
+    /// Renames the struct or enum at `path`, keeping its contents
+    Rename {
+        path: Path,
+        name: &'static str,
+    },
+}
+
+/// A list of [`Operation`]s, applied in order
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Patch {
+    pub operations: Vec<Operation>,
+}
+
+/// An error produced while applying a [`Patch`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PatchError {
+    /// The index of the failing operation in the patch
+    pub index: usize,
+    pub kind: PatchErrorKind,
+}
+
+/// The reason a [`Patch`] failed to apply
+#[derive(Clone, Debug, PartialEq)]
+pub enum PatchErrorKind {
+    /// A path could not be followed
+    Path(PathError),
+    /// A [`Operation::Test`] did not hold
+    TestFailed {
+        path: Path,
+        expected: Box<Value>,
+        actual: Option<Box<Value>>,
+    },
+    /// A [`Operation::Move`] would move a value into one of its own descendants
+    MoveIntoDescendant {
+        from: Path,
+        path: Path,
+    },
+    /// A [`Operation::Rename`] targets a value without a type name
+    NotNamed(Path),
+}
+
+impl std::error::Error for PatchError {}
+
+impl Display for PatchError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "patch operation {} failed: ", self.index)?;
+        match &self.kind {
+            PatchErrorKind::Path(err) => write!(f, "{err}"),
+            PatchErrorKind::TestFailed { path, expected, actual: Some(actual) } => {
+                write!(f, "expected {expected:?} at {:?}, found {actual:?}", path.to_string())
+            }
+            PatchErrorKind::TestFailed { path, expected, actual: None } => {
+                write!(f, "expected {expected:?} at {:?}, found nothing", path.to_string())
+            }
+            PatchErrorKind::MoveIntoDescendant { from, path } => {
+                write!(f, "cannot move {:?} into its descendant {:?}", from.to_string(), path.to_string())
+            }
+            PatchErrorKind::NotNamed(path) => write!(f, "value at {:?} has no type name", path.to_string()),
+        }
+    }
+}
+
+impl From<Vec<Operation>> for Patch {
+    fn from(operations: Vec<Operation>) -> Self {
+        Self { operations }
+    }
+}
+
+impl FromIterator<Operation> for Patch {
+    fn from_iter<I: IntoIterator<Item=Operation>>(iter: I) -> Self {
+        Self { operations: iter.into_iter().collect() }
+    }
+}
+
+impl Patch {
+    /// Builds the patch turning `a` into `b`, see [`Value::diff`]
+    pub fn diff(a: &Value, b: &Value) -> Self {
+        Self::from_changes(&Value::diff(a, b))
+    }
+
+    /// Converts a list of changes, in the order produced by [`Value::diff`], into a patch
+    pub fn from_changes(changes: &[Change]) -> Self {
+        changes.iter()
+            .map(|Change { path, kind }| {
+                let path = path.clone();
+                match kind {
+                    ChangeKind::Added(value) => Operation::Add { path, value: value.clone() },
+                    ChangeKind::Removed(_) => Operation::Remove { path },
+                    ChangeKind::Changed { to, .. }
+                    | ChangeKind::TypeChanged { to, .. }
+                    | ChangeKind::VariantSwitched { to, .. } => Operation::Replace { path, value: to.clone() },
+                    ChangeKind::NumberWidthChanged { to, .. } => Operation::Replace { path, value: Value::Number(*to) },
+                    ChangeKind::Renamed { to, .. } => Operation::Rename { path, name: to },
+                }
+            })
+            .collect()
+    }
+}
+
+impl Operation {
+    fn apply(&self, value: &mut Value) -> Result<(), PatchErrorKind> {
+        match self {
+            Operation::Add { path, value: new } => {
+                value.insert_at(path, new.clone()).map_err(PatchErrorKind::Path)?;
+            }
+            Operation::Remove { path } => {
+                value.remove_at(path).map_err(PatchErrorKind::Path)?;
+            }
+            Operation::Replace { path, value: new } => {
+                *target(value, path)? = new.clone();
+            }
+            Operation::Move { from, path } => {
+                if from == path {
+                    return target(value, from).map(drop);
+                }
+                if path.starts_with(from) {
+                    return Err(PatchErrorKind::MoveIntoDescendant { from: from.clone(), path: path.clone() });
+                }
+                let moved = value.remove_at(from).map_err(PatchErrorKind::Path)?;
+                value.insert_at(path, moved).map_err(PatchErrorKind::Path)?;
+            }
+            Operation::Copy { from, path } => {
+                let copied = target(value, from)?.clone();
+                value.insert_at(path, copied).map_err(PatchErrorKind::Path)?;
+            }
+            Operation::Test { path, value: expected } => {
+                let actual = value.get_path(path);
+                if actual != Some(expected) {
+                    return Err(PatchErrorKind::TestFailed {
+                        path: path.clone(),
+                        expected: Box::new(expected.clone()),
+                        actual: actual.cloned().map(Box::new),
+                    });
+                }
+            }
+            Operation::Rename { path, name } => {
+                let slot = match target(value, path)? {
+                    Value::UnitStruct(val) => &mut val.name,
+                    Value::TupleStruct(val) => &mut val.name,
+                    Value::NamedStruct(val) => &mut val.name,
+                    Value::UnitVariant(val) => &mut val.name,
+                    Value::TupleVariant(val) => &mut val.name,
+                    Value::NamedVariant(val) => &mut val.name,
+                    _ => return Err(PatchErrorKind::NotNamed(path.clone())),
+                };
+                *slot = name;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn target<'v>(value: &'v mut Value, path: &Path) -> Result<&'v mut Value, PatchErrorKind> {
+    value.get_path_mut(path).ok_or_else(|| PatchErrorKind::Path(PathError::NotFound(path.clone())))
+}
+
+impl Value {
+    /// Applies every operation of `patch` in order.
+    ///
+    /// The patch is atomic: if any operation fails, `self` is left unchanged and the error
+    /// reports the index of the failing operation.
+    pub fn apply_patch(&mut self, patch: &Patch) -> Result<(), PatchError> {
+        let mut patched = self.clone();
+        for (index, operation) in patch.operations.iter().enumerate() {
+            operation.apply(&mut patched).map_err(|kind| PatchError { index, kind })?;
+        }
+        *self = patched;
+        Ok(())
+    }
+}
+
+#[test]
+fn test_patch() {
+    use std::collections::BTreeMap;
+    use crate::value::{NamedStruct, NamedVariant, Segment, TupleVariant, UnitVariant};
+
+    fn state(name: &'static str, items: Vec<Value>, fields: Vec<(&'static str, Value)>) -> Value {
+        let mut all: BTreeMap<_, _> = fields.into_iter().collect();
+        all.insert("items", Value::Seq(items));
+        Value::NamedStruct(NamedStruct { name, fields: all })
+    }
+
+    let point = |x: i32| Value::TupleVariant(TupleVariant { name: "Shape", variant: "Point", values: vec![Value::from(x)] });
+    let before = state("State", vec![point(1), Value::from(vec![1u8, 2, 3]), point(3), Value::from("x")], vec![
+        ("mode", Value::UnitVariant(UnitVariant { name: "Mode", variant: "On" })),
+        ("count", Value::from(3u8)),
+        ("gone", Value::Unit),
+    ]);
+    let after = state("StateV2", vec![Value::from("y"), point(1), Value::from(vec![3u8, 2, 1, 0]), point(4)], vec![
+        ("mode", Value::NamedVariant(NamedVariant { name: "Mode", variant: "Off", fields: BTreeMap::new() })),
+        ("count", Value::from(3u64)),
+        ("added", Value::from('a')),
+    ]);
+
+    let patch = Patch::diff(&before, &after);
+    let mut value = before.clone();
+    value.apply_patch(&patch).unwrap();
+    assert_eq!(value, after);
+
+    let path = |p: &str| Path::parse(p).unwrap();
+    let mut value = before.clone();
+    value.apply_patch(&Patch::from(vec![
+        Operation::Test { path: path("/count"), value: Value::from(3u8) },
+        Operation::Move { from: path("/items/3"), path: path("/items/0") },
+        Operation::Copy { from: path("/items/1"), path: path("/items/-") },
+        Operation::Replace { path: path("/items/2/0"), value: Value::from(9u8) },
+        Operation::Remove { path: path("/gone") },
+        Operation::Rename { path: path("/mode"), name: "Switch" },
+    ])).unwrap();
+    assert_eq!(value["items"], Value::Seq(vec![Value::from("x"), point(1), Value::from(vec![9u8, 2, 3]), point(3), point(1)]));
+    assert_eq!(value["mode"], Value::UnitVariant(UnitVariant { name: "Switch", variant: "On" }));
+    assert!(value.get("gone").is_none());
+
+    let mut value = before.clone();
+    let err = value.apply_patch(&Patch::from(vec![
+        Operation::Remove { path: path("/gone") },
+        Operation::Add { path: path("/items/9"), value: Value::Unit },
+    ])).unwrap_err();
+    assert_eq!(err.index, 1);
+    assert_eq!(err.kind, PatchErrorKind::Path(PathError::OutOfBounds { path: path("/items/9"), len: 4 }));
+    assert_eq!(value, before);
+
+    let err = value.apply_patch(&Patch::from(vec![
+        Operation::Test { path: path("/count"), value: Value::from(3u16) },
+    ])).unwrap_err();
+    assert_eq!(err.to_string(), "patch operation 0 failed: expected 3u16 at \"/count\", found 3u8");
+
+    let err = value.apply_patch(&Patch::from(vec![
+        Operation::Move { from: path("/items"), path: path("/items/0") },
+    ])).unwrap_err();
+    assert!(matches!(err.kind, PatchErrorKind::MoveIntoDescendant { .. }));
+
+    value.apply_patch(&Patch::from(vec![
+        Operation::Add { path: path("/new"), value: Value::Unit },
+    ])).unwrap();
+    assert_eq!(value["new"], Value::Unit);
+
+    let err = value.apply_patch(&Patch::from(vec![
+        Operation::Add { path: Path::from(vec![Segment::Key(Value::from(1u8))]), value: Value::Unit },
+    ])).unwrap_err();
+    assert!(matches!(err.kind, PatchErrorKind::Path(PathError::UnknownField(_))));
+}
@@ -0,0 +1,318 @@
+//! Projection of [`Value`] trees into plain data
+//!
+//! Many sinks only understand the JSON data model: null, booleans, numbers, strings, arrays
+//! and objects. [`Value::to_plain`] projects any value into that subset, with `Unit` values
+//! for null, `Seq`s for arrays and `Map`s with `String` keys for objects, and reports every
+//! conversion that lost information along the way.
+//!
+//! ```
+//! use serde_derive::Serialize;
+//! use serde_value::Value;
+//! use serde_value::plain::{LossyKind, PlainOptions};
+//!
+//! #[derive(Serialize)]
+//! struct Point(i32, i32);
+//!
+//! let value = serde_value::to_value(&vec![Some(Point(1, 2)), None]).unwrap();
+//! let (plain, report) = value.to_plain(&PlainOptions::default());
+//! assert_eq!(plain, Value::Seq(vec![Value::from(vec![1i32, 2]), Value::Unit]));
+//! assert_eq!(report.lossy[0].kind, LossyKind::StructName("Point"));
+//! ```
+
+mod walk;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt::{Display, Formatter};
+use crate::repr::EnumRepr;
+use crate::value::{Number, Path, Value};
+
+pub(crate) use walk::{walk, Sink};
+
+/// How [`Value::to_plain`] projects values
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PlainOptions {
+    /// The name of a field added to the objects of named structs, holding the struct name
+    pub type_field: Option<String>,
+    /// How enum variants are tagged
+    pub enums: EnumRepr,
+    /// How maps with keys that are not strings are projected
+    pub keys: KeyPolicy,
+    /// The value replacing NaN and infinite floats
+    pub non_finite: Value,
+}
+
+impl Default for PlainOptions {
+    fn default() -> Self {
+        PlainOptions {
+            type_field: None,
+            enums: EnumRepr::External,
+            keys: KeyPolicy::default(),
+            non_finite: Value::Unit,
+        }
+    }
+}
+
+/// How maps with keys that are not strings are projected
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum KeyPolicy {
+    /// Boolean, character, number and unit keys are written as strings, other maps become
+    /// arrays of pairs
+    #[default]
+    Stringify,
+    /// The map becomes an array of `[key, value]` pairs
+    Pairs,
+}
+
+/// The lossy conversions made by [`Value::to_plain`]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PlainReport {
+    pub lossy: Vec<Lossy>,
+}
+
+impl PlainReport {
+    /// Whether the projection kept all the information of the value
+    pub fn is_lossless(&self) -> bool {
+        self.lossy.is_empty()
+    }
+}
+
+/// A lossy conversion of the value at `path`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Lossy {
+    /// The path of the converted value, in the original tree
+    pub path: Path,
+    pub kind: LossyKind,
+}
+
+impl Display for Lossy {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}: ", self.path.to_string())?;
+        match &self.kind {
+            LossyKind::Char => write!(f, "char written as a string"),
+            LossyKind::Tuple => write!(f, "tuple written as an array"),
+            LossyKind::StructName(name) => write!(f, "struct name {name} dropped"),
+            LossyKind::EnumName(name) => write!(f, "enum name {name} dropped"),
+            LossyKind::Untagged { name, variant } => write!(f, "variant {name}::{variant} untagged"),
+            LossyKind::ExternallyTagged { name, variant } => {
+                write!(f, "variant {name}::{variant} externally tagged, it cannot be internally tagged")
+            }
+            LossyKind::Pairs => write!(f, "map written as an array of pairs"),
+            LossyKind::StringKeys => write!(f, "map keys written as strings"),
+            LossyKind::KeyCollision(key) => write!(f, "object keys collide as {key:?}"),
+            LossyKind::NonFinite => write!(f, "non-finite float replaced"),
+        }
+    }
+}
+
+/// A kind of lossy conversion
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LossyKind {
+    /// A `Char` became a string
+    Char,
+    /// A `Tuple` or `TupleStruct` became an array
+    Tuple,
+    /// The name of a struct was dropped
+    StructName(&'static str),
+    /// The name of the enum of a tagged variant was dropped
+    EnumName(&'static str),
+    /// The names of an untagged variant were dropped
+    Untagged {
+        name: &'static str,
+        variant: &'static str,
+    },
+    /// A tuple variant was externally tagged, as it has no internally tagged representation
+    ExternallyTagged {
+        name: &'static str,
+        variant: &'static str,
+    },
+    /// A map became an array of pairs
+    Pairs,
+    /// The keys of a map became strings
+    StringKeys,
+    /// Several entries of an object have the same key, and only the last one was kept: map keys
+    /// written as the same string, a field named like [`PlainOptions::type_field`], which holds
+    /// the struct name, or equal tag and content keys of an adjacently tagged variant
+    KeyCollision(String),
+    /// A NaN or infinite float was replaced by [`PlainOptions::non_finite`]
+    NonFinite,
+}
+
+impl Value {
+    /// Projects this value into the JSON data model, following `options`:
+    ///
+    /// - `Unit`, unit structs and `None` become `Unit`, `Some` its inner value
+    /// - `Char`s become strings
+    /// - `Tuple`s and tuple structs become sequences, newtype structs their inner value
+    /// - named structs become maps with `String` keys, with an optional type field
+    /// - enum variants are tagged as [`PlainOptions::enums`], internally tagged tuple variants
+    ///   are externally tagged
+    /// - maps with other keys than strings follow [`PlainOptions::keys`]
+    /// - NaN and infinite floats become [`PlainOptions::non_finite`]
+    ///
+    /// The returned report lists the lossy conversions, in the order of the tree.
+    pub fn to_plain(&self, options: &PlainOptions) -> (Value, PlainReport) {
+        let mut report = PlainReport::default();
+        let mut builder = Builder::default();
+        let Ok(()) = walk(self, options, Some(&mut report), &mut builder);
+        (builder.value.unwrap_or(Value::Unit), report)
+    }
+}
+
+/// An array or object being built
+enum Frame {
+    Array(Vec<Value>),
+    /// An object, with the key of its next entry
+    Object(BTreeMap<Value, Value>, String),
+}
+
+/// Builds the projected value
+#[derive(Default)]
+struct Builder {
+    stack: Vec<Frame>,
+    value: Option<Value>,
+}
+
+impl Builder {
+    fn push(&mut self, value: Value) -> Result<(), Infallible> {
+        match self.stack.last_mut() {
+            Some(Frame::Array(values)) => values.push(value),
+            Some(Frame::Object(map, key)) => {
+                map.insert(Value::String(std::mem::take(key)), value);
+            }
+            None => self.value = Some(value),
+        }
+        Ok(())
+    }
+}
+
+impl Sink for Builder {
+    type Error = Infallible;
+
+    fn null(&mut self) -> Result<(), Infallible> {
+        self.push(Value::Unit)
+    }
+
+    fn bool(&mut self, val: bool) -> Result<(), Infallible> {
+        self.push(Value::Bool(val))
+    }
+
+    fn number(&mut self, number: &Number) -> Result<(), Infallible> {
+        self.push(Value::Number(*number))
+    }
+
+    fn string(&mut self, val: Cow<'_, str>) -> Result<(), Infallible> {
+        self.push(Value::String(val.into_owned()))
+    }
+
+    fn begin_array(&mut self, len: usize) -> Result<(), Infallible> {
+        self.stack.push(Frame::Array(Vec::with_capacity(len)));
+        Ok(())
+    }
+
+    fn element(&mut self, _index: usize) -> Result<(), Infallible> {
+        Ok(())
+    }
+
+    fn end_array(&mut self, _len: usize) -> Result<(), Infallible> {
+        match self.stack.pop() {
+            Some(Frame::Array(values)) => self.push(Value::Seq(values)),
+            _ => unreachable!(),
+        }
+    }
+
+    fn begin_object(&mut self, _len: usize) -> Result<(), Infallible> {
+        self.stack.push(Frame::Object(BTreeMap::new(), String::new()));
+        Ok(())
+    }
+
+    fn key(&mut self, _index: usize, key: Cow<'_, str>) -> Result<(), Infallible> {
+        if let Some(Frame::Object(_, next)) = self.stack.last_mut() {
+            *next = key.into_owned();
+        }
+        Ok(())
+    }
+
+    fn end_object(&mut self, _len: usize) -> Result<(), Infallible> {
+        match self.stack.pop() {
+            Some(Frame::Object(map, _)) => self.push(Value::Map(map)),
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[test]
+fn test_plain() {
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize)]
+    struct Reading {
+        sensor: char,
+        values: Vec<f64>,
+        at: (u8, u8),
+        state: State,
+    }
+
+    #[derive(Serialize)]
+    enum State {
+        Ok,
+        Faulty { code: u16 },
+        Pair(u8, u8),
+    }
+
+    let reading = Reading { sensor: 'a', values: vec![1.5, f64::NAN], at: (1, 2), state: State::Faulty { code: 7 } };
+    let value = to_value(&reading).unwrap();
+
+    let options = PlainOptions {
+        type_field: Some("$type".to_string()),
+        enums: EnumRepr::Adjacent { tag: "kind".to_string(), content: "data".to_string() },
+        non_finite: Value::from("NaN"),
+        ..PlainOptions::default()
+    };
+    let (plain, report) = value.to_plain(&options);
+    assert_eq!(plain["$type"], Value::from("Reading"));
+    assert_eq!(plain["sensor"], Value::from("a"));
+    assert_eq!(plain["values"], Value::Seq(vec![Value::from(1.5f64), Value::from("NaN")]));
+    assert_eq!(plain["at"], Value::from(vec![1u8, 2]));
+    assert_eq!(plain["state"]["kind"], Value::from("Faulty"));
+    assert_eq!(plain["state"]["data"]["code"], Value::from(7u16));
+    let lossy: Vec<_> = report.lossy.iter().map(ToString::to_string).collect();
+    assert_eq!(lossy, [
+        "\"/at\": tuple written as an array",
+        "\"/sensor\": char written as a string",
+        "\"/state\": enum name State dropped",
+        "\"/values/1\": non-finite float replaced",
+    ]);
+
+    let internal = PlainOptions { enums: EnumRepr::Internal { tag: "t".to_string() }, ..PlainOptions::default() };
+    let (plain, report) = to_value(&State::Pair(1, 2)).unwrap().to_plain(&internal);
+    assert_eq!(plain["Pair"], Value::from(vec![1u8, 2]));
+    assert_eq!(report.lossy[1].kind, LossyKind::ExternallyTagged { name: "State", variant: "Pair" });
+    let (plain, _) = to_value(&State::Ok).unwrap().to_plain(&internal);
+    assert_eq!(plain["t"], Value::from("Ok"));
+
+    let mut map = BTreeMap::new();
+    map.insert(Value::from(1u8), Value::from('x'));
+    map.insert(Value::from("1"), Value::Unit);
+    let (plain, report) = Value::Map(map.clone()).to_plain(&PlainOptions::default());
+    assert_eq!(plain, Value::Map([(Value::from("1"), Value::Unit)].into()));
+    assert!(report.lossy.contains(&Lossy { path: Path::root(), kind: LossyKind::StringKeys }));
+    assert!(report.lossy.iter().any(|lossy| lossy.kind == LossyKind::KeyCollision("1".to_string())));
+
+    let pairs = PlainOptions { keys: KeyPolicy::Pairs, ..PlainOptions::default() };
+    let (plain, _) = Value::Map(map).to_plain(&pairs);
+    assert_eq!(plain[0], Value::Seq(vec![Value::from(1u8), Value::from("x")]));
+    assert!(Value::from(vec!["a"]).to_plain(&pairs).1.is_lossless());
+
+    let typed = PlainOptions { type_field: Some("sensor".to_string()), ..PlainOptions::default() };
+    let (plain, report) = value.to_plain(&typed);
+    assert_eq!(plain["sensor"], Value::from("Reading"));
+    assert!(report.lossy.contains(&Lossy { path: Path::root(), kind: LossyKind::KeyCollision("sensor".to_string()) }));
+
+    let same = PlainOptions { enums: EnumRepr::Adjacent { tag: "k".to_string(), content: "k".to_string() }, ..PlainOptions::default() };
+    let (plain, report) = to_value(&State::Pair(1, 2)).unwrap().to_plain(&same);
+    assert_eq!(plain["k"], Value::from(vec![1u8, 2]));
+    assert_eq!(report.lossy.last().map(|lossy| &lossy.kind), Some(&LossyKind::KeyCollision("k".to_string())));
+}
@@ -0,0 +1,391 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use crate::repr::EnumRepr;
+use crate::value::path::key_segment;
+use crate::value::{Number, Path, Segment, Value};
+use super::{KeyPolicy, Lossy, LossyKind, PlainOptions, PlainReport};
+
+/// Receives the plain projection of a value, node by node
+pub(crate) trait Sink {
+    type Error;
+
+    fn null(&mut self) -> Result<(), Self::Error>;
+
+    fn bool(&mut self, val: bool) -> Result<(), Self::Error>;
+
+    /// Receives a number, which is only non-finite when [`PlainOptions::non_finite`] holds one
+    fn number(&mut self, number: &Number) -> Result<(), Self::Error>;
+
+    fn string(&mut self, val: Cow<'_, str>) -> Result<(), Self::Error>;
+
+    /// Starts an array of `len` elements, each preceded by a call to [`Sink::element`]
+    fn begin_array(&mut self, len: usize) -> Result<(), Self::Error>;
+
+    fn element(&mut self, index: usize) -> Result<(), Self::Error>;
+
+    fn end_array(&mut self, len: usize) -> Result<(), Self::Error>;
+
+    /// Starts an object of `len` entries sorted by key, each value preceded by a call to
+    /// [`Sink::key`]
+    fn begin_object(&mut self, len: usize) -> Result<(), Self::Error>;
+
+    fn key(&mut self, index: usize, key: Cow<'_, str>) -> Result<(), Self::Error>;
+
+    fn end_object(&mut self, len: usize) -> Result<(), Self::Error>;
+}
+
+/// Projects `value` into `sink` following `options`, adding the lossy conversions to `report`.
+///
+/// This walk holds the rules of [`Value::to_plain`], which every projection goes through.
+pub(crate) fn walk<'v, S: Sink>(value: &'v Value, options: &'v PlainOptions, report: Option<&mut PlainReport>, sink: &mut S) -> Result<(), S::Error> {
+    let mut walker = Walker { options, path: Vec::new(), report };
+    walker.walk(Child::here(Node::Value(value)), sink)
+}
+
+/// A step from a node to one of its children, in the original tree
+#[derive(Copy, Clone)]
+enum Step<'v> {
+    Index(usize),
+    Field(&'static str),
+    Key(&'v Value),
+    /// Into the replacement of a non-finite float, which is not part of the path
+    Replaced,
+}
+
+/// The steps from a node to one of its children
+enum Route<'v> {
+    Same,
+    Step(Step<'v>),
+    Steps(Vec<Step<'v>>),
+}
+
+impl<'v> Route<'v> {
+    fn steps(&self) -> &[Step<'v>] {
+        match self {
+            Route::Same => &[],
+            Route::Step(step) => std::slice::from_ref(step),
+            Route::Steps(steps) => steps,
+        }
+    }
+}
+
+/// A part of the projection, projected when it is walked
+enum Node<'v> {
+    Value(&'v Value),
+    Str(&'v str),
+    Elements(&'v [Value]),
+    Fields(&'v BTreeMap<&'static str, Value>),
+    /// An entry of a map whose keys cannot be strings, projected as `[key, value]`
+    Pair(&'v Value, &'v Value),
+    /// A node already projected, while looking for the fields of an internally tagged variant
+    Projected(Box<Shape<'v>>),
+}
+
+struct Child<'v> {
+    route: Route<'v>,
+    node: Node<'v>,
+}
+
+impl<'v> Child<'v> {
+    fn here(node: Node<'v>) -> Self {
+        Child { route: Route::Same, node }
+    }
+
+    fn at(step: Step<'v>, node: Node<'v>) -> Self {
+        Child { route: Route::Step(step), node }
+    }
+
+    /// This child, reached from a node `prefix` steps above
+    fn after(self, prefix: &[Step<'v>]) -> Self {
+        if prefix.is_empty() {
+            return self;
+        }
+        let mut steps = prefix.to_vec();
+        steps.extend_from_slice(self.route.steps());
+        Child { route: Route::Steps(steps), node: self.node }
+    }
+}
+
+type Entries<'v> = Vec<(Cow<'v, str>, Child<'v>)>;
+
+/// The projection of a node, whose children are projected as they are walked
+enum Shape<'v> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(Cow<'v, str>),
+    Array(Vec<Child<'v>>),
+    Object(Entries<'v>),
+}
+
+/// Sorts object entries by key, the last entry of duplicate keys first
+fn sort(entries: &mut Entries<'_>) {
+    entries.reverse();
+    entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+}
+
+fn elements(values: &[Value]) -> Vec<Child<'_>> {
+    values.iter()
+        .enumerate()
+        .map(|(index, value)| Child::at(Step::Index(index), Node::Value(value)))
+        .collect()
+}
+
+fn field_entries<'v>(fields: &'v BTreeMap<&'static str, Value>) -> Entries<'v> {
+    fields.iter()
+        .map(|(name, value)| (Cow::Borrowed(*name), Child::at(Step::Field(name), Node::Value(value))))
+        .collect()
+}
+
+/// The key of a map projected as an object
+fn string_key(key: &Value) -> Option<Cow<'_, str>> {
+    Some(match key {
+        Value::Unit => Cow::Borrowed("null"),
+        Value::Bool(val) => Cow::Owned(val.to_string()),
+        Value::Char(val) => Cow::Owned(val.to_string()),
+        Value::Number(val) => Cow::Owned(val.to_string()),
+        Value::String(val) => Cow::Borrowed(val.as_str()),
+        _ => return None,
+    })
+}
+
+fn non_finite(number: &Number) -> bool {
+    match number {
+        Number::F32(val) => !val.0.is_finite(),
+        Number::F64(val) => !val.0.is_finite(),
+        _ => false,
+    }
+}
+
+/// An externally tagged variant
+fn external<'v>(variant: &'static str, content: Option<Child<'v>>) -> Shape<'v> {
+    match content {
+        None => Shape::String(Cow::Borrowed(variant)),
+        Some(content) => Shape::Object(vec![(Cow::Borrowed(variant), content)]),
+    }
+}
+
+struct Walker<'v, 'r> {
+    options: &'v PlainOptions,
+    /// The steps from the root to the node being projected
+    path: Vec<Step<'v>>,
+    report: Option<&'r mut PlainReport>,
+}
+
+impl<'v> Walker<'v, '_> {
+    /// Sorts object entries by key, keeping the last entry of duplicate keys, which are reported
+    fn object(&mut self, mut entries: Entries<'v>) -> Entries<'v> {
+        sort(&mut entries);
+        let collisions: Vec<_> = entries.windows(2)
+            .filter(|pair| pair[0].0 == pair[1].0)
+            .map(|pair| pair[0].0.to_string())
+            .collect();
+        for key in collisions {
+            self.lossy(LossyKind::KeyCollision(key));
+        }
+        entries.dedup_by(|(a, _), (b, _)| a == b);
+        entries
+    }
+
+    fn lossy(&mut self, kind: LossyKind) {
+        let Some(report) = &mut self.report else {
+            return;
+        };
+        let mut path = Path::root();
+        for step in &self.path {
+            match step {
+                Step::Index(index) => path.push(Segment::Index(*index)),
+                Step::Field(name) => path.push(Segment::from(*name)),
+                Step::Key(key) => path.push(key_segment(key)),
+                Step::Replaced => {}
+            }
+        }
+        report.lossy.push(Lossy { path, kind });
+    }
+
+    fn walk<S: Sink>(&mut self, child: Child<'v>, sink: &mut S) -> Result<(), S::Error> {
+        let len = self.path.len();
+        self.path.extend_from_slice(child.route.steps());
+        match self.project(child.node) {
+            Shape::Null => sink.null()?,
+            Shape::Bool(val) => sink.bool(val)?,
+            Shape::Number(number) => sink.number(&number)?,
+            Shape::String(val) => sink.string(val)?,
+            Shape::Array(elements) => {
+                let len = elements.len();
+                sink.begin_array(len)?;
+                for (index, element) in elements.into_iter().enumerate() {
+                    sink.element(index)?;
+                    self.walk(element, sink)?;
+                }
+                sink.end_array(len)?;
+            }
+            Shape::Object(entries) => {
+                let len = entries.len();
+                sink.begin_object(len)?;
+                for (index, (key, value)) in entries.into_iter().enumerate() {
+                    sink.key(index, key)?;
+                    self.walk(value, sink)?;
+                }
+                sink.end_object(len)?;
+            }
+        }
+        self.path.truncate(len);
+        Ok(())
+    }
+
+    /// Projects `node` without its children. Transparent wrappers such as `Some` add their
+    /// steps to the path, down to the node they hold.
+    fn project(&mut self, node: Node<'v>) -> Shape<'v> {
+        let value = match node {
+            Node::Value(value) => value,
+            Node::Str(val) => return Shape::String(Cow::Borrowed(val)),
+            Node::Elements(values) => return Shape::Array(elements(values)),
+            Node::Fields(fields) => return Shape::Object(field_entries(fields)),
+            Node::Pair(key, value) => {
+                return Shape::Array(vec![Child::at(Step::Key(key), Node::Value(key)), Child::at(Step::Key(key), Node::Value(value))]);
+            }
+            Node::Projected(shape) => return *shape,
+        };
+        let options = self.options;
+
+        match value {
+            Value::Unit => Shape::Null,
+            Value::Bool(val) => Shape::Bool(*val),
+            Value::String(val) => Shape::String(Cow::Borrowed(val)),
+            Value::Char(val) => {
+                self.lossy(LossyKind::Char);
+                Shape::String(Cow::Owned(val.to_string()))
+            }
+            // the replacement is projected as well, but its own non-finite floats are kept
+            Value::Number(number) if non_finite(number) && !self.path.iter().any(|step| matches!(step, Step::Replaced)) => {
+                self.lossy(LossyKind::NonFinite);
+                self.path.push(Step::Replaced);
+                self.project(Node::Value(&options.non_finite))
+            }
+            Value::Number(number) => Shape::Number(*number),
+            Value::Seq(values) => Shape::Array(elements(values)),
+            Value::Tuple(values) => {
+                self.lossy(LossyKind::Tuple);
+                Shape::Array(elements(values))
+            }
+            Value::Map(map) => self.map(map),
+            Value::UnitStruct(val) if val.name == "None" => Shape::Null,
+            Value::TupleStruct(val) if val.name == "Some" && val.values.len() == 1 => {
+                self.path.push(Step::Index(0));
+                self.project(Node::Value(&val.values[0]))
+            }
+            Value::UnitStruct(val) => {
+                self.lossy(LossyKind::StructName(val.name));
+                Shape::Null
+            }
+            Value::TupleStruct(val) => {
+                self.lossy(LossyKind::StructName(val.name));
+                if val.values.len() == 1 {
+                    self.path.push(Step::Index(0));
+                    return self.project(Node::Value(&val.values[0]));
+                }
+                self.lossy(LossyKind::Tuple);
+                Shape::Array(elements(&val.values))
+            }
+            Value::NamedStruct(val) => {
+                let mut entries = field_entries(&val.fields);
+                match &options.type_field {
+                    Some(field) => {
+                        entries.push((Cow::Borrowed(field.as_str()), Child::here(Node::Str(val.name))));
+                        entries = self.object(entries);
+                    }
+                    None => self.lossy(LossyKind::StructName(val.name)),
+                }
+                Shape::Object(entries)
+            }
+            Value::UnitVariant(val) => self.variant(val.name, val.variant, None, false),
+            Value::TupleVariant(val) if val.values.len() == 1 => {
+                let content = Child::at(Step::Index(0), Node::Value(&val.values[0]));
+                self.variant(val.name, val.variant, Some(content), false)
+            }
+            Value::TupleVariant(val) => {
+                self.variant(val.name, val.variant, Some(Child::here(Node::Elements(&val.values))), true)
+            }
+            Value::NamedVariant(val) => {
+                self.variant(val.name, val.variant, Some(Child::here(Node::Fields(&val.fields))), false)
+            }
+        }
+    }
+
+    fn map(&mut self, map: &'v BTreeMap<Value, Value>) -> Shape<'v> {
+        let string_keys = map.keys().all(Value::is_string);
+        let stringify = self.options.keys == KeyPolicy::Stringify && map.keys().all(|key| string_key(key).is_some());
+
+        if !string_keys && !stringify {
+            self.lossy(LossyKind::Pairs);
+            return Shape::Array(map.iter().map(|(key, value)| Child::here(Node::Pair(key, value))).collect());
+        }
+        let mut entries: Entries = map.iter()
+            .map(|(key, value)| (string_key(key).unwrap(), Child::at(Step::Key(key), Node::Value(value))))
+            .collect();
+        if !string_keys {
+            self.lossy(LossyKind::StringKeys);
+            entries = self.object(entries);
+        }
+        Shape::Object(entries)
+    }
+
+    /// Tags a variant holding `content`, which is `None` for unit variants
+    fn variant(&mut self, name: &'static str, variant: &'static str, content: Option<Child<'v>>, tuple: bool) -> Shape<'v> {
+        let options = self.options;
+        if options.enums == EnumRepr::Untagged {
+            self.lossy(LossyKind::Untagged { name, variant });
+        } else {
+            self.lossy(LossyKind::EnumName(name));
+        }
+        let tag = |key: &'v str| (Cow::Borrowed(key), Child::here(Node::Str(variant)));
+
+        match &options.enums {
+            EnumRepr::External => external(variant, content),
+            EnumRepr::Adjacent { tag: key, content: content_key } => {
+                let mut entries = vec![tag(key)];
+                entries.extend(content.map(|content| (Cow::Borrowed(content_key.as_str()), content)));
+                Shape::Object(self.object(entries))
+            }
+            EnumRepr::Untagged => match content {
+                None => Shape::Null,
+                Some(content) => {
+                    self.path.extend_from_slice(content.route.steps());
+                    self.project(content.node)
+                }
+            },
+            EnumRepr::Internal { tag: key } => {
+                let content = match content {
+                    None => return Shape::Object(vec![tag(key)]),
+                    Some(content) if tuple => content,
+                    Some(content) => match self.merged(content, key) {
+                        Ok(mut entries) => {
+                            entries.push(tag(key));
+                            return Shape::Object(self.object(entries));
+                        }
+                        Err(content) => content,
+                    },
+                };
+                self.lossy(LossyKind::ExternallyTagged { name, variant });
+                external(variant, Some(content))
+            }
+        }
+    }
+
+    /// The entries an internally tagged variant merges its `content` into, or the projected
+    /// content if it is not an object or has an entry named like the `tag`
+    fn merged(&mut self, content: Child<'v>, tag: &str) -> Result<Entries<'v>, Child<'v>> {
+        let len = self.path.len();
+        self.path.extend_from_slice(content.route.steps());
+        let shape = self.project(content.node);
+        let steps = self.path.split_off(len);
+        match shape {
+            Shape::Null => Ok(Vec::new()),
+            Shape::Object(entries) if entries.iter().all(|(key, _)| key != tag) => {
+                Ok(entries.into_iter().map(|(key, child)| (key, child.after(&steps))).collect())
+            }
+            shape => Err(Child { route: Route::Steps(steps), node: Node::Projected(Box::new(shape)) }),
+        }
+    }
+}
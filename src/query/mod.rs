@@ -0,0 +1,454 @@
+//! A JSONPath-like selector language over [`Value`] trees
+//!
+//! A query starts at the root `$` and applies a chain of steps, each of which maps the current
+//! set of nodes to a new one:
+//!
+//! | Step                 | Selects                                                          |
+//! |----------------------|------------------------------------------------------------------|
+//! | `.name`, `['name']`  | the field, or `String` map key, called `name`                    |
+//! | `.0`, `[0]`, `[-1]`  | the element at an index, negative indices count from the end     |
+//! | `[{5u32}]`           | the map entry with a typed key, using the [`Path`] key literals  |
+//! | `.*`, `[*]`          | every child                                                      |
+//! | `[1:5:2]`            | a slice of a sequence, with optional start, end and step         |
+//! | `[?expr]`            | every child for which the filter expression holds                |
+//! | `..name`, `..[...]`  | recursive descent: the selector applied to every descendant      |
+//! | `[a, 'b', 3]`        | the union of several selectors                                   |
+//!
+//! Filter expressions refer to the candidate child as `@` and to the root as `$`, followed by
+//! singular steps such as `@.port` or `@['key'][0]`. They support:
+//!
+//! * comparisons `==`, `!=`, `<`, `<=`, `>`, `>=` against literals: `'text'`, `"text"`, numbers
+//!   with or without a width suffix (compared numerically across widths), `true`, `false`, and
+//!   `null`, which matches `()` and `None`
+//! * existence tests, `?@.email` holds when the field exists
+//! * type-name predicates, `@ is Point` matches structs named `Point` and variants of an enum
+//!   named `Point`, `@ is Shape::Circle` matches a single variant, `@ is Shape::*` any variant
+//! * `!`, `&&`, `||` and parentheses
+//!
+//! ```
+//! use serde_derive::Serialize;
+//!
+//! #[derive(Serialize)]
+//! enum Shape {
+//!     Circle { radius: u32 },
+//!     Square { side: u32 },
+//! }
+//!
+//! let shapes = vec![Shape::Circle { radius: 1 }, Shape::Square { side: 2 }, Shape::Circle { radius: 3 }];
+//! let value = serde_value::to_value(&shapes).unwrap();
+//!
+//! let radii: Vec<_> = value.select("$[?@ is Shape::Circle && @.radius > 2].radius")
+//!     .unwrap()
+//!     .map(|(path, value)| (path.to_string(), value.as_u32()))
+//!     .collect();
+//! assert_eq!(radii, vec![("/2/radius".to_string(), Some(3))]);
+//! ```
+
+mod parser;
+
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use crate::value::{Path, Segment, Value};
+use crate::value::path::children;
+
+/// A parsed query, see the [module documentation](self) for the syntax
+#[derive(Clone, Debug, PartialEq)]
+pub struct Query {
+    steps: Vec<Step>,
+}
+
+/// An error produced while parsing a [`Query`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QueryError {
+    query: String,
+    position: usize,
+    message: String,
+}
+
+impl QueryError {
+    /// The byte offset in the query at which the error was found
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid query {:?} at offset {}: {}", self.query, self.position, self.message)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Step {
+    descendants: bool,
+    selectors: Vec<Selector>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Selector {
+    Name(String),
+    Index(isize),
+    Key(Value),
+    Wildcard,
+    Slice {
+        start: Option<isize>,
+        end: Option<isize>,
+        step: Option<isize>,
+    },
+    Filter(Expr),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Or(Box<Expr>, Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Exists(Operand),
+    Compare(Operand, CompareOp, Operand),
+    Is(Operand, TypePattern),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Operand {
+    Current(Path),
+    Root(Path),
+    Literal(Value),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum CompareOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct TypePattern {
+    name: String,
+    variant: Option<String>,
+}
+
+/// An iterator over the nodes selected by a [`Query`], with their paths
+pub struct Matches<'v> {
+    inner: std::vec::IntoIter<(Path, &'v Value)>,
+}
+
+impl<'v> Iterator for Matches<'v> {
+    type Item = (Path, &'v Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl ExactSizeIterator for Matches<'_> {}
+
+impl Query {
+    /// Parse a query, see the [module documentation](self) for the syntax
+    pub fn parse(query: &str) -> Result<Self, QueryError> {
+        parser::Parser::new(query).parse_query()
+    }
+
+    /// Returns the nodes of `root` selected by this query, in document order for each step
+    pub fn select<'v>(&self, root: &'v Value) -> Matches<'v> {
+        let mut nodes = vec![(Path::root(), root)];
+
+        for step in &self.steps {
+            let candidates = if step.descendants {
+                let mut all = Vec::new();
+                for (path, node) in nodes {
+                    descendants(path, node, &mut all);
+                }
+                all
+            } else {
+                nodes
+            };
+
+            nodes = Vec::new();
+            for (path, node) in candidates {
+                for selector in &step.selectors {
+                    selector.apply(&path, node, root, &mut nodes);
+                }
+            }
+        }
+
+        Matches {
+            inner: nodes.into_iter(),
+        }
+    }
+
+    /// Returns true if this query selects the node of `root` at `path`
+    pub fn matches(&self, root: &Value, path: &Path) -> bool {
+        self.select(root).any(|(p, _)| &p == path)
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl Value {
+    /// Parse `query` and return the nodes it selects, see [`query`](crate::query)
+    pub fn select(&self, query: &str) -> Result<Matches<'_>, QueryError> {
+        Ok(Query::parse(query)?.select(self))
+    }
+}
+
+fn descendants<'v>(path: Path, node: &'v Value, out: &mut Vec<(Path, &'v Value)>) {
+    let kids = children(node);
+    out.push((path.clone(), node));
+    for (segment, child) in kids {
+        descendants(path.child(segment), child, out);
+    }
+}
+
+fn sequence(value: &Value) -> Option<&[Value]> {
+    match value {
+        Value::Seq(values) | Value::Tuple(values) => Some(values),
+        Value::TupleStruct(val) => Some(&val.values),
+        Value::TupleVariant(val) => Some(&val.values),
+        _ => None,
+    }
+}
+
+impl Selector {
+    fn apply<'v>(&self, path: &Path, node: &'v Value, root: &'v Value, out: &mut Vec<(Path, &'v Value)>) {
+        match self {
+            Selector::Name(name) => {
+                let segment = Segment::Field(name.clone());
+                if let Some(child) = segment.resolve(node) {
+                    out.push((path.child(segment), child));
+                }
+            }
+            Selector::Index(index) => {
+                let segment = match sequence(node) {
+                    Some(values) => {
+                        let index = if *index < 0 { values.len() as isize + index } else { *index };
+                        match usize::try_from(index) {
+                            Ok(index) => Segment::Index(index),
+                            Err(_) => return,
+                        }
+                    }
+                    None => match usize::try_from(*index) {
+                        Ok(index) => Segment::Index(index),
+                        Err(_) => return,
+                    },
+                };
+                if let Some(child) = segment.resolve(node) {
+                    out.push((path.child(segment), child));
+                }
+            }
+            Selector::Key(key) => {
+                let segment = Segment::Key(key.clone());
+                if let Some(child) = segment.resolve(node) {
+                    out.push((path.child(segment), child));
+                }
+            }
+            Selector::Wildcard => {
+                for (segment, child) in children(node) {
+                    out.push((path.child(segment), child));
+                }
+            }
+            Selector::Slice { start, end, step } => {
+                let Some(values) = sequence(node) else { return };
+                for index in slice_indices(values.len(), *start, *end, step.unwrap_or(1)) {
+                    out.push((path.child(Segment::Index(index)), &values[index]));
+                }
+            }
+            Selector::Filter(expr) => {
+                for (segment, child) in children(node) {
+                    if expr.eval(child, root) {
+                        out.push((path.child(segment), child));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns the indices selected by a slice, following Python's semantics
+fn slice_indices(len: usize, start: Option<isize>, end: Option<isize>, step: isize) -> Vec<usize> {
+    let len = len as isize;
+    let normalize = |i: isize| if i < 0 { len + i } else { i };
+
+    let mut indices = Vec::new();
+    match step.cmp(&0) {
+        Ordering::Equal => {}
+        Ordering::Greater => {
+            let lower = start.map(normalize).unwrap_or(0).clamp(0, len);
+            let upper = end.map(normalize).unwrap_or(len).clamp(0, len);
+            let mut i = lower;
+            while i < upper {
+                indices.push(i as usize);
+                match i.checked_add(step) {
+                    Some(next) => i = next,
+                    None => break,
+                }
+            }
+        }
+        Ordering::Less => {
+            let upper = start.map(normalize).unwrap_or(len - 1).clamp(-1, len - 1);
+            let lower = end.map(normalize).unwrap_or(-1).clamp(-1, len - 1);
+            let mut i = upper;
+            while lower < i {
+                indices.push(i as usize);
+                match i.checked_add(step) {
+                    Some(next) => i = next,
+                    None => break,
+                }
+            }
+        }
+    }
+    indices
+}
+
+impl Expr {
+    fn eval(&self, current: &Value, root: &Value) -> bool {
+        match self {
+            Expr::Or(a, b) => a.eval(current, root) || b.eval(current, root),
+            Expr::And(a, b) => a.eval(current, root) && b.eval(current, root),
+            Expr::Not(a) => !a.eval(current, root),
+            Expr::Exists(operand) => operand.eval(current, root).is_some(),
+            Expr::Compare(a, op, b) => {
+                let (Some(a), Some(b)) = (a.eval(current, root), b.eval(current, root)) else {
+                    return false;
+                };
+                let ordering = compare(a, b);
+                match op {
+                    CompareOp::Eq => ordering == Some(Ordering::Equal),
+                    CompareOp::Ne => ordering != Some(Ordering::Equal),
+                    CompareOp::Lt => ordering == Some(Ordering::Less),
+                    CompareOp::Le => matches!(ordering, Some(Ordering::Less | Ordering::Equal)),
+                    CompareOp::Gt => ordering == Some(Ordering::Greater),
+                    CompareOp::Ge => matches!(ordering, Some(Ordering::Greater | Ordering::Equal)),
+                }
+            }
+            Expr::Is(operand, pattern) => operand.eval(current, root).is_some_and(|v| pattern.matches(v)),
+        }
+    }
+}
+
+impl Operand {
+    fn eval<'a>(&'a self, current: &'a Value, root: &'a Value) -> Option<&'a Value> {
+        match self {
+            Operand::Current(path) => current.get_path(path),
+            Operand::Root(path) => root.get_path(path),
+            Operand::Literal(value) => Some(value),
+        }
+    }
+}
+
+impl TypePattern {
+    fn matches(&self, value: &Value) -> bool {
+        let (name, variant) = match value {
+            Value::UnitStruct(val) => (val.name, None),
+            Value::TupleStruct(val) => (val.name, None),
+            Value::NamedStruct(val) => (val.name, None),
+            Value::UnitVariant(val) => (val.name, Some(val.variant)),
+            Value::TupleVariant(val) => (val.name, Some(val.variant)),
+            Value::NamedVariant(val) => (val.name, Some(val.variant)),
+            _ => return false,
+        };
+
+        if name != self.name {
+            return false;
+        }
+
+        match (&self.variant, variant) {
+            (None, _) => true,
+            (Some(pattern), Some(variant)) => pattern == "*" || pattern == variant,
+            (Some(_), None) => false,
+        }
+    }
+}
+
+/// Compares two values for a filter, numbers by value and `()` as equal to `None`
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    fn is_null(value: &Value) -> bool {
+        match value {
+            Value::Unit => true,
+            Value::UnitStruct(val) => val.name == "None",
+            _ => false,
+        }
+    }
+
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.numeric_cmp(b),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Char(a), Value::String(b)) => Some(a.to_string().as_str().cmp(b.as_str())),
+        (Value::String(a), Value::Char(b)) => Some(a.as_str().cmp(b.to_string().as_str())),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        (a, b) if is_null(a) && is_null(b) => Some(Ordering::Equal),
+        (a, b) => (a == b).then_some(Ordering::Equal),
+    }
+}
+
+#[test]
+fn test_query() {
+    use std::collections::BTreeMap;
+    use crate::value::{NamedStruct, TupleStruct, UnitVariant};
+
+    fn server(name: &str, port: u16, role: &'static str) -> Value {
+        let mut fields = BTreeMap::new();
+        fields.insert("name", Value::from(name));
+        fields.insert("port", Value::from(port));
+        fields.insert("role", Value::UnitVariant(UnitVariant { name: "Role", variant: role }));
+        Value::NamedStruct(NamedStruct { name: "Server", fields })
+    }
+
+    let mut limits = BTreeMap::new();
+    limits.insert(Value::from(1u32), Value::from("one"));
+    limits.insert(Value::from("two"), Value::from(2i64));
+
+    let mut fields = BTreeMap::new();
+    fields.insert("servers", Value::Seq(vec![server("a", 80, "Primary"), server("b", 8080, "Replica"), server("c", 443, "Replica")]));
+    fields.insert("limits", Value::Map(limits));
+    fields.insert("pair", Value::TupleStruct(TupleStruct { name: "Pair", values: vec![Value::from('x'), Value::from('y')] }));
+    let root = Value::NamedStruct(NamedStruct { name: "Config", fields });
+
+    let paths = |query: &str| -> Vec<String> {
+        root.select(query).unwrap().map(|(path, _)| path.to_string()).collect()
+    };
+
+    assert_eq!(paths("$"), vec![""]);
+    assert_eq!(paths("$.servers[0].name"), vec!["/servers/0/name"]);
+    assert_eq!(paths("$['servers'][-1]['port']"), vec!["/servers/2/port"]);
+    assert_eq!(paths("$.servers[::-2].name"), vec!["/servers/2/name", "/servers/0/name"]);
+    assert_eq!(paths("$.servers[1:].name"), vec!["/servers/1/name", "/servers/2/name"]);
+    assert_eq!(paths("$.servers[1::9223372036854775807].name"), vec!["/servers/1/name"]);
+    assert_eq!(paths("$.servers[-1::-9223372036854775807].name"), vec!["/servers/2/name"]);
+    assert_eq!(paths("$.servers[0, 2].port"), vec!["/servers/0/port", "/servers/2/port"]);
+    assert_eq!(paths("$.pair.1"), vec!["/pair/1"]);
+    assert_eq!(paths("$.limits[{1u32}]"), vec!["/limits/{1u32}"]);
+    assert_eq!(paths("$.limits.*"), vec!["/limits/{1u32}", "/limits/two"]);
+    assert_eq!(paths("$..port").len(), 3);
+    assert_eq!(paths("$.servers[?@.port >= 443u64].name"), vec!["/servers/1/name", "/servers/2/name"]);
+    assert_eq!(paths("$.servers[?(@.role is Role::Replica && !(@.port == 443))]"), vec!["/servers/1"]);
+    assert_eq!(paths("$.servers[?@.name == $.servers[0].name]"), vec!["/servers/0"]);
+    assert_eq!(paths("$..[?@ is Server]").len(), 3);
+    assert_eq!(paths("$..[?@ is Role::*]").len(), 3);
+    assert_eq!(paths("$.limits[?@ == 2 || @ == 'one']").len(), 2);
+    assert_eq!(paths("$.pair[?@ == 'y']"), vec!["/pair/1"]);
+    assert_eq!(paths("$.servers[?@.missing]"), Vec::<String>::new());
+
+    assert!(Query::parse("servers").is_err());
+    assert!(Query::parse("$.servers[").is_err());
+    assert_eq!(Query::parse("$.a[?@ ==]").unwrap_err().position(), 9);
+
+    let query = Query::parse("$.servers[1]").unwrap();
+    assert!(query.matches(&root, &Path::parse("/servers/1").unwrap()));
+}
@@ -0,0 +1,428 @@
+use crate::query::{CompareOp, Expr, Operand, Query, QueryError, Selector, Step, TypePattern};
+use crate::value::{Number, Path, Segment, Value};
+use crate::value::path::{parse_literal, parse_number};
+
+pub(super) struct Parser<'q> {
+    query: &'q str,
+    pos: usize,
+}
+
+type Result<T> = std::result::Result<T, QueryError>;
+
+impl<'q> Parser<'q> {
+    pub(super) fn new(query: &'q str) -> Self {
+        Self { query, pos: 0 }
+    }
+
+    pub(super) fn parse_query(mut self) -> Result<Query> {
+        self.skip_ws();
+        if !self.eat('$') {
+            return Err(self.error("query must start with '$'"));
+        }
+
+        let mut steps = Vec::new();
+        loop {
+            self.skip_ws();
+            if self.peek().is_none() {
+                break;
+            }
+            steps.push(self.parse_step()?);
+        }
+
+        Ok(Query { steps })
+    }
+
+    fn parse_step(&mut self) -> Result<Step> {
+        if self.eat_str("..") {
+            let selectors = if self.peek() == Some('[') {
+                self.parse_bracket()?
+            } else {
+                vec![self.parse_dot_selector()?]
+            };
+            return Ok(Step { descendants: true, selectors });
+        }
+
+        if self.eat('.') {
+            return Ok(Step { descendants: false, selectors: vec![self.parse_dot_selector()?] });
+        }
+
+        if self.peek() == Some('[') {
+            return Ok(Step { descendants: false, selectors: self.parse_bracket()? });
+        }
+
+        Err(self.error("expected '.', '..' or '['"))
+    }
+
+    fn parse_dot_selector(&mut self) -> Result<Selector> {
+        if self.eat('*') {
+            return Ok(Selector::Wildcard);
+        }
+
+        let name = self.parse_name()?;
+        Ok(match name.parse::<isize>() {
+            Ok(index) if name.bytes().all(|b| b.is_ascii_digit()) => Selector::Index(index),
+            _ => Selector::Name(name),
+        })
+    }
+
+    fn parse_bracket(&mut self) -> Result<Vec<Selector>> {
+        self.expect('[')?;
+        let mut selectors = Vec::new();
+        loop {
+            self.skip_ws();
+            selectors.push(self.parse_selector()?);
+            self.skip_ws();
+            if self.eat(']') {
+                return Ok(selectors);
+            }
+            self.expect(',')?;
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector> {
+        match self.peek() {
+            Some('*') => {
+                self.bump();
+                Ok(Selector::Wildcard)
+            }
+            Some('\'' | '"') => Ok(Selector::Name(self.parse_string()?)),
+            Some('{') => Ok(Selector::Key(self.parse_key()?)),
+            Some('?') => {
+                self.bump();
+                Ok(Selector::Filter(self.parse_or()?))
+            }
+            _ => {
+                let start = self.parse_optional_int()?;
+                self.skip_ws();
+                if !self.eat(':') {
+                    return start.map(Selector::Index).ok_or_else(|| self.error("expected a selector"));
+                }
+                self.skip_ws();
+                let end = self.parse_optional_int()?;
+                self.skip_ws();
+                let step = if self.eat(':') {
+                    self.skip_ws();
+                    self.parse_optional_int()?
+                } else {
+                    None
+                };
+                Ok(Selector::Slice { start, end, step })
+            }
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_and()?;
+        loop {
+            self.skip_ws();
+            if !self.eat_str("||") {
+                return Ok(expr);
+            }
+            expr = Expr::Or(Box::new(expr), Box::new(self.parse_and()?));
+        }
+    }
+
+    fn parse_and(&mut self) -> Result<Expr> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            self.skip_ws();
+            if !self.eat_str("&&") {
+                return Ok(expr);
+            }
+            expr = Expr::And(Box::new(expr), Box::new(self.parse_unary()?));
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr> {
+        self.skip_ws();
+        if self.peek() == Some('!') && !self.rest().starts_with("!=") {
+            self.bump();
+            return Ok(Expr::Not(Box::new(self.parse_unary()?)));
+        }
+
+        if self.eat('(') {
+            let expr = self.parse_or()?;
+            self.skip_ws();
+            self.expect(')')?;
+            return Ok(expr);
+        }
+
+        let left = self.parse_operand()?;
+        self.skip_ws();
+
+        if self.rest().starts_with("is") && !self.rest()[2..].starts_with(is_name_char) {
+            self.pos += 2;
+            self.skip_ws();
+            return Ok(Expr::Is(left, self.parse_type_pattern()?));
+        }
+
+        let op = if self.eat_str("==") {
+            CompareOp::Eq
+        } else if self.eat_str("!=") {
+            CompareOp::Ne
+        } else if self.eat_str("<=") {
+            CompareOp::Le
+        } else if self.eat_str(">=") {
+            CompareOp::Ge
+        } else if self.eat('<') {
+            CompareOp::Lt
+        } else if self.eat('>') {
+            CompareOp::Gt
+        } else {
+            return match left {
+                Operand::Literal(_) => Err(self.error("expected a comparison")),
+                left => Ok(Expr::Exists(left)),
+            };
+        };
+
+        self.skip_ws();
+        let right = self.parse_operand()?;
+        Ok(Expr::Compare(left, op, right))
+    }
+
+    fn parse_operand(&mut self) -> Result<Operand> {
+        match self.peek() {
+            Some('@') => {
+                self.bump();
+                Ok(Operand::Current(self.parse_relative_path()?))
+            }
+            Some('$') => {
+                self.bump();
+                Ok(Operand::Root(self.parse_relative_path()?))
+            }
+            Some('\'' | '"') => Ok(Operand::Literal(Value::String(self.parse_string()?))),
+            Some('{') => Ok(Operand::Literal(self.parse_key()?)),
+            Some(c) if c == '-' || c.is_ascii_digit() => Ok(Operand::Literal(self.parse_number()?)),
+            Some(c) if is_name_char(c) => {
+                let start = self.pos;
+                let name = self.parse_name()?;
+                match name.as_str() {
+                    "true" => Ok(Operand::Literal(Value::Bool(true))),
+                    "false" => Ok(Operand::Literal(Value::Bool(false))),
+                    "null" => Ok(Operand::Literal(Value::Unit)),
+                    _ => {
+                        self.pos = start;
+                        Err(self.error("expected '@', '$' or a literal"))
+                    }
+                }
+            }
+            _ => Err(self.error("expected an operand")),
+        }
+    }
+
+    /// Parse the singular steps following `@` or `$` in a filter
+    fn parse_relative_path(&mut self) -> Result<Path> {
+        let mut path = Path::root();
+        loop {
+            if self.rest().starts_with("..") {
+                return Err(self.error("recursive descent is not allowed in filter paths"));
+            }
+
+            if self.eat('.') {
+                let name = self.parse_name()?;
+                path.push(match name.parse::<usize>() {
+                    Ok(index) if name.bytes().all(|b| b.is_ascii_digit()) => Segment::Index(index),
+                    _ => Segment::Field(name),
+                });
+            } else if self.peek() == Some('[') {
+                self.bump();
+                self.skip_ws();
+                path.push(match self.peek() {
+                    Some('\'' | '"') => Segment::Field(self.parse_string()?),
+                    Some('{') => Segment::Key(self.parse_key()?),
+                    _ => {
+                        let start = self.pos;
+                        match self.parse_optional_int()? {
+                            Some(index) => Segment::Index(usize::try_from(index).map_err(|_| {
+                                self.pos = start;
+                                self.error("negative indices are not allowed in filter paths")
+                            })?),
+                            None => return Err(self.error("expected a name, index or key")),
+                        }
+                    }
+                });
+                self.skip_ws();
+                self.expect(']')?;
+            } else {
+                return Ok(path);
+            }
+        }
+    }
+
+    fn parse_type_pattern(&mut self) -> Result<TypePattern> {
+        let name = self.parse_name()?;
+        let variant = if self.eat_str("::") {
+            if self.eat('*') {
+                Some("*".to_string())
+            } else {
+                Some(self.parse_name()?)
+            }
+        } else {
+            None
+        };
+        Ok(TypePattern { name, variant })
+    }
+
+    fn parse_name(&mut self) -> Result<String> {
+        let start = self.pos;
+        while self.peek().is_some_and(is_name_char) {
+            self.bump();
+        }
+        if start == self.pos {
+            return Err(self.error("expected a name"));
+        }
+        Ok(self.query[start..self.pos].to_string())
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        let start = self.pos;
+        let Some(quote) = self.bump() else {
+            return Err(self.error("expected a string"));
+        };
+
+        let mut out = String::new();
+        loop {
+            match self.bump() {
+                Some('\\') => match self.bump() {
+                    Some('n') => out.push('\n'),
+                    Some('r') => out.push('\r'),
+                    Some('t') => out.push('\t'),
+                    Some(c) => out.push(c),
+                    None => break,
+                },
+                Some(c) if c == quote => return Ok(out),
+                Some(c) => out.push(c),
+                None => break,
+            }
+        }
+
+        self.pos = start;
+        Err(self.error("unterminated string"))
+    }
+
+    /// Parse a braced key literal such as `{5u32}`, using the [`Path`] key syntax
+    fn parse_key(&mut self) -> Result<Value> {
+        let start = self.pos;
+        self.expect('{')?;
+
+        let mut quote = None;
+        let mut escaped = false;
+        while let Some(c) = self.bump() {
+            match (quote, c) {
+                (Some(_), _) if escaped => escaped = false,
+                (Some(_), '\\') => escaped = true,
+                (Some(q), c) if c == q => quote = None,
+                (None, '\'' | '"') => quote = Some(c),
+                (None, '}') => {
+                    let literal = &self.query[start + 1..self.pos - 1];
+                    return parse_literal(literal).map_err(|message| {
+                        self.pos = start;
+                        self.error(&message)
+                    });
+                }
+                _ => {}
+            }
+        }
+
+        self.pos = start;
+        Err(self.error("unterminated key literal"))
+    }
+
+    fn parse_number(&mut self) -> Result<Value> {
+        let start = self.pos;
+        self.eat('-');
+        while self.peek().is_some_and(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_') {
+            self.bump();
+        }
+        let text = &self.query[start..self.pos];
+
+        if let Some(number) = parse_number(text) {
+            return Ok(Value::Number(number));
+        }
+
+        let number = if let Ok(v) = text.parse::<i128>() {
+            Number::I128(v)
+        } else if let Ok(v) = text.parse::<u128>() {
+            Number::U128(v)
+        } else if let Ok(v) = text.parse::<f64>() {
+            Number::from(v)
+        } else {
+            self.pos = start;
+            return Err(self.error("invalid number"));
+        };
+        Ok(Value::Number(number))
+    }
+
+    fn parse_optional_int(&mut self) -> Result<Option<isize>> {
+        let start = self.pos;
+        self.eat('-');
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.bump();
+        }
+        match &self.query[start..self.pos] {
+            "" => Ok(None),
+            text => text.parse().map(Some).map_err(|_| {
+                self.pos = start;
+                self.error("invalid index")
+            }),
+        }
+    }
+
+    fn rest(&self) -> &'q str {
+        &self.query[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        if self.rest().starts_with(s) {
+            self.pos += s.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<()> {
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{c}'")))
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.bump();
+        }
+    }
+
+    fn error(&self, message: &str) -> QueryError {
+        QueryError {
+            query: self.query.to_string(),
+            position: self.pos,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
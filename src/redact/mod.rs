@@ -0,0 +1,202 @@
+//! Redaction of sensitive nodes in [`Value`] trees
+//!
+//! A [`Redactor`] replaces every node matched by a field name, a type name or a
+//! [`Query`] with a marker, keeping the rest of the tree intact so that a captured value can
+//! be logged safely. Instead of a fixed marker, nodes can be replaced by a hash of their
+//! contents, so that equal secrets stay correlatable across log lines without being revealed.
+//!
+//! ```
+//! use serde_derive::Serialize;
+//! use serde_value::Value;
+//! use serde_value::redact::Redactor;
+//!
+//! #[derive(Serialize)]
+//! struct Login {
+//!     user: String,
+//!     password: String,
+//! }
+//!
+//! let value = serde_value::to_value(&Login { user: "root".into(), password: "hunter2".into() }).unwrap();
+//! let redacted = Redactor::new().field("password").redact(value);
+//!
+//! assert_eq!(redacted["user"], Value::from("root"));
+//! assert_eq!(redacted["password"], Value::from("[REDACTED]"));
+//! ```
+
+use crate::digest::Digest;
+use crate::fold::Order;
+use crate::query::Query;
+use crate::value::{Path, Segment, Value};
+
+/// What a redacted node is replaced with
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Replacement {
+    /// A fixed marker value
+    Marker(Value),
+    /// A `String` of the form `hash:<16 hex digits>`, computed from the [digest](Value::digest)
+    /// of the node and a key, which is stable across processes
+    Hash {
+        key: u64,
+    },
+}
+
+/// Replaces sensitive nodes of a [`Value`], see the [module documentation](self)
+#[derive(Clone, Debug)]
+pub struct Redactor {
+    fields: Vec<String>,
+    types: Vec<String>,
+    paths: Vec<Query>,
+    replacement: Replacement,
+}
+
+impl Default for Redactor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Redactor {
+    /// A redactor matching nothing, replacing nodes with the `"[REDACTED]"` marker
+    pub fn new() -> Self {
+        Self {
+            fields: Vec::new(),
+            types: Vec::new(),
+            paths: Vec::new(),
+            replacement: Replacement::Marker(Value::from("[REDACTED]")),
+        }
+    }
+
+    /// Redacts the values of struct and variant fields, and of `String` map keys, called
+    /// `name`, ignoring ASCII case
+    pub fn field(mut self, name: impl Into<String>) -> Self {
+        self.fields.push(name.into());
+        self
+    }
+
+    /// Redacts structs named `name`, and variants of an enum named `name`. A name of the form
+    /// `Enum::Variant` matches a single variant
+    pub fn type_name(mut self, name: impl Into<String>) -> Self {
+        self.types.push(name.into());
+        self
+    }
+
+    /// Redacts every node selected by `query`
+    pub fn path(mut self, query: Query) -> Self {
+        self.paths.push(query);
+        self
+    }
+
+    /// Replaces redacted nodes with `marker`
+    pub fn marker(mut self, marker: Value) -> Self {
+        self.replacement = Replacement::Marker(marker);
+        self
+    }
+
+    /// Replaces redacted nodes with a hash of their contents, computed with `key`.
+    ///
+    /// Equal nodes hash to the same string for a given key, across processes and builds of this
+    /// crate, see [`Replacement::Hash`].
+    pub fn hashed(mut self, key: u64) -> Self {
+        self.replacement = Replacement::Hash { key };
+        self
+    }
+
+    /// Returns `value` with every matched node replaced. A redacted node's children are not
+    /// visited
+    pub fn redact(&self, value: Value) -> Value {
+        let selected: Vec<Path> = self.paths.iter()
+            .flat_map(|query| query.select(&value).map(|(path, _)| path))
+            .collect();
+
+        value.transform(Order::TopDown, |path, node| {
+            if selected.contains(path) || self.matches_field(path) || self.matches_type(&node) {
+                Some(self.replace(&node))
+            } else {
+                Some(node)
+            }
+        })
+    }
+
+    /// In-place version of [`Redactor::redact`]
+    pub fn redact_in_place(&self, value: &mut Value) {
+        *value = self.redact(std::mem::replace(value, Value::Unit));
+    }
+
+    fn matches_field(&self, path: &Path) -> bool {
+        match path.last() {
+            Some(Segment::Field(name)) => self.fields.iter().any(|field| field.eq_ignore_ascii_case(name)),
+            _ => false,
+        }
+    }
+
+    fn matches_type(&self, node: &Value) -> bool {
+        let (name, variant) = match node {
+            Value::UnitStruct(val) => (val.name, None),
+            Value::TupleStruct(val) => (val.name, None),
+            Value::NamedStruct(val) => (val.name, None),
+            Value::UnitVariant(val) => (val.name, Some(val.variant)),
+            Value::TupleVariant(val) => (val.name, Some(val.variant)),
+            Value::NamedVariant(val) => (val.name, Some(val.variant)),
+            _ => return false,
+        };
+
+        self.types.iter().any(|ty| match ty.split_once("::") {
+            Some((ty_name, ty_variant)) => ty_name == name && Some(ty_variant) == variant,
+            None => ty == name,
+        })
+    }
+
+    fn replace(&self, node: &Value) -> Value {
+        match &self.replacement {
+            Replacement::Marker(marker) => marker.clone(),
+            Replacement::Hash { key } => {
+                let hash = Digest::of(&[&key.to_le_bytes()[..], node.digest().as_bytes()].concat());
+                Value::String(format!("hash:{}", &hash.to_string()[..16]))
+            }
+        }
+    }
+}
+
+#[test]
+fn test_redact() {
+    use std::collections::BTreeMap;
+    use crate::value::{NamedStruct, TupleStruct, UnitVariant};
+
+    let mut headers = BTreeMap::new();
+    headers.insert(Value::from("Authorization"), Value::from("Bearer abc"));
+    headers.insert(Value::from("Accept"), Value::from("*/*"));
+
+    let mut fields = BTreeMap::new();
+    fields.insert("Token", Value::from("t0k3n"));
+    fields.insert("headers", Value::Map(headers));
+    fields.insert("key", Value::TupleStruct(TupleStruct { name: "Secret", values: vec![Value::from("k")] }));
+    fields.insert("cards", Value::from(vec!["4111", "5500"]));
+    fields.insert("level", Value::UnitVariant(UnitVariant { name: "Level", variant: "Admin" }));
+    let request = Value::NamedStruct(NamedStruct { name: "Request", fields });
+
+    let redactor = Redactor::new()
+        .field("token")
+        .field("authorization")
+        .type_name("Secret")
+        .type_name("Level::Admin")
+        .path(Query::parse("$.cards[1:]").unwrap());
+    let redacted = redactor.redact(request.clone());
+
+    let marker = Value::from("[REDACTED]");
+    assert_eq!(redacted["Token"], marker);
+    assert_eq!(redacted["headers"]["Authorization"], marker);
+    assert_eq!(redacted["headers"]["Accept"], Value::from("*/*"));
+    assert_eq!(redacted["key"], marker);
+    assert_eq!(redacted["cards"], Value::from(vec!["4111", "[REDACTED]"]));
+    assert_eq!(redacted["level"], marker);
+
+    let hashed = Redactor::new().field("token").field("cards").hashed(7);
+    let mut a = request.clone();
+    hashed.redact_in_place(&mut a);
+    let b = hashed.redact(request.clone());
+    let other_key = Redactor::new().field("token").hashed(8).redact(request);
+    assert_eq!(a, b);
+    assert!(a["Token"].as_string().is_some_and(|h| h.starts_with("hash:") && h.len() == 21));
+    assert_ne!(a["Token"], a["cards"]);
+    assert_ne!(a["Token"], other_key["Token"]);
+}
@@ -0,0 +1,431 @@
+//! Serde enum representations
+//!
+//! [`to_value`](crate::to_value) captures enum variants as [`UnitVariant`], [`TupleVariant`]
+//! and [`NamedVariant`] values, keeping their enum and variant names. Formats like JSON have no
+//! such values, and serde represents enums there with one of the [`EnumRepr`]s selected by the
+//! `#[serde(tag, content, untagged)]` attributes. [`Value::retag_enums`] converts every variant
+//! of a tree into one of these representations, using maps with string keys, and
+//! [`Value::detag_enums`] converts them back given the [`Schema`] of the tree.
+//!
+//! ```
+//! use serde_derive::{Deserialize, Serialize};
+//! use serde_value::Value;
+//! use serde_value::repr::EnumRepr;
+//! use serde_value::schema::Tracer;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! enum Event {
+//!     Click { x: u16, y: u16 },
+//!     Close,
+//! }
+//!
+//! let value = serde_value::to_value(&Event::Click { x: 1, y: 2 }).unwrap();
+//! let repr = EnumRepr::Internal { tag: "type".to_string() };
+//!
+//! let mut tagged = value.clone();
+//! tagged.retag_enums(&repr).unwrap();
+//! assert_eq!(tagged["type"], Value::from("Click"));
+//! assert_eq!(tagged["x"], Value::from(1u16));
+//!
+//! let mut tracer = Tracer::new();
+//! let schema = tracer.trace::<Event>().unwrap();
+//! tracer.registry().detag_enums(&mut tagged, &repr, &schema).unwrap();
+//! assert_eq!(tagged, value);
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use crate::fold::Order;
+use crate::schema::{EnumSchema, Fields, Registry, Schema, StructSchema};
+use crate::value::{NamedStruct, NamedVariant, Path, Segment, TupleStruct, TupleVariant, UnitVariant, Value};
+
+/// A serde enum representation, see the
+/// [serde documentation](https://serde.rs/enum-representations.html)
+#[derive(Clone, Debug, Default, Eq, PartialEq, Hash)]
+pub enum EnumRepr {
+    /// `{"Variant": content}`, or `"Variant"` for unit variants
+    #[default]
+    External,
+    /// `{"<tag>": "Variant", ...fields}`, which cannot represent tuple variants
+    Internal {
+        tag: String,
+    },
+    /// `{"<tag>": "Variant", "<content>": content}`
+    Adjacent {
+        tag: String,
+        content: String,
+    },
+    /// `content`, or `()` for unit variants
+    Untagged,
+}
+
+/// An error produced while converting enum representations
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RetagError {
+    /// The path of the offending value
+    pub path: Path,
+    pub message: String,
+}
+
+impl std::error::Error for RetagError {}
+
+impl Display for RetagError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {:?}", self.message, self.path.to_string())
+    }
+}
+
+impl Value {
+    /// Converts every enum variant of this value into the `repr` representation.
+    ///
+    /// The fields of named variants become maps with string keys, and the values of tuple
+    /// variants become sequences, or their single value for newtype variants. The enum names are
+    /// lost, see [`Value::detag_enums`] to restore them. With [`EnumRepr::Internal`], newtype
+    /// variants must hold a struct or a map, the struct name is lost as well.
+    ///
+    /// The conversion is atomic: on error, `self` is left unchanged.
+    pub fn retag_enums(&mut self, repr: &EnumRepr) -> Result<(), RetagError> {
+        let mut error = None;
+        let retagged = self.clone().transform(Order::BottomUp, |path, node| {
+            if error.is_some() {
+                return Some(node);
+            }
+            match retag(node, repr) {
+                Ok(node) => Some(node),
+                Err(message) => {
+                    error = Some(RetagError { path: path.clone(), message });
+                    Some(Value::Unit)
+                }
+            }
+        });
+        match error {
+            Some(error) => Err(error),
+            None => {
+                *self = retagged;
+                Ok(())
+            }
+        }
+    }
+
+    /// Converts the enums of this value from the `repr` representation back into variants,
+    /// following `schema` to find them, see [`Registry::detag_enums`]
+    pub fn detag_enums(&mut self, repr: &EnumRepr, schema: &Schema) -> Result<(), RetagError> {
+        Registry::default().detag_enums(self, repr, schema)
+    }
+}
+
+impl Registry {
+    /// Converts the enums of `value` from the `repr` representation back into variants,
+    /// following `schema` and the [`Schema::Named`] references into this registry.
+    ///
+    /// Maps with string keys become named structs wherever `schema` expects one. Untagged
+    /// variants are matched in name order, the first variant whose rebuilt value
+    /// [validates](Registry::validate) is kept. Values that do not match `schema` are left as
+    /// they are, except for enums, which must be in the `repr` representation.
+    ///
+    /// The conversion is atomic: on error, `value` is left unchanged.
+    pub fn detag_enums(&self, value: &mut Value, repr: &EnumRepr, schema: &Schema) -> Result<(), RetagError> {
+        let mut detagger = Detagger { registry: self, repr, path: Path::root() };
+        *value = detagger.detag(value.clone(), schema)?;
+        Ok(())
+    }
+}
+
+/// The content of a variant
+fn content(value: Value) -> Option<Value> {
+    match value {
+        Value::UnitVariant(_) => None,
+        Value::TupleVariant(mut val) if val.values.len() == 1 => val.values.pop(),
+        Value::TupleVariant(val) => Some(Value::Seq(val.values)),
+        Value::NamedVariant(val) => Some(Value::Map(val.fields.into_iter()
+            .map(|(name, value)| (Value::from(name), value))
+            .collect())),
+        value => Some(value),
+    }
+}
+
+/// Converts `node` into the `repr` representation if it is a variant, whose content is kept as is
+fn retag(node: Value, repr: &EnumRepr) -> Result<Value, String> {
+    let (variant, tuple) = match &node {
+        Value::UnitVariant(val) => (val.variant, false),
+        Value::TupleVariant(val) => (val.variant, val.values.len() != 1),
+        Value::NamedVariant(val) => (val.variant, false),
+        _ => return Ok(node),
+    };
+    let tag = |name: &str| (Value::from(name), Value::from(variant));
+
+    Ok(match (repr, content(node)) {
+        (EnumRepr::External, None) => Value::from(variant),
+        (EnumRepr::External, Some(content)) => Value::Map([(Value::from(variant), content)].into()),
+        (EnumRepr::Internal { .. }, Some(_)) if tuple => {
+            return Err(format!("tuple variant {variant} cannot be internally tagged"));
+        }
+        (EnumRepr::Internal { tag: name }, content) => {
+            let mut map = match content {
+                None | Some(Value::Unit) | Some(Value::UnitStruct(_)) => BTreeMap::new(),
+                Some(Value::Map(map)) => map,
+                Some(Value::NamedStruct(val)) => val.fields.into_iter()
+                    .map(|(name, value)| (Value::from(name), value))
+                    .collect(),
+                Some(_) => return Err(format!("variant {variant} must hold a struct or a map to be internally tagged")),
+            };
+            let (key, value) = tag(name);
+            if map.insert(key, value).is_some() {
+                return Err(format!("variant {variant} has a field named like the tag {name:?}"));
+            }
+            Value::Map(map)
+        }
+        (EnumRepr::Adjacent { tag: name, .. }, None) => Value::Map([tag(name)].into()),
+        (EnumRepr::Adjacent { tag: name, content: key }, Some(content)) => {
+            Value::Map([tag(name), (Value::from(key.as_str()), content)].into())
+        }
+        (EnumRepr::Untagged, content) => content.unwrap_or(Value::Unit),
+    })
+}
+
+struct Detagger<'r> {
+    registry: &'r Registry,
+    repr: &'r EnumRepr,
+    path: Path,
+}
+
+impl Detagger<'_> {
+    fn error<T>(&self, message: String) -> Result<T, RetagError> {
+        Err(RetagError { path: self.path.clone(), message })
+    }
+
+    fn child(&mut self, segment: Segment, value: Value, schema: &Schema) -> Result<Value, RetagError> {
+        self.path.push(segment);
+        let result = self.detag(value, schema);
+        self.path.pop();
+        result
+    }
+
+    fn elements(&mut self, values: Vec<Value>, schemas: &[Schema]) -> Result<Vec<Value>, RetagError> {
+        values.into_iter()
+            .zip(schemas)
+            .enumerate()
+            .map(|(index, (value, schema))| self.child(Segment::Index(index), value, schema))
+            .collect()
+    }
+
+    /// Detags the values of string-keyed maps or named fields into named fields
+    fn fields(&mut self, value: Value, schemas: &BTreeMap<&'static str, crate::schema::Field>) -> Result<BTreeMap<&'static str, Value>, RetagError> {
+        let entries: Vec<(String, Value)> = match value {
+            Value::NamedStruct(val) => val.fields.into_iter().map(|(name, value)| (name.to_string(), value)).collect(),
+            Value::Map(map) => map.into_iter()
+                .map(|(key, value)| match key {
+                    Value::String(key) => Ok((key, value)),
+                    key => self.error(format!("expected a field name, found {key:?}")),
+                })
+                .collect::<Result<_, _>>()?,
+            value => return self.error(format!("expected named fields, found {value:?}")),
+        };
+        entries.into_iter()
+            .map(|(key, value)| match schemas.get_key_value(key.as_str()) {
+                Some((name, field)) => Ok((*name, self.child(Segment::from(*name), value, &field.schema)?)),
+                None => self.error(format!("unknown field {key:?}")),
+            })
+            .collect()
+    }
+
+    fn detag(&mut self, value: Value, schema: &Schema) -> Result<Value, RetagError> {
+        Ok(match (schema, value) {
+            (Schema::Named(name), value) => match self.registry.get(name) {
+                Some(schema) => self.detag(value, schema)?,
+                None => return self.error(format!("unresolved type {name:?}")),
+            },
+            (Schema::Enum(schema), value) => self.detag_enum(value, schema)?,
+            (Schema::Struct(schema), value) => self.detag_struct(value, schema)?,
+            (Schema::Option(inner), Value::TupleStruct(mut val)) if val.name == "Some" && val.values.len() == 1 => {
+                let value = self.child(Segment::Index(0), val.values.pop().unwrap(), inner)?;
+                Value::TupleStruct(TupleStruct { name: val.name, values: vec![value] })
+            }
+            (Schema::Seq(inner), Value::Seq(values)) => Value::Seq(values.into_iter()
+                .enumerate()
+                .map(|(index, value)| self.child(Segment::Index(index), value, inner))
+                .collect::<Result<_, _>>()?),
+            (Schema::Tuple(schemas), Value::Tuple(values)) if schemas.len() == values.len() => {
+                Value::Tuple(self.elements(values, schemas)?)
+            }
+            (Schema::Map { key, value: inner }, Value::Map(map)) => Value::Map(map.into_iter()
+                .map(|(k, v)| {
+                    let segment = crate::value::path::key_segment(&k);
+                    let k = self.child(segment.clone(), k, key)?;
+                    Ok((k, self.child(segment, v, inner)?))
+                })
+                .collect::<Result<_, _>>()?),
+            (Schema::OneOf(alternatives), value) => {
+                let matched = alternatives.iter().find_map(|alternative| {
+                    let detagged = self.detag(value.clone(), alternative).ok()?;
+                    self.registry.validate(alternative, &detagged).is_ok().then_some(detagged)
+                });
+                matched.unwrap_or(value)
+            }
+            (_, value) => value,
+        })
+    }
+
+    fn detag_struct(&mut self, value: Value, schema: &StructSchema) -> Result<Value, RetagError> {
+        Ok(match (&schema.fields, value) {
+            (Fields::Tuple(schemas), Value::TupleStruct(val)) if schemas.len() == val.values.len() => {
+                Value::TupleStruct(TupleStruct { name: val.name, values: self.elements(val.values, schemas)? })
+            }
+            (Fields::Named(schemas), value @ (Value::NamedStruct(_) | Value::Map(_))) => {
+                Value::NamedStruct(NamedStruct { name: schema.name, fields: self.fields(value, schemas)? })
+            }
+            (_, value) => value,
+        })
+    }
+
+    fn detag_enum(&mut self, value: Value, schema: &EnumSchema) -> Result<Value, RetagError> {
+        let tag = |map: &mut BTreeMap<Value, Value>, name: &str| match map.remove(&Value::from(name)) {
+            Some(Value::String(variant)) => Ok(variant),
+            _ => Err(format!("expected a {name:?} tag naming a variant of {}", schema.name)),
+        };
+
+        match (self.repr, value) {
+            (EnumRepr::External, Value::String(variant)) => self.variant(schema, &variant, None),
+            (EnumRepr::External, Value::Map(map)) if map.len() == 1 => match map.into_iter().next() {
+                Some((Value::String(variant), content)) => self.variant(schema, &variant, Some(content)),
+                _ => self.error(format!("expected a variant name of {}", schema.name)),
+            },
+            (EnumRepr::Internal { tag: name }, Value::Map(mut map)) => {
+                let variant = tag(&mut map, name).or_else(|message| self.error(message))?;
+                let content = match schema.variants.get(variant.as_str()) {
+                    Some(Fields::Unit) if map.is_empty() => None,
+                    _ => Some(Value::Map(map)),
+                };
+                self.variant(schema, &variant, content)
+            }
+            (EnumRepr::Adjacent { tag: name, content: key }, Value::Map(mut map)) => {
+                let variant = tag(&mut map, name).or_else(|message| self.error(message))?;
+                let content = map.remove(&Value::from(key.as_str()));
+                if !map.is_empty() {
+                    return self.error(format!("unexpected entries next to {name:?} and {key:?}"));
+                }
+                self.variant(schema, &variant, content)
+            }
+            (EnumRepr::Untagged, value) => {
+                let whole = Schema::Enum(schema.clone());
+                for variant in schema.variants.keys() {
+                    let content = Some(value.clone()).filter(|value| *value != Value::Unit);
+                    let Ok(detagged) = self.variant(schema, variant, content) else {
+                        continue;
+                    };
+                    if self.registry.validate(&whole, &detagged).is_ok() {
+                        return Ok(detagged);
+                    }
+                }
+                self.error(format!("no variant of {} matches", schema.name))
+            }
+            (_, value) => self.error(format!("expected an enum {}, found {value:?}", schema.name)),
+        }
+    }
+
+    /// Rebuilds the `variant` of an enum from its content
+    fn variant(&mut self, schema: &EnumSchema, variant: &str, content: Option<Value>) -> Result<Value, RetagError> {
+        let Some((variant, fields)) = schema.variants.get_key_value(variant) else {
+            return self.error(format!("unknown variant {variant:?} of {}", schema.name));
+        };
+        let name = schema.name;
+
+        Ok(match (fields, content) {
+            (Fields::Unit, None | Some(Value::Unit)) => Value::UnitVariant(UnitVariant { name, variant }),
+            (Fields::Tuple(schemas), Some(content)) if schemas.len() == 1 => {
+                let value = self.child(Segment::Index(0), content, &schemas[0])?;
+                Value::TupleVariant(TupleVariant { name, variant, values: vec![value] })
+            }
+            (Fields::Tuple(schemas), Some(Value::Seq(values) | Value::Tuple(values))) if schemas.len() == values.len() => {
+                Value::TupleVariant(TupleVariant { name, variant, values: self.elements(values, schemas)? })
+            }
+            (Fields::Named(schemas), Some(content)) => {
+                Value::NamedVariant(NamedVariant { name, variant, fields: self.fields(content, schemas)? })
+            }
+            (_, content) => return self.error(format!("unexpected content {content:?} for variant {name}::{variant}")),
+        })
+    }
+}
+
+#[test]
+fn test_repr() {
+    use serde_derive::{Deserialize, Serialize};
+    use crate::schema::Tracer;
+    use crate::to_value;
+
+    #[derive(Serialize, Deserialize)]
+    enum Shape {
+        Dot,
+        Circle { radius: u8 },
+        Label(Text),
+        Line(u8, u8),
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Text {
+        text: String,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Drawing {
+        shapes: Vec<Shape>,
+        last: Option<Shape>,
+    }
+
+    let drawing = Drawing {
+        shapes: vec![
+            Shape::Dot,
+            Shape::Circle { radius: 3 },
+            Shape::Label(Text { text: "a".to_string() }),
+            Shape::Line(1, 2),
+        ],
+        last: Some(Shape::Dot),
+    };
+    let value = to_value(&drawing).unwrap();
+    let mut tracer = Tracer::new();
+    let schema = tracer.trace::<Drawing>().unwrap();
+    let registry = tracer.into_registry();
+
+    let internal = EnumRepr::Internal { tag: "type".to_string() };
+    let adjacent = EnumRepr::Adjacent { tag: "t".to_string(), content: "c".to_string() };
+    for repr in [EnumRepr::External, adjacent.clone(), EnumRepr::Untagged] {
+        let mut retagged = value.clone();
+        retagged.retag_enums(&repr).unwrap();
+        registry.detag_enums(&mut retagged, &repr, &schema).unwrap();
+        assert_eq!(retagged, value, "{repr:?}");
+    }
+
+    let mut external = value.clone();
+    external.retag_enums(&EnumRepr::External).unwrap();
+    assert_eq!(external["shapes"][0], Value::from("Dot"));
+    assert_eq!(external["shapes"][1]["Circle"]["radius"], Value::from(3u8));
+    assert_eq!(external["shapes"][3]["Line"], Value::from(vec![1u8, 2]));
+
+    let mut tagged = value.clone();
+    tagged.retag_enums(&adjacent).unwrap();
+    assert_eq!(tagged["shapes"][2]["t"], Value::from("Label"));
+    assert_eq!(tagged["shapes"][2]["c"]["text"], Value::from("a"));
+
+    let mut untagged = value.clone();
+    untagged.retag_enums(&EnumRepr::Untagged).unwrap();
+    assert_eq!(untagged["shapes"][0], Value::Unit);
+
+    let err = value.clone().retag_enums(&internal).unwrap_err();
+    assert_eq!(err.to_string(), "tuple variant Line cannot be internally tagged at \"/shapes/3\"");
+
+    let mut line_free = value.clone();
+    line_free.remove_at(&Path::parse("/shapes/3").unwrap()).unwrap();
+    let mut retagged = line_free.clone();
+    retagged.retag_enums(&internal).unwrap();
+    assert_eq!(retagged["shapes"][2], Value::Map([
+        (Value::from("text"), Value::from("a")),
+        (Value::from("type"), Value::from("Label")),
+    ].into()));
+    registry.detag_enums(&mut retagged, &internal, &schema).unwrap();
+    assert_eq!(retagged, line_free);
+
+    let mut bad = Value::from(vec!["Square"]);
+    let err = registry.detag_enums(&mut bad, &EnumRepr::External, &Schema::Seq(Box::new(Schema::Named("Shape")))).unwrap_err();
+    assert_eq!(err.to_string(), "unknown variant \"Square\" of Shape at \"/0\"");
+    assert_eq!(bad, Value::from(vec!["Square"]));
+}
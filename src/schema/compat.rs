@@ -0,0 +1,430 @@
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use crate::value::NumberKind;
+use super::infer::mergeable;
+use super::validate::brief;
+use super::{pick, Fields, NumberSchema, Registry, Schema};
+
+/// The compatibility issues between an old and a new version of a schema
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Compatibility {
+    pub issues: Vec<Issue>,
+}
+
+/// A difference between two schemas that breaks compatibility in at least one direction
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Issue {
+    /// Where the difference is, e.g. `$.items[].id` or `$.status::Moved.to`
+    pub location: String,
+    pub kind: IssueKind,
+}
+
+/// The kind of an [`Issue`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum IssueKind {
+    /// The type changed, e.g. from a string to a number
+    TypeChanged {
+        from: String,
+        to: String,
+    },
+    /// A struct or enum changed its name, which is part of a [`Value`](crate::Value)
+    Renamed {
+        from: &'static str,
+        to: &'static str,
+    },
+    /// A required field was added
+    FieldAdded(&'static str),
+    /// A required field was removed
+    FieldRemoved(&'static str),
+    /// An optional field became required
+    FieldRequired(&'static str),
+    /// A required field became optional
+    FieldOptional(&'static str),
+    /// An enum variant was added
+    VariantAdded(&'static str),
+    /// An enum variant was removed
+    VariantRemoved(&'static str),
+    /// Some old numbers cannot be represented by the new schema
+    NumberNarrowed {
+        from: NumberSchema,
+        to: NumberSchema,
+    },
+    /// Some new numbers cannot be represented by the old schema
+    NumberWidened {
+        from: NumberSchema,
+        to: NumberSchema,
+    },
+    /// An `Option<T>` became a `T`
+    OptionRemoved,
+    /// A `T` became an `Option<T>`
+    OptionAdded,
+}
+
+/// The direction in which an [`Issue`] breaks compatibility
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Direction {
+    /// Old values may not be readable with the new schema
+    Backward,
+    /// New values may not be readable with the old schema
+    Forward,
+    Both,
+}
+
+impl IssueKind {
+    /// The direction in which this issue breaks compatibility.
+    ///
+    /// Unknown fields are assumed to be ignored, as serde does by default, so that removing a
+    /// required field only breaks old readers and adding one only breaks new readers.
+    pub fn direction(&self) -> Direction {
+        match self {
+            IssueKind::TypeChanged { .. } | IssueKind::Renamed { .. } => Direction::Both,
+            IssueKind::FieldAdded(_)
+            | IssueKind::FieldRequired(_)
+            | IssueKind::VariantRemoved(_)
+            | IssueKind::NumberNarrowed { .. }
+            | IssueKind::OptionRemoved => Direction::Backward,
+            IssueKind::FieldRemoved(_)
+            | IssueKind::FieldOptional(_)
+            | IssueKind::VariantAdded(_)
+            | IssueKind::NumberWidened { .. }
+            | IssueKind::OptionAdded => Direction::Forward,
+        }
+    }
+}
+
+impl Compatibility {
+    /// Whether every value of the old schema matches the new schema
+    pub fn is_backward_compatible(&self) -> bool {
+        self.issues.iter().all(|issue| issue.kind.direction() == Direction::Forward)
+    }
+
+    /// Whether every value of the new schema matches the old schema
+    pub fn is_forward_compatible(&self) -> bool {
+        self.issues.iter().all(|issue| issue.kind.direction() == Direction::Backward)
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: ", self.location)?;
+        match &self.kind {
+            IssueKind::TypeChanged { from, to } => write!(f, "type changed from {from} to {to}")?,
+            IssueKind::Renamed { from, to } => write!(f, "renamed from {from} to {to}")?,
+            IssueKind::FieldAdded(name) => write!(f, "required field {name:?} added")?,
+            IssueKind::FieldRemoved(name) => write!(f, "required field {name:?} removed")?,
+            IssueKind::FieldRequired(name) => write!(f, "field {name:?} became required")?,
+            IssueKind::FieldOptional(name) => write!(f, "field {name:?} became optional")?,
+            IssueKind::VariantAdded(name) => write!(f, "variant {name:?} added")?,
+            IssueKind::VariantRemoved(name) => write!(f, "variant {name:?} removed")?,
+            IssueKind::NumberNarrowed { from, to } => write!(f, "number narrowed from {from} to {to}")?,
+            IssueKind::NumberWidened { from, to } => write!(f, "number widened from {from} to {to}")?,
+            IssueKind::OptionRemoved => write!(f, "no longer optional")?,
+            IssueKind::OptionAdded => write!(f, "became optional")?,
+        }
+        match self.kind.direction() {
+            Direction::Backward => write!(f, " (breaks backward compatibility)"),
+            Direction::Forward => write!(f, " (breaks forward compatibility)"),
+            Direction::Both => write!(f, " (breaks backward and forward compatibility)"),
+        }
+    }
+}
+
+impl Schema {
+    /// Compares an `old` and a `new` version of a schema, see [`Registry::compatibility`]
+    pub fn compatibility(old: &Schema, new: &Schema) -> Compatibility {
+        Registry::compatibility(&Registry::default(), old, &Registry::default(), new)
+    }
+}
+
+impl Registry {
+    /// Compares an `old` and a `new` version of a schema, following [`Schema::Named`]
+    /// references into their registries.
+    ///
+    /// Backward compatibility issues are changes that may prevent old values from being read
+    /// with the new schema, e.g. a removed enum variant or a narrowed number. Forward
+    /// compatibility issues prevent new values from being read with the old schema, e.g. an
+    /// added enum variant. References that cannot be resolved are compared by name.
+    ///
+    /// ```
+    /// use serde_value::Value;
+    /// use serde_value::schema::Schema;
+    ///
+    /// let old = Schema::infer(&[Value::from(vec![300u16])]);
+    /// let new = Schema::infer(&[Value::from(vec![3u8])]);
+    /// let compatibility = Schema::compatibility(&old, &new);
+    /// assert!(!compatibility.is_backward_compatible());
+    /// let issues: Vec<_> = compatibility.issues.iter().map(|issue| issue.to_string()).collect();
+    /// assert_eq!(issues, ["$[]: number narrowed from u16 to u8 (breaks backward compatibility)"]);
+    /// ```
+    pub fn compatibility(old_registry: &Registry, old: &Schema, new_registry: &Registry, new: &Schema) -> Compatibility {
+        let mut checker = Checker {
+            registries: (old_registry, new_registry),
+            location: "$".to_string(),
+            visited: BTreeSet::new(),
+            issues: Vec::new(),
+        };
+        checker.check(old, new);
+        Compatibility { issues: checker.issues }
+    }
+}
+
+struct Checker<'r> {
+    registries: (&'r Registry, &'r Registry),
+    location: String,
+    /// The pairs of named types already compared
+    visited: BTreeSet<(&'static str, &'static str)>,
+    issues: Vec<Issue>,
+}
+
+impl<'r> Checker<'r> {
+    fn issue(&mut self, kind: IssueKind) {
+        self.issues.push(Issue { location: self.location.clone(), kind });
+    }
+
+    fn child(&mut self, suffix: &str, old: &Schema, new: &Schema) {
+        let len = self.location.len();
+        self.location.push_str(suffix);
+        self.check(old, new);
+        self.location.truncate(len);
+    }
+
+    fn check(&mut self, old: &Schema, new: &Schema) {
+        match (old, new) {
+            (Schema::Named(a), Schema::Named(b)) => {
+                if !self.visited.insert((a, b)) {
+                    return;
+                }
+                match (self.registries.0.get(a), self.registries.1.get(b)) {
+                    (Some(old), Some(new)) => self.check(old, new),
+                    _ if a != b => self.issue(IssueKind::Renamed { from: a, to: b }),
+                    _ => {}
+                }
+            }
+            (Schema::Named(name), new) => match self.registries.0.get(name) {
+                Some(old) => self.check(old, new),
+                None => self.changed(old, new),
+            },
+            (old, Schema::Named(name)) => match self.registries.1.get(name) {
+                Some(new) => self.check(old, new),
+                None => self.changed(old, new),
+            },
+            (Schema::Any | Schema::Unknown, _) | (_, Schema::Any | Schema::Unknown) => {}
+            (Schema::Number(a), Schema::Number(b)) => {
+                if !covers(b, a) {
+                    self.issue(IssueKind::NumberNarrowed { from: a.clone(), to: b.clone() });
+                }
+                if !covers(a, b) {
+                    self.issue(IssueKind::NumberWidened { from: a.clone(), to: b.clone() });
+                }
+            }
+            (Schema::Option(a), Schema::Option(b)) => self.check(a, b),
+            (Schema::Option(a), b) => {
+                self.issue(IssueKind::OptionRemoved);
+                self.check(a, b);
+            }
+            (a, Schema::Option(b)) => {
+                self.issue(IssueKind::OptionAdded);
+                self.check(a, b);
+            }
+            (Schema::Seq(a), Schema::Seq(b)) => self.child("[]", a, b),
+            (Schema::Map { key: ka, value: va }, Schema::Map { key: kb, value: vb }) => {
+                self.child("{key}", ka, kb);
+                self.child("{}", va, vb);
+            }
+            (Schema::Tuple(a), Schema::Tuple(b)) if a.len() == b.len() => {
+                for (index, (a, b)) in a.iter().zip(b).enumerate() {
+                    self.child(&format!(".{index}"), a, b);
+                }
+            }
+            (Schema::Struct(a), Schema::Struct(b)) => {
+                if a.name != b.name {
+                    self.issue(IssueKind::Renamed { from: a.name, to: b.name });
+                }
+                self.check_fields(&a.fields, &b.fields, old, new);
+            }
+            (Schema::Enum(a), Schema::Enum(b)) => {
+                if a.name != b.name {
+                    self.issue(IssueKind::Renamed { from: a.name, to: b.name });
+                }
+                for (variant, fields) in &a.variants {
+                    match b.variants.get(variant) {
+                        Some(new_fields) => {
+                            let len = self.location.len();
+                            self.location.push_str(&format!("::{variant}"));
+                            self.check_fields(fields, new_fields, old, new);
+                            self.location.truncate(len);
+                        }
+                        None => self.issue(IssueKind::VariantRemoved(variant)),
+                    }
+                }
+                for variant in b.variants.keys().filter(|variant| !a.variants.contains_key(*variant)) {
+                    self.issue(IssueKind::VariantAdded(variant));
+                }
+            }
+            (Schema::OneOf(_), _) | (_, Schema::OneOf(_)) => self.check_alternatives(old, new),
+            (a, b) if mergeable(a, b) => {}
+            (a, b) => self.changed(a, b),
+        }
+    }
+
+    fn changed(&mut self, old: &Schema, new: &Schema) {
+        self.issue(IssueKind::TypeChanged { from: brief(old), to: brief(new) });
+    }
+
+    fn check_fields(&mut self, a: &Fields, b: &Fields, old: &Schema, new: &Schema) {
+        match (a, b) {
+            (Fields::Unit, Fields::Unit) => {}
+            (Fields::Tuple(a), Fields::Tuple(b)) if a.len() == b.len() => {
+                for (index, (a, b)) in a.iter().zip(b).enumerate() {
+                    self.child(&format!(".{index}"), a, b);
+                }
+            }
+            (Fields::Named(a), Fields::Named(b)) => {
+                for (name, field) in a {
+                    match b.get(name) {
+                        Some(new_field) => {
+                            match (field.required, new_field.required) {
+                                (false, true) => self.issue(IssueKind::FieldRequired(name)),
+                                (true, false) => self.issue(IssueKind::FieldOptional(name)),
+                                _ => {}
+                            }
+                            self.child(&format!(".{name}"), &field.schema, &new_field.schema);
+                        }
+                        None if field.required => self.issue(IssueKind::FieldRemoved(name)),
+                        None => {}
+                    }
+                }
+                for (name, field) in b {
+                    if field.required && !a.contains_key(name) {
+                        self.issue(IssueKind::FieldAdded(name));
+                    }
+                }
+            }
+            _ => self.changed(old, new),
+        }
+    }
+
+    /// Pairs up the alternatives of one-of schemas by shape
+    fn check_alternatives(&mut self, old: &Schema, new: &Schema) {
+        let (old_alternatives, new_alternatives) = (alternatives(old), alternatives(new));
+
+        for a in old_alternatives {
+            match new_alternatives.iter().find(|b| mergeable(a, b)) {
+                Some(b) => self.check(a, b),
+                None => self.issue(IssueKind::TypeChanged { from: brief(a), to: brief(new) }),
+            }
+        }
+        for b in new_alternatives {
+            if !old_alternatives.iter().any(|a| mergeable(a, b)) {
+                self.issue(IssueKind::TypeChanged { from: brief(old), to: brief(b) });
+            }
+        }
+    }
+}
+
+fn alternatives(schema: &Schema) -> &[Schema] {
+    match schema {
+        Schema::OneOf(alternatives) => alternatives,
+        schema => std::slice::from_ref(schema),
+    }
+}
+
+/// Whether every number of `inner` can be represented by `outer`
+fn covers(outer: &NumberSchema, inner: &NumberSchema) -> bool {
+    let kinds: Vec<_> = match inner.kinds.is_empty() {
+        true => NumberKind::ALL.to_vec(),
+        false => inner.kinds.iter().copied().collect(),
+    };
+    kinds.into_iter().all(|kind| {
+        let low = inner.min.map_or(kind.min_value(), |min| pick(min, kind.min_value(), Ordering::Greater));
+        let high = inner.max.map_or(kind.max_value(), |max| pick(max, kind.max_value(), Ordering::Less));
+        let in_range = !matches!(outer.min, Some(min) if low.numeric_cmp(&min) == Some(Ordering::Less))
+            && !matches!(outer.max, Some(max) if high.numeric_cmp(&max) == Some(Ordering::Greater));
+        let fits = outer.kinds.is_empty()
+            || outer.kinds.iter().any(|outer| outer.fits(&low) && outer.fits(&high) && (outer.is_float() || !kind.is_float()));
+        in_range && fits
+    })
+}
+
+#[test]
+fn test_compatibility() {
+    use serde_derive::{Deserialize, Serialize};
+    use crate::to_value;
+    use super::Tracer;
+
+    mod v1 {
+        use serde_derive::{Deserialize, Serialize};
+
+        #[derive(Serialize, Deserialize)]
+        pub struct Order {
+            pub id: u32,
+            pub note: String,
+            pub status: Status,
+            pub lines: Vec<Line>,
+            pub legacy: bool,
+        }
+
+        #[derive(Serialize, Deserialize)]
+        pub enum Status {
+            Open,
+            Cancelled,
+        }
+
+        #[derive(Serialize, Deserialize)]
+        pub struct Line(pub u16);
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Order {
+        id: u64,
+        note: Option<String>,
+        status: Status,
+        lines: Vec<Item>,
+        priority: u8,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum Status {
+        Open,
+        Shipped { carrier: String },
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Item(u8);
+
+    let mut old_tracer = Tracer::new();
+    let old = old_tracer.trace::<v1::Order>().unwrap();
+    let mut new_tracer = Tracer::new();
+    let new = new_tracer.trace::<Order>().unwrap();
+
+    let compatibility = Registry::compatibility(old_tracer.registry(), &old, new_tracer.registry(), &new);
+    let issues: Vec<_> = compatibility.issues.iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(issues, [
+        "$.id: number widened from u32 to u64 (breaks forward compatibility)",
+        "$: required field \"legacy\" removed (breaks forward compatibility)",
+        "$.lines[]: renamed from Line to Item (breaks backward and forward compatibility)",
+        "$.lines[].0: number narrowed from u16 to u8 (breaks backward compatibility)",
+        "$.note: became optional (breaks forward compatibility)",
+        "$.status: variant \"Cancelled\" removed (breaks backward compatibility)",
+        "$.status: variant \"Shipped\" added (breaks forward compatibility)",
+        "$: required field \"priority\" added (breaks backward compatibility)",
+    ]);
+    assert!(!compatibility.is_backward_compatible());
+    assert!(!compatibility.is_forward_compatible());
+
+    let snapshot = |values: Vec<crate::Value>| Schema::infer(&values);
+    let old = snapshot(vec![to_value(&v1::Status::Open).unwrap(), to_value(&v1::Status::Cancelled).unwrap()]);
+    let new = snapshot(vec![to_value(&Status::Open).unwrap()]);
+    let compatibility = Schema::compatibility(&old, &new);
+    assert!(compatibility.is_forward_compatible());
+    assert!(!compatibility.is_backward_compatible());
+    assert_eq!(Schema::compatibility(&new, &new), Compatibility::default());
+
+    let old = Schema::infer(&[crate::Value::from(7u32), crate::Value::from(9u32)]);
+    let new = Schema::infer(&[crate::Value::from(8u32), crate::Value::from(10u32)]);
+    assert_eq!(Schema::compatibility(&old, &new), Compatibility::default());
+    let new = Schema::infer(&[crate::Value::from(8u64)]);
+    let issues: Vec<_> = Schema::compatibility(&old, &new).issues.iter().map(|issue| issue.to_string()).collect();
+    assert_eq!(issues, ["$: number widened from u32 to u64 (breaks forward compatibility)"]);
+}
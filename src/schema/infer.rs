@@ -0,0 +1,252 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use crate::value::Value;
+use super::{pick, EnumSchema, Field, Fields, NumberSchema, Schema, StringSchema, StructSchema};
+
+impl Schema {
+    /// Infers the narrowest schema matching every sample, see [`Schema::union`].
+    ///
+    /// Without samples, the schema is [`Schema::Unknown`].
+    pub fn infer(samples: &[Value]) -> Schema {
+        union_of(samples)
+    }
+
+    /// The schema of a single value.
+    ///
+    /// Numbers are described by their kind alone, without a range, so that schemas inferred
+    /// from different samples of the same type are equal. `None` unit structs and `Some` tuple
+    /// structs are described by [`Schema::Option`], and every named field is required.
+    pub fn of(value: &Value) -> Schema {
+        match value {
+            Value::Unit => Schema::Unit,
+            Value::Bool(_) => Schema::Bool,
+            Value::Char(_) => Schema::Char,
+            Value::Number(number) => Schema::Number(NumberSchema::new([number.kind()])),
+            Value::String(_) => Schema::string(),
+            Value::Seq(values) => Schema::Seq(Box::new(union_of(values))),
+            Value::Map(map) => Schema::Map {
+                key: Box::new(union_of(map.keys())),
+                value: Box::new(union_of(map.values())),
+            },
+            Value::Tuple(values) => Schema::Tuple(values.iter().map(Schema::of).collect()),
+            Value::UnitStruct(val) if val.name == "None" => Schema::Option(Box::new(Schema::Unknown)),
+            Value::TupleStruct(val) if val.name == "Some" && val.values.len() == 1 => {
+                Schema::Option(Box::new(Schema::of(&val.values[0])))
+            }
+            Value::UnitStruct(val) => Schema::Struct(StructSchema { name: val.name, fields: Fields::Unit }),
+            Value::TupleStruct(val) => Schema::Struct(StructSchema { name: val.name, fields: tuple_fields(&val.values) }),
+            Value::NamedStruct(val) => Schema::Struct(StructSchema { name: val.name, fields: named_fields(&val.fields) }),
+            Value::UnitVariant(val) => variant(val.name, val.variant, Fields::Unit),
+            Value::TupleVariant(val) => variant(val.name, val.variant, tuple_fields(&val.values)),
+            Value::NamedVariant(val) => variant(val.name, val.variant, named_fields(&val.fields)),
+        }
+    }
+
+    /// The narrowest schema matching every value matched by either schema.
+    ///
+    /// Structs of the same name and shape merge their fields, a named field missing from
+    /// either side becomes optional. Enums of the same name merge their variants, numbers merge
+    /// their kinds and ranges. Schemas that cannot be merged become [`Schema::OneOf`].
+    /// [`Schema::Unknown`] is left out, while [`Schema::Any`] absorbs every other schema.
+    pub fn union(self, other: Schema) -> Schema {
+        if self == Schema::Any || other == Schema::Any {
+            return Schema::Any;
+        }
+        let mut alternatives = self.into_alternatives();
+        for schema in other.into_alternatives() {
+            add_alternative(&mut alternatives, schema);
+        }
+        match alternatives.len() {
+            0 => Schema::Unknown,
+            1 => alternatives.pop().unwrap(),
+            _ => Schema::OneOf(alternatives),
+        }
+    }
+
+    fn into_alternatives(self) -> Vec<Schema> {
+        match self {
+            Schema::OneOf(alternatives) => alternatives,
+            Schema::Unknown => Vec::new(),
+            schema => vec![schema],
+        }
+    }
+}
+
+impl NumberSchema {
+    /// Accepts the kinds of both schemas, over the range covering both. An empty set of kinds
+    /// accepts any kind
+    pub fn union(mut self, other: NumberSchema) -> NumberSchema {
+        if self.kinds.is_empty() || other.kinds.is_empty() {
+            self.kinds.clear();
+        } else {
+            self.kinds.extend(other.kinds);
+        }
+        self.min = self.min.zip(other.min).map(|(a, b)| pick(a, b, Ordering::Less));
+        self.max = self.max.zip(other.max).map(|(a, b)| pick(a, b, Ordering::Greater));
+        self
+    }
+}
+
+impl StringSchema {
+    /// Accepts the lengths of both schemas, keeping the pattern only if both share it
+    pub fn union(self, other: StringSchema) -> StringSchema {
+        StringSchema {
+            min_len: self.min_len.zip(other.min_len).map(|(a, b)| a.min(b)),
+            max_len: self.max_len.zip(other.max_len).map(|(a, b)| a.max(b)),
+            pattern: self.pattern.filter(|pattern| other.pattern.as_ref() == Some(pattern)),
+        }
+    }
+}
+
+fn union_of<'v>(values: impl IntoIterator<Item=&'v Value>) -> Schema {
+    values.into_iter().map(Schema::of).fold(Schema::Unknown, Schema::union)
+}
+
+fn tuple_fields(values: &[Value]) -> Fields {
+    Fields::Tuple(values.iter().map(Schema::of).collect())
+}
+
+fn named_fields(fields: &BTreeMap<&'static str, Value>) -> Fields {
+    Fields::Named(fields.iter()
+        .map(|(name, value)| (*name, Field::required(Schema::of(value))))
+        .collect())
+}
+
+fn variant(name: &'static str, variant: &'static str, fields: Fields) -> Schema {
+    Schema::Enum(EnumSchema { name, variants: [(variant, fields)].into() })
+}
+
+fn add_alternative(alternatives: &mut Vec<Schema>, schema: Schema) {
+    match alternatives.iter_mut().find(|alternative| mergeable(alternative, &schema)) {
+        Some(alternative) => *alternative = merge(std::mem::replace(alternative, Schema::Unknown), schema),
+        None => alternatives.push(schema),
+    }
+}
+
+/// Whether two schemas have the same shape and can be merged
+pub(super) fn mergeable(a: &Schema, b: &Schema) -> bool {
+    match (a, b) {
+        (Schema::Unit, Schema::Unit)
+        | (Schema::Bool, Schema::Bool)
+        | (Schema::Char, Schema::Char)
+        | (Schema::String(_), Schema::String(_))
+        | (Schema::Number(_), Schema::Number(_))
+        | (Schema::Option(_), Schema::Option(_))
+        | (Schema::Seq(_), Schema::Seq(_))
+        | (Schema::Map { .. }, Schema::Map { .. }) => true,
+        (Schema::Tuple(a), Schema::Tuple(b)) => a.len() == b.len(),
+        (Schema::Named(a), Schema::Named(b)) => a == b,
+        (Schema::Struct(a), Schema::Struct(b)) => a.name == b.name && a.fields.same_shape(&b.fields),
+        (Schema::Enum(a), Schema::Enum(b)) => a.name == b.name && compatible_variants(a, b),
+        _ => false,
+    }
+}
+
+/// Merges two [`mergeable`] schemas
+fn merge(a: Schema, b: Schema) -> Schema {
+    match (a, b) {
+        (Schema::Number(a), Schema::Number(b)) => Schema::Number(a.union(b)),
+        (Schema::String(a), Schema::String(b)) => Schema::String(a.union(b)),
+        (Schema::Option(a), Schema::Option(b)) => Schema::Option(Box::new(a.union(*b))),
+        (Schema::Seq(a), Schema::Seq(b)) => Schema::Seq(Box::new(a.union(*b))),
+        (Schema::Map { key: ka, value: va }, Schema::Map { key: kb, value: vb }) => Schema::Map {
+            key: Box::new(ka.union(*kb)),
+            value: Box::new(va.union(*vb)),
+        },
+        (Schema::Tuple(a), Schema::Tuple(b)) => Schema::Tuple(union_elements(a, b)),
+        (Schema::Struct(a), Schema::Struct(b)) => {
+            Schema::Struct(StructSchema { name: a.name, fields: union_fields(a.fields, b.fields) })
+        }
+        (Schema::Enum(mut a), Schema::Enum(b)) => {
+            for (variant, fields) in b.variants {
+                let merged = match a.variants.remove(variant) {
+                    Some(existing) => union_fields(existing, fields),
+                    None => fields,
+                };
+                a.variants.insert(variant, merged);
+            }
+            Schema::Enum(a)
+        }
+        (a, _) => a,
+    }
+}
+
+fn compatible_variants(a: &EnumSchema, b: &EnumSchema) -> bool {
+    b.variants.iter().all(|(variant, fields)| {
+        !matches!(a.variants.get(variant), Some(existing) if !existing.same_shape(fields))
+    })
+}
+
+fn union_elements(a: Vec<Schema>, b: Vec<Schema>) -> Vec<Schema> {
+    a.into_iter().zip(b).map(|(a, b)| a.union(b)).collect()
+}
+
+/// Merges fields of the same shape
+fn union_fields(a: Fields, b: Fields) -> Fields {
+    match (a, b) {
+        (Fields::Tuple(a), Fields::Tuple(b)) => Fields::Tuple(union_elements(a, b)),
+        (Fields::Named(mut a), Fields::Named(mut b)) => {
+            for (name, field) in a.iter_mut() {
+                match b.remove(name) {
+                    Some(other) => {
+                        field.required &= other.required;
+                        field.schema = std::mem::replace(&mut field.schema, Schema::Unknown).union(other.schema);
+                    }
+                    None => field.required = false,
+                }
+            }
+            a.extend(b.into_iter().map(|(name, field)| (name, Field::optional(field.schema))));
+            Fields::Named(a)
+        }
+        (a, _) => a,
+    }
+}
+
+#[test]
+fn test_infer() {
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize)]
+    struct Event {
+        id: u32,
+        tags: Vec<&'static str>,
+        origin: Option<(char, i8)>,
+        kind: Kind,
+    }
+
+    #[derive(Serialize)]
+    enum Kind {
+        Click,
+        Key(char),
+    }
+
+    #[derive(Serialize)]
+    #[serde(rename = "Event")]
+    struct EventV2 {
+        id: u64,
+        extra: bool,
+    }
+
+    let samples = [
+        to_value(&Event { id: 7, tags: vec![], origin: None, kind: Kind::Click }).unwrap(),
+        to_value(&Event { id: 70_000, tags: vec!["a"], origin: Some(('x', -3)), kind: Kind::Key('k') }).unwrap(),
+        to_value(&EventV2 { id: 1, extra: true }).unwrap(),
+    ];
+    assert_eq!(
+        Schema::infer(&samples).to_string(),
+        "Event { extra?: bool, id: u32|u64, kind?: Kind::{Click, Key(char)}, \
+         origin?: Option<(char, i8)>, tags?: [String] }",
+    );
+
+    let mixed = [Value::from(1u8), Value::from("a"), Value::from(vec![2u8]), Value::from(3i16)];
+    assert_eq!(Schema::infer(&mixed).to_string(), "u8|i16 | String | [u8]");
+    assert_eq!(Schema::infer(&[]), Schema::Unknown);
+    assert_eq!(Schema::infer(&[Value::from(Vec::<u8>::new())]).to_string(), "[_]");
+    assert_eq!(Schema::of(&Value::Map(Default::default())).union(Schema::Unknown).to_string(), "{_: _}");
+    assert_eq!(Schema::Any.union(Schema::Bool), Schema::Any);
+    let declared = Schema::Number(NumberSchema::new([crate::value::NumberKind::U8]).range(1u8, 5u8));
+    assert_eq!(declared.union(Schema::Number(NumberSchema::new([]).range(3u8, 9u8))).to_string(), "number (1..=9)");
+    assert_eq!(Schema::of(&Value::from(true)).union(Schema::Any).to_string(), "any");
+    assert_eq!(Schema::Seq(Box::new(Schema::Any)).union(Schema::infer(&[Value::from(vec![1u8])])).to_string(), "[any]");
+}
@@ -0,0 +1,266 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use crate::value::{Number, NumberKind, Value};
+use super::validate::{pattern_tokens, Token};
+use super::{pick, EnumSchema, Fields, NumberSchema, Registry, Schema, StringSchema, StructSchema};
+
+/// The dialect of the exported documents
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl Schema {
+    /// Converts this schema into a JSON Schema document, see [`Registry::to_json_schema`].
+    ///
+    /// [`Schema::Named`] references are exported as `$ref`s to `#/$defs/<name>`, which are left
+    /// unresolved.
+    pub fn to_json_schema(&self) -> Value {
+        Registry::default().to_json_schema(self)
+    }
+}
+
+impl Registry {
+    /// Converts `root` into a JSON Schema (draft 2020-12) document, with every type of this
+    /// registry under `$defs`.
+    ///
+    /// The document describes the JSON produced by serde for the described types:
+    ///
+    /// - enums use serde's default externally tagged representation: unit variants are
+    ///   strings, other variants are objects with a single property named after the variant;
+    /// - options are `null` or their inner value, unit structs are `null` and newtype structs
+    ///   are their inner value;
+    /// - integer widths become `minimum` and `maximum` bounds, narrowed by the range of the
+    ///   [`NumberSchema`], floats are only bounded by that range;
+    /// - maps are objects, whose keys are only constrained if they are strings;
+    /// - string patterns become anchored regular expressions.
+    ///
+    /// ```
+    /// use serde_value::{NumberKind, Value};
+    /// use serde_value::schema::Schema;
+    ///
+    /// let schema = Schema::Seq(Box::new(Schema::number([NumberKind::U8])));
+    /// let json = schema.to_json_schema();
+    /// assert_eq!(json["type"], Value::from("array"));
+    /// assert_eq!(json["items"]["type"], Value::from("integer"));
+    /// assert_eq!(json["items"]["maximum"], Value::from(255u8));
+    /// ```
+    pub fn to_json_schema(&self, root: &Schema) -> Value {
+        let mut document = match convert(root) {
+            Value::Map(map) => map,
+            _ => BTreeMap::new(),
+        };
+        document.insert(Value::from("$schema"), Value::from(DIALECT));
+        if !self.types.is_empty() {
+            let defs = self.types.iter().map(|(name, schema)| (*name, convert(schema)));
+            document.insert(Value::from("$defs"), object(defs));
+        }
+        Value::Map(document)
+    }
+}
+
+/// Builds a JSON object
+fn object<'a>(properties: impl IntoIterator<Item=(&'a str, Value)>) -> Value {
+    Value::Map(properties.into_iter()
+        .map(|(name, value)| (Value::from(name), value))
+        .collect())
+}
+
+fn convert(schema: &Schema) -> Value {
+    match schema {
+        Schema::Any | Schema::Unknown => object([]),
+        Schema::Unit => object([("type", Value::from("null"))]),
+        Schema::Bool => object([("type", Value::from("boolean"))]),
+        Schema::Char => object([
+            ("type", Value::from("string")),
+            ("minLength", Value::from(1u8)),
+            ("maxLength", Value::from(1u8)),
+        ]),
+        Schema::Number(number) => convert_number(number),
+        Schema::String(string) => convert_string(string),
+        Schema::Option(inner) => object([
+            ("anyOf", Value::Seq(vec![object([("type", Value::from("null"))]), convert(inner)])),
+        ]),
+        Schema::Seq(inner) => object([("type", Value::from("array")), ("items", convert(inner))]),
+        Schema::Map { key, value } => {
+            let mut properties = vec![("type", Value::from("object")), ("additionalProperties", convert(value))];
+            if let Schema::String(_) = **key {
+                properties.push(("propertyNames", convert(key)));
+            }
+            object(properties)
+        }
+        Schema::Tuple(elements) => convert_tuple(elements),
+        Schema::Struct(val) => convert_struct(val),
+        Schema::Enum(val) => convert_enum(val),
+        Schema::Named(name) => object([("$ref", Value::from(format!("#/$defs/{name}")))]),
+        Schema::OneOf(alternatives) => object([("anyOf", alternatives.iter().map(convert).collect())]),
+    }
+}
+
+fn convert_number(schema: &NumberSchema) -> Value {
+    let integer = !schema.kinds.is_empty() && schema.kinds.iter().all(|kind| !kind.is_float());
+    let mut properties = vec![("type", Value::from(if integer { "integer" } else { "number" }))];
+
+    let (mut min, mut max) = (schema.min, schema.max);
+    if integer {
+        let widest = |bound: fn(NumberKind) -> Number, keep| schema.kinds.iter()
+            .map(|kind| bound(*kind))
+            .reduce(|a, b| pick(a, b, keep))
+            .unwrap();
+        let (widest_min, widest_max) = (widest(NumberKind::min_value, Ordering::Less), widest(NumberKind::max_value, Ordering::Greater));
+        min = Some(min.map_or(widest_min, |min| pick(min, widest_min, Ordering::Greater)));
+        max = Some(max.map_or(widest_max, |max| pick(max, widest_max, Ordering::Less)));
+    }
+    if let Some(min) = min {
+        properties.push(("minimum", Value::Number(min)));
+    }
+    if let Some(max) = max {
+        properties.push(("maximum", Value::Number(max)));
+    }
+    object(properties)
+}
+
+fn convert_string(schema: &StringSchema) -> Value {
+    let mut properties = vec![("type", Value::from("string"))];
+    if let Some(min) = schema.min_len {
+        properties.push(("minLength", Value::from(min as u64)));
+    }
+    if let Some(max) = schema.max_len {
+        properties.push(("maxLength", Value::from(max as u64)));
+    }
+    if let Some(pattern) = &schema.pattern {
+        let mut regex = String::from("^");
+        for token in pattern_tokens(pattern) {
+            match token {
+                Token::Star => regex.push_str(".*"),
+                Token::Any => regex.push('.'),
+                Token::Char(c) => {
+                    if "\\.^$|?*+()[]{}/".contains(c) {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+            }
+        }
+        regex.push('$');
+        properties.push(("pattern", Value::from(regex)));
+    }
+    object(properties)
+}
+
+fn convert_tuple(elements: &[Schema]) -> Value {
+    object([
+        ("type", Value::from("array")),
+        ("prefixItems", elements.iter().map(convert).collect()),
+        ("items", Value::Bool(false)),
+        ("minItems", Value::from(elements.len() as u64)),
+    ])
+}
+
+/// Converts the fields of a struct or variant, as serde writes them
+fn convert_fields(fields: &Fields) -> Value {
+    match fields {
+        Fields::Unit => object([("type", Value::from("null"))]),
+        Fields::Tuple(elements) if elements.len() == 1 => convert(&elements[0]),
+        Fields::Tuple(elements) => convert_tuple(elements),
+        Fields::Named(fields) => {
+            let properties = fields.iter().map(|(name, field)| (*name, convert(&field.schema)));
+            let required: Vec<_> = fields.iter()
+                .filter(|(_, field)| field.required)
+                .map(|(name, _)| Value::from(*name))
+                .collect();
+            object([
+                ("type", Value::from("object")),
+                ("properties", object(properties)),
+                ("required", Value::Seq(required)),
+                ("additionalProperties", Value::Bool(false)),
+            ])
+        }
+    }
+}
+
+fn convert_struct(schema: &StructSchema) -> Value {
+    let mut converted = convert_fields(&schema.fields);
+    if let Value::Map(map) = &mut converted {
+        map.insert(Value::from("title"), Value::from(schema.name));
+    }
+    converted
+}
+
+fn convert_enum(schema: &EnumSchema) -> Value {
+    let units: Vec<_> = schema.variants.iter()
+        .filter(|(_, fields)| **fields == Fields::Unit)
+        .map(|(variant, _)| Value::from(*variant))
+        .collect();
+
+    let mut alternatives = Vec::new();
+    if !units.is_empty() {
+        alternatives.push(object([("enum", Value::Seq(units))]));
+    }
+    for (variant, fields) in schema.variants.iter().filter(|(_, fields)| **fields != Fields::Unit) {
+        alternatives.push(object([
+            ("type", Value::from("object")),
+            ("properties", object([(*variant, convert_fields(fields))])),
+            ("required", Value::from(vec![*variant])),
+            ("additionalProperties", Value::Bool(false)),
+        ]));
+    }
+    object([("title", Value::from(schema.name)), ("oneOf", Value::Seq(alternatives))])
+}
+
+#[test]
+fn test_json_schema() {
+    use serde_derive::Deserialize;
+    use super::{Field, Tracer};
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Order {
+        id: u32,
+        ratio: f64,
+        status: Status,
+        items: Vec<(String, i8)>,
+        note: Option<String>,
+    }
+
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    enum Status {
+        Open,
+        Closed,
+        Held(u16),
+        Moved { to: String },
+    }
+
+    let mut tracer = Tracer::new();
+    let root = tracer.trace::<Order>().unwrap();
+    let json = tracer.registry().to_json_schema(&root);
+
+    assert_eq!(json["$schema"], Value::from(DIALECT));
+    assert_eq!(json["$ref"], Value::from("#/$defs/Order"));
+    let order = &json["$defs"]["Order"];
+    assert_eq!(order["title"], Value::from("Order"));
+    assert_eq!(order["required"], Value::from(vec!["id", "items", "note", "ratio", "status"]));
+    assert_eq!(order["properties"]["id"]["minimum"], Value::from(0u32));
+    assert_eq!(order["properties"]["id"]["maximum"], Value::from(u32::MAX));
+    assert_eq!(order["properties"]["ratio"], object([("type", Value::from("number"))]));
+    assert_eq!(order["properties"]["items"]["items"]["prefixItems"][1]["minimum"], Value::from(-128i8));
+    assert_eq!(order["properties"]["note"]["anyOf"][0]["type"], Value::from("null"));
+
+    let status = &json["$defs"]["Status"]["oneOf"];
+    assert_eq!(status[0]["enum"], Value::from(vec!["Closed", "Open"]));
+    assert_eq!(status[1]["properties"]["Held"]["maximum"], Value::from(u16::MAX));
+    assert_eq!(status[2]["properties"]["Moved"]["properties"]["to"]["type"], Value::from("string"));
+    assert_eq!(status[2]["required"], Value::from(vec!["Moved"]));
+
+    let declared = Schema::Struct(StructSchema {
+        name: "Port",
+        fields: Fields::Named([
+            ("port", Field::required(Schema::Number(NumberSchema::new([NumberKind::U16]).range(1024u16, 70000u32)))),
+            ("host", Field::optional(Schema::String(StringSchema::default().pattern("*.example.com")))),
+        ].into()),
+    });
+    let json = declared.to_json_schema();
+    assert_eq!(json["properties"]["port"]["minimum"], Value::from(1024u16));
+    assert_eq!(json["properties"]["port"]["maximum"], Value::from(u16::MAX));
+    assert_eq!(json["properties"]["host"]["pattern"], Value::from("^.*\\.example\\.com$"));
+    assert_eq!(json["required"], Value::from(vec!["port"]));
+    assert!(json.get("$defs").is_none());
+}
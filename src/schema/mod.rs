@@ -0,0 +1,342 @@
+//! Type descriptions of [`Value`] trees
+//!
+//! A [`Schema`] describes the shape of a family of values in the terms of this crate: struct
+//! and enum names with their fields, number widths, sequences, maps and tuples. A schema can be
+//! inferred from sample values with [`Schema::infer`], which is useful to reverse-engineer the
+//! shape of a `Serialize` type whose source is not available. A schema can also be declared by
+//! hand, with constraints on string lengths and number ranges, and used to
+//! [validate](Schema::validate) values received from untrusted sources. Finally, the schemas
+//! of a type and of every type it contains can be captured from its `Deserialize`
+//! implementation with a [`Tracer`].
+//!
+//! Schemas can be exported as [JSON Schema](Registry::to_json_schema) documents, and two
+//! versions of a schema can be [compared](Registry::compatibility) to find the changes that
+//! break reading old values with the new schema, or new values with the old one.
+//!
+//! ```
+//! use serde_derive::Serialize;
+//! use serde_value::schema::Schema;
+//!
+//! #[derive(Serialize)]
+//! enum Shape {
+//!     Circle { radius: u16 },
+//!     Square(u8),
+//! }
+//!
+//! let samples = [
+//!     serde_value::to_value(&Shape::Circle { radius: 300 }).unwrap(),
+//!     serde_value::to_value(&Shape::Square(4)).unwrap(),
+//! ];
+//! let schema = Schema::infer(&samples);
+//! assert_eq!(schema.to_string(), "Shape::{Circle { radius: u16 }, Square(u8)}");
+//! ```
+
+mod compat;
+mod infer;
+mod json_schema;
+mod trace;
+mod validate;
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+use crate::value::{Number, NumberKind};
+pub use compat::{Compatibility, Direction, Issue, IssueKind};
+pub use trace::{TraceError, Tracer};
+pub use validate::{ValidationError, ValidationErrorKind};
+
+/// A description of the shape of a [`Value`](crate::Value)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Schema {
+    /// Any value
+    Any,
+    /// No value observed yet, e.g. the elements of an empty sequence. [`Schema::union`] leaves
+    /// it out, and it accepts any value like [`Schema::Any`] otherwise
+    Unknown,
+    Unit,
+    Bool,
+    Char,
+    Number(NumberSchema),
+    String(StringSchema),
+    /// An `Option`, which [`to_value`](crate::to_value) encodes as a `None` unit struct or a
+    /// `Some` tuple struct
+    Option(Box<Schema>),
+    /// A sequence whose elements all match the inner schema
+    Seq(Box<Schema>),
+    /// A map whose keys and values match the inner schemas
+    Map {
+        key: Box<Schema>,
+        value: Box<Schema>,
+    },
+    /// A tuple with one schema per element
+    Tuple(Vec<Schema>),
+    Struct(StructSchema),
+    Enum(EnumSchema),
+    /// A struct or enum described in a [`Registry`]
+    Named(&'static str),
+    /// A value matching one of several schemas
+    OneOf(Vec<Schema>),
+}
+
+/// Struct and enum schemas by name, referenced by [`Schema::Named`]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Registry {
+    pub types: BTreeMap<&'static str, Schema>,
+}
+
+/// The widths and range of a number
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NumberSchema {
+    /// The accepted kinds
+    pub kinds: BTreeSet<NumberKind>,
+    /// The smallest value, if bounded
+    pub min: Option<Number>,
+    /// The largest value, if bounded
+    pub max: Option<Number>,
+}
+
+/// The length and pattern of a string
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StringSchema {
+    /// The smallest length in characters
+    pub min_len: Option<usize>,
+    /// The largest length in characters
+    pub max_len: Option<usize>,
+    /// A pattern the whole string must match, where `*` matches any run of characters, `?`
+    /// matches a single character and `\` escapes the next character
+    pub pattern: Option<String>,
+}
+
+/// A struct and its fields
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StructSchema {
+    pub name: &'static str,
+    pub fields: Fields,
+}
+
+/// An enum and its variants
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EnumSchema {
+    pub name: &'static str,
+    pub variants: BTreeMap<&'static str, Fields>,
+}
+
+/// The fields of a struct or enum variant
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Fields {
+    Unit,
+    Tuple(Vec<Schema>),
+    Named(BTreeMap<&'static str, Field>),
+}
+
+/// A named field
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Field {
+    pub schema: Schema,
+    /// Whether the field is always present
+    pub required: bool,
+}
+
+impl Schema {
+    /// An unconstrained string
+    pub fn string() -> Self {
+        Schema::String(StringSchema::default())
+    }
+
+    /// Any number of the given `kinds`, or of any kind if empty
+    pub fn number(kinds: impl IntoIterator<Item=NumberKind>) -> Self {
+        Schema::Number(NumberSchema::new(kinds))
+    }
+}
+
+impl Registry {
+    /// The schema of the struct or enum called `name`
+    pub fn get(&self, name: &str) -> Option<&Schema> {
+        self.types.get(name)
+    }
+
+    /// Adds a struct or enum schema under its name, returning the schema it replaces.
+    ///
+    /// Other schemas have no name and are not added.
+    pub fn insert(&mut self, schema: Schema) -> Option<Schema> {
+        let name = match &schema {
+            Schema::Struct(val) => val.name,
+            Schema::Enum(val) => val.name,
+            _ => return None,
+        };
+        self.types.insert(name, schema)
+    }
+}
+
+impl NumberSchema {
+    /// A schema accepting any number of the given `kinds`, or of any kind if empty
+    pub fn new(kinds: impl IntoIterator<Item=NumberKind>) -> Self {
+        Self { kinds: kinds.into_iter().collect(), min: None, max: None }
+    }
+
+    /// Restricts the range to `min..=max`
+    pub fn range(mut self, min: impl Into<Number>, max: impl Into<Number>) -> Self {
+        self.min = Some(min.into());
+        self.max = Some(max.into());
+        self
+    }
+}
+
+impl StringSchema {
+    /// Restricts the length to `min..=max` characters
+    pub fn length(mut self, min: usize, max: usize) -> Self {
+        self.min_len = Some(min);
+        self.max_len = Some(max);
+        self
+    }
+
+    /// Requires the string to match a `pattern`, see the `pattern` field for the syntax
+    pub fn pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.pattern = Some(pattern.into());
+        self
+    }
+}
+
+impl Fields {
+    /// Whether both are unit, tuples of the same length, or named
+    pub fn same_shape(&self, other: &Fields) -> bool {
+        match (self, other) {
+            (Fields::Unit, Fields::Unit) | (Fields::Named(_), Fields::Named(_)) => true,
+            (Fields::Tuple(a), Fields::Tuple(b)) => a.len() == b.len(),
+            _ => false,
+        }
+    }
+}
+
+impl Field {
+    pub fn required(schema: Schema) -> Self {
+        Self { schema, required: true }
+    }
+
+    pub fn optional(schema: Schema) -> Self {
+        Self { schema, required: false }
+    }
+}
+
+impl Display for Schema {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Schema::Any => f.write_str("any"),
+            Schema::Unknown => f.write_str("_"),
+            Schema::Unit => f.write_str("()"),
+            Schema::Bool => f.write_str("bool"),
+            Schema::Char => f.write_str("char"),
+            Schema::Number(number) => write!(f, "{number}"),
+            Schema::String(string) => write!(f, "{string}"),
+            Schema::Option(inner) => write!(f, "Option<{inner}>"),
+            Schema::Seq(inner) => write!(f, "[{inner}]"),
+            Schema::Map { key, value } => write!(f, "{{{key}: {value}}}"),
+            Schema::Tuple(elements) => {
+                f.write_str("(")?;
+                write_list(f, elements)?;
+                f.write_str(")")
+            }
+            Schema::Struct(val) => write!(f, "{}{}", val.name, val.fields),
+            Schema::Enum(val) => {
+                write!(f, "{}::{{", val.name)?;
+                for (i, (variant, fields)) in val.variants.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{variant}{fields}")?;
+                }
+                f.write_str("}")
+            }
+            Schema::Named(name) => f.write_str(name),
+            Schema::OneOf(alternatives) => {
+                for (i, alternative) in alternatives.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" | ")?;
+                    }
+                    write!(f, "{alternative}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for NumberSchema {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.kinds.is_empty() {
+            f.write_str("number")?;
+        }
+        for (i, kind) in self.kinds.iter().enumerate() {
+            if i > 0 {
+                f.write_str("|")?;
+            }
+            write!(f, "{kind}")?;
+        }
+        match (&self.min, &self.max) {
+            (None, None) => Ok(()),
+            (Some(min), None) => write!(f, " ({min}..)"),
+            (None, Some(max)) => write!(f, " (..={max})"),
+            (Some(min), Some(max)) => write!(f, " ({min}..={max})"),
+        }
+    }
+}
+
+impl Display for StringSchema {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("String")?;
+        let len = match (self.min_len, self.max_len) {
+            (None, None) => None,
+            (Some(min), None) => Some(format!("len {min}..")),
+            (None, Some(max)) => Some(format!("len ..={max}")),
+            (Some(min), Some(max)) => Some(format!("len {min}..={max}")),
+        };
+        match (len, &self.pattern) {
+            (None, None) => Ok(()),
+            (Some(len), None) => write!(f, " ({len})"),
+            (None, Some(pattern)) => write!(f, " ({pattern:?})"),
+            (Some(len), Some(pattern)) => write!(f, " ({len}, {pattern:?})"),
+        }
+    }
+}
+
+impl Display for Fields {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Fields::Unit => Ok(()),
+            Fields::Tuple(elements) => {
+                f.write_str("(")?;
+                write_list(f, elements)?;
+                f.write_str(")")
+            }
+            Fields::Named(fields) => {
+                f.write_str(" {")?;
+                for (i, (name, field)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    let optional = if field.required { "" } else { "?" };
+                    write!(f, " {name}{optional}: {}", field.schema)?;
+                }
+                f.write_str(" }")
+            }
+        }
+    }
+}
+
+fn write_list(f: &mut Formatter<'_>, schemas: &[Schema]) -> std::fmt::Result {
+    for (i, schema) in schemas.iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{schema}")?;
+    }
+    Ok(())
+}
+
+/// Picks `b` if it compares to `a` as `keep`, or `a` otherwise
+pub(super) fn pick(a: Number, b: Number, keep: Ordering) -> Number {
+    match b.numeric_cmp(&a) {
+        Some(ordering) if ordering == keep => b,
+        _ => a,
+    }
+}
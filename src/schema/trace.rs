@@ -0,0 +1,567 @@
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use serde::de::{self, DeserializeSeed, Deserialize, IntoDeserializer, Visitor};
+use crate::value::NumberKind;
+use super::{EnumSchema, Field, Fields, Registry, Schema, StructSchema};
+
+/// The deepest nesting of structs and enums a trace follows
+const MAX_DEPTH: usize = 128;
+
+/// Captures the schema of a type from its `Deserialize` implementation.
+///
+/// The tracer drives `T::deserialize` with a deserializer that records every type it is
+/// asked for and answers with placeholder values: zero, empty strings, a single element per
+/// sequence and map, and `Some` for options. Structs and enums are recorded in a [`Registry`]
+/// and referenced by [`Schema::Named`], so that recursive types can be described. Enums are
+/// traced one variant at a time, deserializing `T` again until every variant is known.
+///
+/// Tracing relies on the `deserialize_*` hints of the implementation: types calling
+/// `deserialize_any` cannot be traced, and types rejecting the placeholder values (e.g. a
+/// non-zero integer) fail with a [`TraceError`].
+///
+/// ```
+/// use serde_derive::Deserialize;
+/// use serde_value::schema::Tracer;
+///
+/// #[derive(Deserialize)]
+/// struct Node {
+///     label: String,
+///     children: Vec<Node>,
+/// }
+///
+/// let mut tracer = Tracer::new();
+/// let schema = tracer.trace::<Node>().unwrap();
+/// assert_eq!(schema.to_string(), "Node");
+/// assert_eq!(tracer.registry().types["Node"].to_string(), "Node { children: [Node], label: String }");
+/// ```
+#[derive(Debug, Default)]
+pub struct Tracer {
+    registry: Registry,
+    /// The declared variants of every enum seen so far
+    enums: BTreeMap<&'static str, &'static [&'static str]>,
+    /// The structs and enum variants being traced
+    stack: Vec<(&'static str, Option<&'static str>)>,
+    /// The steps from the root to the value being traced
+    steps: Vec<Step>,
+    /// The sequences, maps and options being traced, relative to their closest container
+    sites: Vec<String>,
+}
+
+#[derive(Debug)]
+enum Step {
+    Container(&'static str),
+    Segment(String),
+}
+
+/// An error produced while tracing a type
+#[derive(Debug)]
+pub struct TraceError {
+    message: String,
+}
+
+impl std::error::Error for TraceError {}
+
+impl de::Error for TraceError {
+    fn custom<T>(msg: T) -> Self where T: Display {
+        TraceError {
+            message: format!("{msg}"),
+        }
+    }
+}
+
+impl Display for TraceError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Traces `T`, adding the structs and enums it contains to the registry.
+    ///
+    /// Returns the schema of `T`, which is a [`Schema::Named`] reference if `T` is a struct or
+    /// an enum.
+    pub fn trace<'de, T: Deserialize<'de>>(&mut self) -> Result<Schema, TraceError> {
+        loop {
+            self.stack.clear();
+            self.steps.clear();
+            self.sites.clear();
+
+            let pending = self.pending();
+            let mut schema = Schema::Any;
+            T::deserialize(Deserializer { tracer: self, schema: &mut schema })?;
+
+            match self.pending() {
+                0 => return Ok(schema),
+                remaining if remaining >= pending && pending != 0 => {
+                    return Err(de::Error::custom("enum variants could not be traced"));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// The structs and enums traced so far
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    pub fn into_registry(self) -> Registry {
+        self.registry
+    }
+
+    /// The number of enum variants not traced yet
+    fn pending(&self) -> usize {
+        self.enums.iter()
+            .map(|(name, variants)| variants.len() - self.traced_variants(name).len())
+            .sum()
+    }
+
+    fn traced_variants(&self, name: &str) -> Vec<&'static str> {
+        match self.registry.get(name) {
+            Some(Schema::Enum(val)) => val.variants.keys().copied().collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn enter(&mut self, name: &'static str, variant: Option<&'static str>) -> Result<(), TraceError> {
+        if self.stack.len() >= MAX_DEPTH {
+            return Err(de::Error::custom(format_args!("{name} is nested too deeply")));
+        }
+        self.stack.push((name, variant));
+        self.steps.push(Step::Container(name));
+        Ok(())
+    }
+
+    fn exit(&mut self) {
+        self.stack.pop();
+        self.steps.pop();
+    }
+
+    /// The site of a sequence, map or option about to be traced, or `None` if the same site is
+    /// already being traced in an enclosing value
+    fn site(&self, tag: &str) -> Option<String> {
+        let start = self.steps.iter()
+            .rposition(|step| matches!(step, Step::Container(_)))
+            .unwrap_or(0);
+        let mut site = String::new();
+        for step in &self.steps[start..] {
+            match step {
+                Step::Container(name) => site.push_str(name),
+                Step::Segment(segment) => site.push_str(segment),
+            }
+            site.push('/');
+        }
+        site.push_str(tag);
+        (!self.sites.contains(&site)).then_some(site)
+    }
+
+    /// Picks the variant of enum `name` to trace next: a variant that is not traced yet, or
+    /// else one that is not being traced in an enclosing value
+    fn choose_variant(&self, name: &'static str, variants: &'static [&'static str]) -> Option<usize> {
+        let traced = self.traced_variants(name);
+        let active = |variant: &&str| self.stack.contains(&(name, Some(*variant)));
+        variants.iter().position(|variant| !traced.contains(variant) && !active(variant))
+            .or_else(|| variants.iter().position(|variant| !active(variant)))
+    }
+
+    fn record(&mut self, schema: Schema) {
+        self.registry.insert(schema);
+    }
+}
+
+/// Traces one value into `schema`
+struct Deserializer<'t> {
+    tracer: &'t mut Tracer,
+    schema: &'t mut Schema,
+}
+
+macro_rules! trace_number {
+    ($($method:ident => $visit:ident($kind:ident, $zero:expr)),* $(,)?) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                *self.schema = Schema::number([NumberKind::$kind]);
+                visitor.$visit($zero)
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for Deserializer<'_> {
+    type Error = TraceError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+        Err(de::Error::custom("types calling deserialize_any cannot be traced"))
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.schema = Schema::Bool;
+        visitor.visit_bool(false)
+    }
+
+    trace_number! {
+        deserialize_u8 => visit_u8(U8, 0),
+        deserialize_u16 => visit_u16(U16, 0),
+        deserialize_u32 => visit_u32(U32, 0),
+        deserialize_u64 => visit_u64(U64, 0),
+        deserialize_u128 => visit_u128(U128, 0),
+        deserialize_i8 => visit_i8(I8, 0),
+        deserialize_i16 => visit_i16(I16, 0),
+        deserialize_i32 => visit_i32(I32, 0),
+        deserialize_i64 => visit_i64(I64, 0),
+        deserialize_i128 => visit_i128(I128, 0),
+        deserialize_f32 => visit_f32(F32, 0.0),
+        deserialize_f64 => visit_f64(F64, 0.0),
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.schema = Schema::Char;
+        visitor.visit_char('\0')
+    }
+
+    fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.schema = Schema::string();
+        visitor.visit_borrowed_str("")
+    }
+
+    fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        // `to_value` serializes bytes as a sequence of `u8`
+        *self.schema = Schema::Seq(Box::new(Schema::number([NumberKind::U8])));
+        visitor.visit_borrowed_bytes(&[])
+    }
+
+    fn deserialize_byte_buf<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let Some(site) = self.tracer.site("?") else {
+            *self.schema = Schema::Option(Box::new(Schema::Any));
+            return visitor.visit_none();
+        };
+
+        let mut inner = Schema::Any;
+        self.tracer.sites.push(site);
+        self.tracer.steps.push(Step::Segment("?".to_string()));
+        let result = visitor.visit_some(Deserializer { tracer: &mut *self.tracer, schema: &mut inner });
+        self.tracer.steps.pop();
+        self.tracer.sites.pop();
+        *self.schema = Schema::Option(Box::new(inner));
+        result
+    }
+
+    fn deserialize_unit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        *self.schema = Schema::Unit;
+        visitor.visit_unit()
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        self.tracer.record(Schema::Struct(StructSchema { name, fields: Fields::Unit }));
+        *self.schema = Schema::Named(name);
+        visitor.visit_unit()
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, name: &'static str, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut inner = Schema::Any;
+        self.tracer.enter(name, None)?;
+        let result = visitor.visit_newtype_struct(Deserializer { tracer: &mut *self.tracer, schema: &mut inner });
+        self.tracer.exit();
+        self.tracer.record(Schema::Struct(StructSchema { name, fields: Fields::Tuple(vec![inner]) }));
+        *self.schema = Schema::Named(name);
+        result
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let Some(site) = self.tracer.site("[]") else {
+            *self.schema = Schema::Seq(Box::new(Schema::Any));
+            return visitor.visit_seq(Elements::new(self.tracer, &mut [], None));
+        };
+
+        let mut inner = [Schema::Any];
+        self.tracer.sites.push(site);
+        let result = visitor.visit_seq(Elements::new(&mut *self.tracer, &mut inner, None));
+        self.tracer.sites.pop();
+        let [inner] = inner;
+        *self.schema = Schema::Seq(Box::new(inner));
+        result
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut elements = vec![Schema::Any; len];
+        let result = visitor.visit_seq(Elements::new(&mut *self.tracer, &mut elements, None));
+        *self.schema = Schema::Tuple(elements);
+        result
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, name: &'static str, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut elements = vec![Schema::Any; len];
+        self.tracer.enter(name, None)?;
+        let result = visitor.visit_seq(Elements::new(&mut *self.tracer, &mut elements, None));
+        self.tracer.exit();
+        self.tracer.record(Schema::Struct(StructSchema { name, fields: Fields::Tuple(elements) }));
+        *self.schema = Schema::Named(name);
+        result
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let Some(site) = self.tracer.site("{}") else {
+            *self.schema = Schema::Map { key: Box::new(Schema::Any), value: Box::new(Schema::Any) };
+            return visitor.visit_map(Entries { tracer: self.tracer, entry: None });
+        };
+
+        let mut entry = (Schema::Any, Schema::Any);
+        self.tracer.sites.push(site);
+        let result = visitor.visit_map(Entries { tracer: &mut *self.tracer, entry: Some((&mut entry.0, &mut entry.1)) });
+        self.tracer.sites.pop();
+        *self.schema = Schema::Map { key: Box::new(entry.0), value: Box::new(entry.1) };
+        result
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        let mut values = vec![Schema::Any; fields.len()];
+        self.tracer.enter(name, None)?;
+        let result = visitor.visit_seq(Elements::new(&mut *self.tracer, &mut values, Some(fields)));
+        self.tracer.exit();
+        let fields = named_fields(fields, values);
+        self.tracer.record(Schema::Struct(StructSchema { name, fields }));
+        *self.schema = Schema::Named(name);
+        result
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, name: &'static str, variants: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        self.tracer.enums.insert(name, variants);
+        if self.tracer.registry.get(name).is_none() {
+            self.tracer.record(Schema::Enum(EnumSchema { name, variants: BTreeMap::new() }));
+        }
+        let index = self.tracer.choose_variant(name, variants)
+            .ok_or_else(|| de::Error::custom(format_args!("every variant of {name} is recursive")))?;
+
+        let mut fields = Fields::Unit;
+        self.tracer.enter(name, Some(variants[index]))?;
+        let result = visitor.visit_enum(Variant { tracer: &mut *self.tracer, index, fields: &mut fields });
+        self.tracer.exit();
+        if let Some(Schema::Enum(val)) = self.tracer.registry.types.get_mut(name) {
+            val.variants.insert(variants[index], fields);
+        }
+        *self.schema = Schema::Named(name);
+        result
+    }
+
+    fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_unit()
+    }
+}
+
+fn named_fields(names: &'static [&'static str], schemas: Vec<Schema>) -> Fields {
+    Fields::Named(names.iter()
+        .zip(schemas)
+        .map(|(name, schema)| (*name, Field::required(schema)))
+        .collect())
+}
+
+/// Traces the elements of a sequence, tuple or struct, one per schema
+struct Elements<'a, 't> {
+    tracer: &'t mut Tracer,
+    schemas: std::slice::IterMut<'a, Schema>,
+    names: Option<&'static [&'static str]>,
+    index: usize,
+}
+
+impl<'a, 't> Elements<'a, 't> {
+    fn new(tracer: &'t mut Tracer, schemas: &'a mut [Schema], names: Option<&'static [&'static str]>) -> Self {
+        Self { tracer, schemas: schemas.iter_mut(), names, index: 0 }
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for Elements<'_, '_> {
+    type Error = TraceError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error> {
+        let Some(schema) = self.schemas.next() else {
+            return Ok(None);
+        };
+        let segment = match self.names {
+            Some(names) => names[self.index].to_string(),
+            None => self.index.to_string(),
+        };
+        self.index += 1;
+
+        self.tracer.steps.push(Step::Segment(segment));
+        let result = seed.deserialize(Deserializer { tracer: &mut *self.tracer, schema });
+        self.tracer.steps.pop();
+        result.map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.schemas.len())
+    }
+}
+
+/// Traces the single entry of a map, if any
+struct Entries<'a, 't> {
+    tracer: &'t mut Tracer,
+    entry: Option<(&'a mut Schema, &'a mut Schema)>,
+}
+
+impl<'de> de::MapAccess<'de> for Entries<'_, '_> {
+    type Error = TraceError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error> {
+        let Some((key, _)) = &mut self.entry else {
+            return Ok(None);
+        };
+        self.tracer.steps.push(Step::Segment("{key}".to_string()));
+        let result = seed.deserialize(Deserializer { tracer: &mut *self.tracer, schema: key });
+        self.tracer.steps.pop();
+        result.map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, Self::Error> {
+        let (_, value) = self.entry.take().ok_or_else(|| de::Error::custom("value requested before its key"))?;
+        self.tracer.steps.push(Step::Segment("{value}".to_string()));
+        let result = seed.deserialize(Deserializer { tracer: &mut *self.tracer, schema: value });
+        self.tracer.steps.pop();
+        result
+    }
+}
+
+/// Traces the chosen variant of an enum into `fields`
+struct Variant<'a, 't> {
+    tracer: &'t mut Tracer,
+    index: usize,
+    fields: &'a mut Fields,
+}
+
+impl<'de, 'a, 't> de::EnumAccess<'de> for Variant<'a, 't> {
+    type Error = TraceError;
+    type Variant = Self;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<(T::Value, Self::Variant), Self::Error> {
+        let index: de::value::U32Deserializer<TraceError> = (self.index as u32).into_deserializer();
+        Ok((seed.deserialize(index)?, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Variant<'_, '_> {
+    type Error = TraceError;
+
+    fn unit_variant(self) -> Result<(), Self::Error> {
+        *self.fields = Fields::Unit;
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, Self::Error> {
+        let mut inner = [Schema::Any];
+        self.tracer.steps.push(Step::Segment("0".to_string()));
+        let result = seed.deserialize(Deserializer { tracer: &mut *self.tracer, schema: &mut inner[0] });
+        self.tracer.steps.pop();
+        *self.fields = Fields::Tuple(inner.into());
+        result
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Self::Error> {
+        let mut elements = vec![Schema::Any; len];
+        let result = visitor.visit_seq(Elements::new(&mut *self.tracer, &mut elements, None));
+        *self.fields = Fields::Tuple(elements);
+        result
+    }
+
+    fn struct_variant<V: Visitor<'de>>(self, fields: &'static [&'static str], visitor: V) -> Result<V::Value, Self::Error> {
+        let mut values = vec![Schema::Any; fields.len()];
+        let result = visitor.visit_seq(Elements::new(&mut *self.tracer, &mut values, Some(fields)));
+        *self.fields = named_fields(fields, values);
+        result
+    }
+}
+
+#[test]
+fn test_trace() {
+    use std::collections::HashMap;
+    use serde_derive::{Deserialize, Serialize};
+    use crate::to_value;
+    use crate::value::Value;
+
+    #[derive(Serialize, Deserialize)]
+    struct Config {
+        name: String,
+        id: Id,
+        origin: Option<(i8, char)>,
+        list: List,
+        shapes: Vec<Shape>,
+        env: HashMap<String, bool>,
+        marker: Marker,
+        raw: Vec<u8>,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    struct Id(u64);
+
+    #[derive(Serialize, Deserialize)]
+    struct Marker;
+
+    #[derive(Serialize, Deserialize)]
+    enum List {
+        Cons(u8, Box<List>),
+        Nil,
+    }
+
+    #[derive(Serialize, Deserialize)]
+    enum Shape {
+        Circle { radius: f32 },
+        Rect(u16, u16),
+        Point,
+        Group(Vec<Shape>),
+    }
+
+    let mut tracer = Tracer::new();
+    let schema = tracer.trace::<Config>().unwrap();
+    assert_eq!(schema, Schema::Named("Config"));
+    let registry = tracer.into_registry();
+    let types: Vec<_> = registry.types.values().map(|schema| schema.to_string()).collect();
+    assert_eq!(types, [
+        "Config { env: {String: bool}, id: Id, list: List, marker: Marker, name: String, \
+         origin: Option<(i8, char)>, raw: [u8], shapes: [Shape] }",
+        "Id(u64)",
+        "List::{Cons(u8, List), Nil}",
+        "Marker",
+        "Shape::{Circle { radius: f32 }, Group([Shape]), Point, Rect(u16, u16)}",
+    ]);
+
+    let config = Config {
+        name: "a".to_string(),
+        id: Id(3),
+        origin: None,
+        list: List::Cons(1, Box::new(List::Cons(2, Box::new(List::Nil)))),
+        shapes: vec![Shape::Group(vec![Shape::Point, Shape::Circle { radius: 1.0 }]), Shape::Rect(1, 2)],
+        env: HashMap::from([("debug".to_string(), true)]),
+        marker: Marker,
+        raw: vec![1, 2],
+    };
+    assert_eq!(registry.validate(&schema, &to_value(&config).unwrap()), Ok(()));
+    assert_eq!(
+        schema.validate(&Value::Unit).unwrap_err()[0].kind,
+        super::ValidationErrorKind::Unresolved("Config"),
+    );
+
+    struct SelfDescribing;
+
+    impl<'de> Deserialize<'de> for SelfDescribing {
+        fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            de::Deserializer::deserialize_any(deserializer, de::IgnoredAny).map(|_| SelfDescribing)
+        }
+    }
+
+    let err = Tracer::new().trace::<SelfDescribing>().unwrap_err();
+    assert_eq!(err.to_string(), "types calling deserialize_any cannot be traced");
+}
@@ -0,0 +1,457 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use crate::value::{Number, NumberKind, Path, Segment, Value};
+use crate::value::path::key_segment;
+use super::{Fields, NumberSchema, Registry, Schema, StringSchema};
+
+/// A part of a [`Value`] that does not match a [`Schema`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ValidationError {
+    /// The path of the offending value, or of the missing field
+    pub path: Path,
+    pub kind: ValidationErrorKind,
+}
+
+/// The reason a value does not match a [`Schema`]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ValidationErrorKind {
+    /// The value has a different type, struct name or number of elements
+    Mismatch {
+        expected: String,
+        found: String,
+    },
+    /// The number cannot be represented by any of the accepted kinds
+    Width {
+        kinds: BTreeSet<NumberKind>,
+        found: Number,
+    },
+    /// The number is outside of the accepted range
+    OutOfRange {
+        found: Number,
+        min: Option<Number>,
+        max: Option<Number>,
+    },
+    /// The string length, in characters, is outside of the accepted range
+    Length {
+        len: usize,
+        min: Option<usize>,
+        max: Option<usize>,
+    },
+    /// The string does not match the pattern
+    Pattern(String),
+    /// A required field is missing
+    MissingField,
+    /// The field is not part of the schema
+    UnknownField,
+    /// The enum variant is not part of the schema
+    UnknownVariant(&'static str),
+    /// The value matches none of the alternatives of a [`Schema::OneOf`]
+    NoAlternative,
+    /// A [`Schema::Named`] type is not in the [`Registry`]
+    Unresolved(&'static str),
+}
+
+impl std::error::Error for ValidationError {}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let path = self.path.to_string();
+        match &self.kind {
+            ValidationErrorKind::Mismatch { expected, found } => {
+                write!(f, "expected {expected} at {path:?}, found {found}")
+            }
+            ValidationErrorKind::Width { kinds, found } => {
+                let kinds: Vec<_> = kinds.iter().map(|kind| kind.name()).collect();
+                write!(f, "{found:?} at {path:?} does not fit in {}", kinds.join("|"))
+            }
+            ValidationErrorKind::OutOfRange { found, min, max } => {
+                write!(f, "{found:?} at {path:?} is out of range {}..{}", bound(min, ""), bound(max, "="))
+            }
+            ValidationErrorKind::Length { len, min, max } => {
+                write!(f, "length {len} at {path:?} is out of range {}..{}", bound(min, ""), bound(max, "="))
+            }
+            ValidationErrorKind::Pattern(pattern) => write!(f, "string at {path:?} does not match {pattern:?}"),
+            ValidationErrorKind::MissingField => write!(f, "missing field {path:?}"),
+            ValidationErrorKind::UnknownField => write!(f, "unknown field {path:?}"),
+            ValidationErrorKind::UnknownVariant(variant) => write!(f, "unknown variant {variant:?} at {path:?}"),
+            ValidationErrorKind::NoAlternative => write!(f, "value at {path:?} matches no alternative"),
+            ValidationErrorKind::Unresolved(name) => write!(f, "unresolved type {name:?} at {path:?}"),
+        }
+    }
+}
+
+fn bound<T: Display>(bound: &Option<T>, prefix: &str) -> String {
+    match bound {
+        Some(bound) => format!("{prefix}{bound}"),
+        None => String::new(),
+    }
+}
+
+impl Schema {
+    /// Checks that `value` matches this schema.
+    ///
+    /// Every mismatch is reported with the path of the offending value. Structs and enums must
+    /// have the same name and kind of fields as in the schema, fields not in the schema are
+    /// rejected, and a number is accepted if one of the kinds can represent its value, see
+    /// [`NumberKind::fits`].
+    ///
+    /// ```
+    /// use serde_value::{NumberKind, Value};
+    /// use serde_value::schema::{Schema, NumberSchema};
+    ///
+    /// let schema = Schema::Seq(Box::new(Schema::Number(NumberSchema::new([NumberKind::U8]).range(1u8, 10u8))));
+    /// let values = Value::from(vec![Value::from(3u8), Value::from(30u8), Value::from(300u16)]);
+    /// let errors = schema.validate(&values).unwrap_err();
+    /// let errors: Vec<_> = errors.iter().map(|error| error.to_string()).collect();
+    /// assert_eq!(errors, [
+    ///     "30u8 at \"/1\" is out of range 1..=10",
+    ///     "300u16 at \"/2\" does not fit in u8",
+    /// ]);
+    /// ```
+    pub fn validate(&self, value: &Value) -> Result<(), Vec<ValidationError>> {
+        Validator::run(self, value, None)
+    }
+}
+
+impl Registry {
+    /// Checks that `value` matches `schema`, following [`Schema::Named`] references into this
+    /// registry, see [`Schema::validate`]
+    pub fn validate(&self, schema: &Schema, value: &Value) -> Result<(), Vec<ValidationError>> {
+        Validator::run(schema, value, Some(self))
+    }
+}
+
+/// The fields of a struct or variant value
+enum ValueFields<'v> {
+    Unit,
+    Tuple(&'v [Value]),
+    Named(&'v BTreeMap<&'static str, Value>),
+}
+
+struct Validator<'r> {
+    path: Path,
+    errors: Vec<ValidationError>,
+    registry: Option<&'r Registry>,
+}
+
+impl<'r> Validator<'r> {
+    fn run(schema: &Schema, value: &Value, registry: Option<&'r Registry>) -> Result<(), Vec<ValidationError>> {
+        let mut validator = Validator { path: Path::root(), errors: Vec::new(), registry };
+        validator.check(schema, value);
+        match validator.errors.is_empty() {
+            true => Ok(()),
+            false => Err(validator.errors),
+        }
+    }
+
+    fn error(&mut self, kind: ValidationErrorKind) {
+        self.errors.push(ValidationError { path: self.path.clone(), kind });
+    }
+
+    fn child(&mut self, segment: Segment, schema: &Schema, value: &Value) {
+        self.path.push(segment);
+        self.check(schema, value);
+        self.path.pop();
+    }
+
+    fn mismatch(&mut self, expected: String, value: &Value) {
+        self.error(ValidationErrorKind::Mismatch { expected, found: describe(value) });
+    }
+
+    fn check(&mut self, schema: &Schema, value: &Value) {
+        match (schema, value) {
+            (Schema::Any | Schema::Unknown, _)
+            | (Schema::Unit, Value::Unit)
+            | (Schema::Bool, Value::Bool(_))
+            | (Schema::Char, Value::Char(_)) => {}
+            (Schema::Number(schema), Value::Number(number)) => self.check_number(schema, number),
+            (Schema::String(schema), Value::String(string)) => self.check_string(schema, string),
+            (Schema::Option(_), Value::UnitStruct(val)) if val.name == "None" => {}
+            (Schema::Option(inner), Value::TupleStruct(val)) if val.name == "Some" && val.values.len() == 1 => {
+                self.child(Segment::Index(0), inner, &val.values[0]);
+            }
+            (Schema::Seq(inner), Value::Seq(values)) => {
+                for (index, value) in values.iter().enumerate() {
+                    self.child(Segment::Index(index), inner, value);
+                }
+            }
+            (Schema::Map { key, value: inner }, Value::Map(map)) => {
+                for (k, v) in map {
+                    self.child(key_segment(k), key, k);
+                    self.child(key_segment(k), inner, v);
+                }
+            }
+            (Schema::Tuple(schemas), Value::Tuple(values)) if schemas.len() == values.len() => {
+                for (index, (schema, value)) in schemas.iter().zip(values).enumerate() {
+                    self.child(Segment::Index(index), schema, value);
+                }
+            }
+            (Schema::Struct(val), _) => match value_fields(value) {
+                Some((name, None, fields)) if name == val.name => self.check_fields(&val.fields, fields, value),
+                _ => self.mismatch(brief(schema), value),
+            },
+            (Schema::Enum(val), _) => match value_fields(value) {
+                Some((name, Some(variant), fields)) if name == val.name => match val.variants.get(variant) {
+                    Some(expected) => self.check_fields(expected, fields, value),
+                    None => self.error(ValidationErrorKind::UnknownVariant(variant)),
+                },
+                _ => self.mismatch(brief(schema), value),
+            },
+            (Schema::Named(name), _) => match self.registry.and_then(|registry| registry.get(name)) {
+                Some(schema) => self.check(schema, value),
+                None => self.error(ValidationErrorKind::Unresolved(name)),
+            },
+            (Schema::OneOf(alternatives), _) => {
+                let matched = alternatives.iter()
+                    .any(|alternative| Validator::run(alternative, value, self.registry).is_ok());
+                if !matched {
+                    self.error(ValidationErrorKind::NoAlternative);
+                }
+            }
+            (schema, value) => self.mismatch(brief(schema), value),
+        }
+    }
+
+    fn check_number(&mut self, schema: &NumberSchema, number: &Number) {
+        if !schema.kinds.is_empty() && !schema.kinds.iter().any(|kind| kind.fits(number)) {
+            return self.error(ValidationErrorKind::Width { kinds: schema.kinds.clone(), found: *number });
+        }
+        // NaN is outside of any range
+        let below = schema.min.is_some_and(|min| !matches!(number.numeric_cmp(&min), Some(Ordering::Greater | Ordering::Equal)));
+        let above = schema.max.is_some_and(|max| !matches!(number.numeric_cmp(&max), Some(Ordering::Less | Ordering::Equal)));
+        if below || above {
+            self.error(ValidationErrorKind::OutOfRange { found: *number, min: schema.min, max: schema.max });
+        }
+    }
+
+    fn check_string(&mut self, schema: &StringSchema, string: &str) {
+        let len = string.chars().count();
+        if schema.min_len.is_some_and(|min| len < min) || schema.max_len.is_some_and(|max| len > max) {
+            self.error(ValidationErrorKind::Length { len, min: schema.min_len, max: schema.max_len });
+        }
+        if let Some(pattern) = &schema.pattern {
+            if !matches_pattern(pattern, string) {
+                self.error(ValidationErrorKind::Pattern(pattern.clone()));
+            }
+        }
+    }
+
+    fn check_fields(&mut self, schema: &Fields, fields: ValueFields, value: &Value) {
+        match (schema, fields) {
+            (Fields::Unit, ValueFields::Unit) => {}
+            (Fields::Tuple(schemas), ValueFields::Tuple(values)) if schemas.len() == values.len() => {
+                for (index, (schema, value)) in schemas.iter().zip(values).enumerate() {
+                    self.child(Segment::Index(index), schema, value);
+                }
+            }
+            (Fields::Named(schemas), ValueFields::Named(values)) => {
+                for (name, field) in schemas {
+                    match values.get(name) {
+                        Some(value) => self.child(Segment::from(*name), &field.schema, value),
+                        None if field.required => {
+                            self.path.push(Segment::from(*name));
+                            self.error(ValidationErrorKind::MissingField);
+                            self.path.pop();
+                        }
+                        None => {}
+                    }
+                }
+                for name in values.keys().filter(|name| !schemas.contains_key(*name)) {
+                    self.path.push(Segment::from(*name));
+                    self.error(ValidationErrorKind::UnknownField);
+                    self.path.pop();
+                }
+            }
+            (schema, _) => {
+                let expected = match schema {
+                    Fields::Unit => "no fields".to_string(),
+                    Fields::Tuple(schemas) => format!("{} unnamed fields", schemas.len()),
+                    Fields::Named(_) => "named fields".to_string(),
+                };
+                self.mismatch(expected, value);
+            }
+        }
+    }
+}
+
+/// The type name, variant name and fields of a struct or variant
+fn value_fields(value: &Value) -> Option<(&'static str, Option<&'static str>, ValueFields<'_>)> {
+    Some(match value {
+        Value::UnitStruct(val) => (val.name, None, ValueFields::Unit),
+        Value::TupleStruct(val) => (val.name, None, ValueFields::Tuple(&val.values)),
+        Value::NamedStruct(val) => (val.name, None, ValueFields::Named(&val.fields)),
+        Value::UnitVariant(val) => (val.name, Some(val.variant), ValueFields::Unit),
+        Value::TupleVariant(val) => (val.name, Some(val.variant), ValueFields::Tuple(&val.values)),
+        Value::NamedVariant(val) => (val.name, Some(val.variant), ValueFields::Named(&val.fields)),
+        _ => return None,
+    })
+}
+
+/// A short description of a schema, for mismatches
+pub(super) fn brief(schema: &Schema) -> String {
+    match schema {
+        Schema::Option(_) => "Option".to_string(),
+        Schema::Seq(_) => "sequence".to_string(),
+        Schema::Map { .. } => "map".to_string(),
+        Schema::Tuple(schemas) => format!("tuple of {}", schemas.len()),
+        Schema::Struct(val) => format!("struct {}", val.name),
+        Schema::Enum(val) => format!("enum {}", val.name),
+        schema => schema.to_string(),
+    }
+}
+
+/// A short description of a value, for mismatches
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Unit => "()".to_string(),
+        Value::Bool(_) => "bool".to_string(),
+        Value::Char(_) => "char".to_string(),
+        Value::Number(number) => number.kind().to_string(),
+        Value::String(_) => "String".to_string(),
+        Value::Seq(_) => "sequence".to_string(),
+        Value::Map(_) => "map".to_string(),
+        Value::Tuple(values) => format!("tuple of {}", values.len()),
+        Value::UnitStruct(val) => format!("unit struct {}", val.name),
+        Value::TupleStruct(val) => format!("tuple struct {}", val.name),
+        Value::NamedStruct(val) => format!("struct {}", val.name),
+        Value::UnitVariant(val) => format!("unit variant {}::{}", val.name, val.variant),
+        Value::TupleVariant(val) => format!("tuple variant {}::{}", val.name, val.variant),
+        Value::NamedVariant(val) => format!("struct variant {}::{}", val.name, val.variant),
+    }
+}
+
+/// A part of a [`StringSchema`] pattern
+pub(super) enum Token {
+    Char(char),
+    Any,
+    Star,
+}
+
+pub(super) fn pattern_tokens(pattern: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '*' => Token::Star,
+            '?' => Token::Any,
+            '\\' => Token::Char(chars.next().unwrap_or('\\')),
+            c => Token::Char(c),
+        });
+    }
+    tokens
+}
+
+/// Matches a whole string against a pattern of `*`, `?` and `\`-escaped characters
+fn matches_pattern(pattern: &str, string: &str) -> bool {
+    let tokens = pattern_tokens(pattern);
+    let string: Vec<char> = string.chars().collect();
+    let (mut t, mut s) = (0, 0);
+    let mut backtrack = None;
+    while s < string.len() {
+        match tokens.get(t) {
+            Some(Token::Star) => {
+                backtrack = Some((t, s));
+                t += 1;
+            }
+            Some(Token::Any) => (t, s) = (t + 1, s + 1),
+            Some(Token::Char(c)) if *c == string[s] => (t, s) = (t + 1, s + 1),
+            _ => match backtrack {
+                Some((star, from)) => {
+                    backtrack = Some((star, from + 1));
+                    (t, s) = (star + 1, from + 1);
+                }
+                None => return false,
+            },
+        }
+    }
+    tokens[t..].iter().all(|token| matches!(token, Token::Star))
+}
+
+#[test]
+fn test_validate() {
+    use serde_derive::Serialize;
+    use crate::to_value;
+    use super::{EnumSchema, Field, StructSchema};
+
+    #[derive(Serialize)]
+    struct Plugin {
+        name: String,
+        version: (u8, u8),
+        port: u32,
+        mode: Mode,
+        tags: Vec<String>,
+        extra: Option<bool>,
+    }
+
+    #[derive(Serialize)]
+    enum Mode {
+        Fast,
+        Slow { delay: u16 },
+    }
+
+    let mode = Schema::Enum(EnumSchema {
+        name: "Mode",
+        variants: [
+            ("Fast", Fields::Unit),
+            ("Slow", Fields::Named([("delay", Field::required(Schema::number([NumberKind::U16])))].into())),
+        ].into(),
+    });
+    let schema = Schema::Struct(StructSchema {
+        name: "Plugin",
+        fields: Fields::Named([
+            ("name", Field::required(Schema::String(StringSchema::default().length(1, 16).pattern("plugin-*")))),
+            ("version", Field::required(Schema::Tuple(vec![Schema::number([NumberKind::U8]); 2]))),
+            ("port", Field::required(Schema::Number(NumberSchema::new([NumberKind::U16]).range(1024u16, 65535u16)))),
+            ("mode", Field::required(mode)),
+            ("tags", Field::optional(Schema::Seq(Box::new(Schema::string())))),
+            ("id", Field::required(Schema::number([]))),
+        ].into()),
+    });
+
+    let valid = Plugin {
+        name: "plugin-a".to_string(),
+        version: (1, 2),
+        port: 8080,
+        mode: Mode::Slow { delay: 5 },
+        tags: vec!["x".to_string()],
+        extra: None,
+    };
+    let mut value = to_value(&valid).unwrap();
+    value.remove_at(&Path::parse("/extra").unwrap()).unwrap();
+    value.insert_at(&Path::from(vec![Segment::from("id")]), Value::from(-1i64)).unwrap();
+    assert_eq!(schema.validate(&value), Ok(()));
+
+    let invalid = Plugin {
+        name: "tool-with-a-long-name".to_string(),
+        version: (1, 2),
+        port: 80,
+        mode: Mode::Fast,
+        tags: vec![],
+        extra: Some(true),
+    };
+    let errors: Vec<_> = schema.validate(&to_value(&invalid).unwrap()).unwrap_err()
+        .into_iter()
+        .map(|error| error.to_string())
+        .collect();
+    assert_eq!(errors, [
+        "missing field \"/id\"",
+        "length 21 at \"/name\" is out of range 1..=16",
+        "string at \"/name\" does not match \"plugin-*\"",
+        "80u32 at \"/port\" is out of range 1024..=65535",
+        "unknown field \"/extra\"",
+    ]);
+
+    let bad_mode = to_value(&Mode::Slow { delay: 3 }).unwrap();
+    let wrong: Vec<_> = Schema::Seq(Box::new(Schema::Bool)).validate(&bad_mode).unwrap_err();
+    assert_eq!(wrong[0].to_string(), "expected sequence at \"\", found struct variant Mode::Slow");
+    let either = Schema::OneOf(vec![Schema::Bool, Schema::string()]);
+    assert_eq!(either.validate(&Value::from("a")), Ok(()));
+    assert_eq!(either.validate(&Value::Unit).unwrap_err()[0].kind, ValidationErrorKind::NoAlternative);
+
+    assert!(matches_pattern("a*b?c", "axxbyc"));
+    assert!(matches_pattern("*", ""));
+    assert!(matches_pattern("\\*x*", "*xyz"));
+    assert!(!matches_pattern("a*b", "acbd"));
+    assert!(!matches_pattern("\\*", "a"));
+}
@@ -0,0 +1,275 @@
+//! Streaming output of [`Value`] trees
+//!
+//! A [`StreamWriter`] writes a value in one of the crate's output [`Format`]s to any
+//! `io::Write` or `fmt::Write` as it walks the tree, rather than rendering it to a `String`
+//! first. Writes go straight to the underlying writer, which should be buffered when writes are
+//! costly, e.g. with a `BufWriter`.
+//!
+//! With a byte [budget](StreamWriter::budget), writing stops as soon as the output would
+//! exceed it, after writing the bytes that fit, which bounds the output of values dumped into
+//! log lines:
+//!
+//! ```
+//! use serde_value::Value;
+//! use serde_value::json::JsonOptions;
+//! use serde_value::stream::{Format, StreamError, StreamWriter};
+//!
+//! let value = Value::from(vec!["alpha", "beta", "gamma"]);
+//! let writer = StreamWriter::new(Format::Json(JsonOptions::default())).budget(16);
+//!
+//! let mut line = String::new();
+//! let err = writer.write_fmt(&value, &mut line).unwrap_err();
+//! assert!(matches!(err, StreamError::Budget { written: 16 }));
+//! assert_eq!(line, r#"["alpha","beta","#);
+//! ```
+
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use crate::binary::{write_binary, BinaryOptions};
+use crate::json::{write_json, JsonOptions};
+use crate::value::Value;
+
+/// An output format of [`StreamWriter`]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub enum Format {
+    /// The `Debug` notation of values
+    #[default]
+    Notation,
+    /// The alternate, multiline `Debug` notation of values
+    PrettyNotation,
+    /// JSON text, see [`Value::to_json_string`]
+    Json(JsonOptions),
+    /// The binary encoding, see [`Value::to_binary_with`], which can only be written to an
+    /// `io::Write`. With [`BinaryOptions::shared_subtrees`], the value is deduplicated into a
+    /// second tree before anything is written, so the output is not incremental
+    Binary(BinaryOptions),
+}
+
+/// An error produced while streaming a value
+#[derive(Debug)]
+pub enum StreamError {
+    /// The output would have exceeded the budget, after `written` bytes
+    Budget {
+        written: usize,
+    },
+    /// The underlying `io::Write` failed
+    Io(io::Error),
+    /// The underlying `fmt::Write` failed
+    Fmt,
+    /// A binary format was written to a `fmt::Write`
+    NotText,
+}
+
+impl std::error::Error for StreamError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StreamError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Display for StreamError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            StreamError::Budget { written } => write!(f, "output budget exceeded after {written} bytes"),
+            StreamError::Io(err) => write!(f, "{err}"),
+            StreamError::Fmt => write!(f, "formatter error"),
+            StreamError::NotText => write!(f, "binary output cannot be written as text"),
+        }
+    }
+}
+
+/// Writes values incrementally in a [`Format`], within an optional byte budget
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StreamWriter {
+    format: Format,
+    budget: Option<usize>,
+}
+
+impl StreamWriter {
+    /// A writer of `format`, without budget
+    pub fn new(format: Format) -> Self {
+        StreamWriter { format, budget: None }
+    }
+
+    /// Stops writing with [`StreamError::Budget`] when more than `bytes` bytes would be
+    /// written. Text is cut at a character boundary.
+    pub fn budget(mut self, bytes: usize) -> Self {
+        self.budget = Some(bytes);
+        self
+    }
+
+    /// Writes `value` to `w`, returning the number of bytes written
+    pub fn write_io<W: io::Write>(&self, value: &Value, w: W) -> Result<usize, StreamError> {
+        let mut sink = IoSink { inner: w, budget: Budget::new(self.budget), error: None };
+        let result = match &self.format {
+            Format::Binary(options) => write_binary(&mut sink, value, options).map_err(Some),
+            format => write_text(&mut sink, value, format).map_err(|_| sink.error.take()),
+        };
+        match result {
+            Ok(()) => Ok(sink.budget.written),
+            Err(_) if sink.budget.exceeded => Err(StreamError::Budget { written: sink.budget.written }),
+            Err(Some(err)) => Err(StreamError::Io(err)),
+            Err(None) => Err(StreamError::Fmt),
+        }
+    }
+
+    /// Writes `value` to `w`, returning the number of bytes written
+    pub fn write_fmt<W: fmt::Write>(&self, value: &Value, w: W) -> Result<usize, StreamError> {
+        if let Format::Binary(_) = self.format {
+            return Err(StreamError::NotText);
+        }
+        let mut sink = FmtSink { inner: w, budget: Budget::new(self.budget) };
+        match write_text(&mut sink, value, &self.format) {
+            Ok(()) => Ok(sink.budget.written),
+            Err(_) if sink.budget.exceeded => Err(StreamError::Budget { written: sink.budget.written }),
+            Err(_) => Err(StreamError::Fmt),
+        }
+    }
+}
+
+fn write_text<W: fmt::Write>(w: &mut W, value: &Value, format: &Format) -> fmt::Result {
+    match format {
+        Format::Notation => write!(w, "{value:?}"),
+        Format::PrettyNotation => write!(w, "{value:#?}"),
+        Format::Json(options) => write_json(w, value, options),
+        Format::Binary(_) => Err(fmt::Error),
+    }
+}
+
+/// The bytes written so far against an optional limit
+struct Budget {
+    limit: Option<usize>,
+    written: usize,
+    exceeded: bool,
+}
+
+impl Budget {
+    fn new(limit: Option<usize>) -> Self {
+        Budget { limit, written: 0, exceeded: false }
+    }
+
+    /// How many of `len` bytes fit, flagging the budget as exceeded when not all of them do
+    fn allow(&mut self, len: usize) -> usize {
+        match self.limit {
+            Some(limit) if self.written + len > limit => {
+                self.exceeded = true;
+                limit - self.written
+            }
+            _ => len,
+        }
+    }
+
+    /// How many bytes of `s` fit, cut at a character boundary
+    fn allow_str(&mut self, s: &str) -> usize {
+        let mut allowed = self.allow(s.len());
+        while !s.is_char_boundary(allowed) {
+            allowed -= 1;
+        }
+        allowed
+    }
+}
+
+struct IoSink<W> {
+    inner: W,
+    budget: Budget,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> io::Write for IoSink<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let allowed = self.budget.allow(buf.len());
+        self.inner.write_all(&buf[..allowed])?;
+        self.budget.written += allowed;
+        if self.budget.exceeded {
+            return Err(io::Error::other("output budget exceeded"));
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: io::Write> fmt::Write for IoSink<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let allowed = self.budget.allow_str(s);
+        if let Err(err) = self.inner.write_all(&s.as_bytes()[..allowed]) {
+            self.error = Some(err);
+            return Err(fmt::Error);
+        }
+        self.budget.written += allowed;
+        if self.budget.exceeded {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+struct FmtSink<W> {
+    inner: W,
+    budget: Budget,
+}
+
+impl<W: fmt::Write> fmt::Write for FmtSink<W> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let allowed = self.budget.allow_str(s);
+        self.inner.write_str(&s[..allowed])?;
+        self.budget.written += allowed;
+        if self.budget.exceeded {
+            return Err(fmt::Error);
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn test_stream() {
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize)]
+    struct Record {
+        name: String,
+        tags: Vec<(u8, char)>,
+    }
+
+    let value = to_value(&Record { name: "héllo".to_string(), tags: vec![(1, 'a'), (2, 'b')] }).unwrap();
+    let json = JsonOptions::default();
+
+    let mut bytes = Vec::new();
+    let written = StreamWriter::new(Format::Json(json.clone())).write_io(&value, &mut bytes).unwrap();
+    assert_eq!(bytes, value.to_json_string(&json).into_bytes());
+    assert_eq!(written, bytes.len());
+
+    let mut bytes = Vec::new();
+    StreamWriter::new(Format::Binary(BinaryOptions::default())).write_io(&value, &mut bytes).unwrap();
+    assert_eq!(bytes, value.to_binary());
+
+    let mut bytes = Vec::new();
+    let table = BinaryOptions { name_table: true, shared_subtrees: true };
+    StreamWriter::new(Format::Binary(table.clone())).write_io(&value, &mut bytes).unwrap();
+    assert_eq!(bytes, value.to_binary_with(&table));
+
+    let mut text = String::new();
+    StreamWriter::new(Format::PrettyNotation).write_fmt(&value, &mut text).unwrap();
+    assert_eq!(text, format!("{value:#?}"));
+
+    let mut bytes = Vec::new();
+    let err = StreamWriter::new(Format::Notation).budget(40).write_io(&value, &mut bytes).unwrap_err();
+    assert!(matches!(err, StreamError::Budget { written: 40 }));
+    assert_eq!(bytes, format!("{value:?}").as_bytes()[..40]);
+
+    let mut text = String::new();
+    let err = StreamWriter::new(Format::Json(json)).budget(11).write_fmt(&value, &mut text).unwrap_err();
+    assert_eq!(err.to_string(), "output budget exceeded after 10 bytes");
+    assert_eq!(text, "{\"name\":\"h");
+
+    let mut bytes = Vec::new();
+    let err = StreamWriter::new(Format::Binary(BinaryOptions::default())).budget(10).write_io(&value, &mut bytes).unwrap_err();
+    assert!(matches!(err, StreamError::Budget { written: 10 }));
+    assert_eq!(bytes, value.to_binary()[..10]);
+    assert!(matches!(StreamWriter::new(Format::Binary(BinaryOptions::default())).write_fmt(&value, String::new()), Err(StreamError::NotText)));
+}
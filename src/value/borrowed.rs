@@ -0,0 +1,290 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Formatter;
+use serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use crate::value::{NamedStruct, NamedVariant, Number, TupleStruct, TupleVariant, UnitStruct, UnitVariant, Value};
+
+/// An intermediate value borrowing its strings and bytes
+///
+/// A `ValueRef<'a>` mirrors [`Value`], except that strings and byte strings are
+/// `Cow`s which borrow from the data they were captured from whenever possible:
+///
+/// - [`ValueRef::from`] views an existing `&'a Value` without copying its strings
+/// - its [`Deserialize`] implementation borrows the strings and bytes a deserializer lends
+///   from its input, through `visit_borrowed_str` and `visit_borrowed_bytes`, and copies the
+///   other ones
+///
+/// Serde's `Serializer` API cannot lend data: `serialize_str` and `serialize_bytes` receive
+/// slices whose lifetime is unrelated to the serialized value, which is why
+/// [`to_value`](crate::to_value) copies every string.
+///
+/// ```
+/// use std::borrow::Cow;
+/// use serde::Deserialize;
+/// use serde::de::value::{BorrowedStrDeserializer, Error};
+/// use serde_value::{Value, ValueRef};
+///
+/// let input = String::from("a long request field");
+/// let value = ValueRef::deserialize(BorrowedStrDeserializer::<Error>::new(&input)).unwrap();
+/// assert!(matches!(value, ValueRef::String(Cow::Borrowed(_))));
+/// assert_eq!(value.to_owned(), Value::from("a long request field"));
+/// ```
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum ValueRef<'a> {
+    /// A unit value: ()
+    Unit,
+    /// A boolean value
+    Bool(bool),
+    /// A character value
+    Char(char),
+    /// A numeric value
+    Number(Number),
+    /// A string value
+    String(Cow<'a, str>),
+    /// A byte string, which becomes a `Seq` of `u8` values in a `Value`
+    Bytes(Cow<'a, [u8]>),
+    /// A collection of values
+    Seq(Vec<ValueRef<'a>>),
+    /// A map of values
+    Map(BTreeMap<ValueRef<'a>, ValueRef<'a>>),
+    /// A tuple of values
+    Tuple(Vec<ValueRef<'a>>),
+    /// A struct with no fields
+    UnitStruct(UnitStruct),
+    /// A struct with unnamed fields
+    TupleStruct(TupleStruct<Vec<ValueRef<'a>>>),
+    /// A struct with named fields
+    NamedStruct(NamedStruct<BTreeMap<&'static str, ValueRef<'a>>>),
+    /// An enum variant with no fields
+    UnitVariant(UnitVariant),
+    /// An enum variant with unnamed fields
+    TupleVariant(TupleVariant<Vec<ValueRef<'a>>>),
+    /// An enum variant with named fields
+    NamedVariant(NamedVariant<BTreeMap<&'static str, ValueRef<'a>>>),
+}
+
+impl ValueRef<'_> {
+    /// Copies this value into an owned [`Value`]
+    pub fn to_owned(&self) -> Value {
+        self.clone().into_owned()
+    }
+
+    /// Converts this value into an owned [`Value`], copying only the borrowed strings and bytes
+    pub fn into_owned(self) -> Value {
+        fn elements(values: Vec<ValueRef<'_>>) -> Vec<Value> {
+            values.into_iter().map(ValueRef::into_owned).collect()
+        }
+        fn fields(fields: BTreeMap<&'static str, ValueRef<'_>>) -> BTreeMap<&'static str, Value> {
+            fields.into_iter().map(|(name, value)| (name, value.into_owned())).collect()
+        }
+
+        match self {
+            ValueRef::Unit => Value::Unit,
+            ValueRef::Bool(val) => Value::Bool(val),
+            ValueRef::Char(val) => Value::Char(val),
+            ValueRef::Number(val) => Value::Number(val),
+            ValueRef::String(val) => Value::String(val.into_owned()),
+            ValueRef::Bytes(val) => Value::Seq(val.iter().map(|byte| Value::from(*byte)).collect()),
+            ValueRef::Seq(values) => Value::Seq(elements(values)),
+            ValueRef::Map(map) => Value::Map(map.into_iter()
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect()),
+            ValueRef::Tuple(values) => Value::Tuple(elements(values)),
+            ValueRef::UnitStruct(val) => Value::UnitStruct(val),
+            ValueRef::TupleStruct(val) => Value::TupleStruct(TupleStruct { name: val.name, values: elements(val.values) }),
+            ValueRef::NamedStruct(val) => Value::NamedStruct(NamedStruct { name: val.name, fields: fields(val.fields) }),
+            ValueRef::UnitVariant(val) => Value::UnitVariant(val),
+            ValueRef::TupleVariant(val) => Value::TupleVariant(TupleVariant {
+                name: val.name,
+                variant: val.variant,
+                values: elements(val.values),
+            }),
+            ValueRef::NamedVariant(val) => Value::NamedVariant(NamedVariant {
+                name: val.name,
+                variant: val.variant,
+                fields: fields(val.fields),
+            }),
+        }
+    }
+}
+
+impl<'a> From<&'a Value> for ValueRef<'a> {
+    fn from(value: &'a Value) -> Self {
+        fn elements(values: &[Value]) -> Vec<ValueRef<'_>> {
+            values.iter().map(ValueRef::from).collect()
+        }
+        fn fields<'a>(fields: &'a BTreeMap<&'static str, Value>) -> BTreeMap<&'static str, ValueRef<'a>> {
+            fields.iter().map(|(name, value)| (*name, ValueRef::from(value))).collect()
+        }
+
+        match value {
+            Value::Unit => ValueRef::Unit,
+            Value::Bool(val) => ValueRef::Bool(*val),
+            Value::Char(val) => ValueRef::Char(*val),
+            Value::Number(val) => ValueRef::Number(*val),
+            Value::String(val) => ValueRef::String(Cow::Borrowed(val)),
+            Value::Seq(values) => ValueRef::Seq(elements(values)),
+            Value::Map(map) => ValueRef::Map(map.iter()
+                .map(|(key, value)| (ValueRef::from(key), ValueRef::from(value)))
+                .collect()),
+            Value::Tuple(values) => ValueRef::Tuple(elements(values)),
+            Value::UnitStruct(val) => ValueRef::UnitStruct(*val),
+            Value::TupleStruct(val) => ValueRef::TupleStruct(TupleStruct { name: val.name, values: elements(&val.values) }),
+            Value::NamedStruct(val) => ValueRef::NamedStruct(NamedStruct { name: val.name, fields: fields(&val.fields) }),
+            Value::UnitVariant(val) => ValueRef::UnitVariant(*val),
+            Value::TupleVariant(val) => ValueRef::TupleVariant(TupleVariant {
+                name: val.name,
+                variant: val.variant,
+                values: elements(&val.values),
+            }),
+            Value::NamedVariant(val) => ValueRef::NamedVariant(NamedVariant {
+                name: val.name,
+                variant: val.variant,
+                fields: fields(&val.fields),
+            }),
+        }
+    }
+}
+
+/// Deserializes self-describing data, mapping options as [`to_value`](crate::to_value) does and
+/// newtype structs to their inner value, as their names are unknown. Enums cannot be captured,
+/// as their names are not `'static`.
+impl<'de> Deserialize<'de> for ValueRef<'de> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueRefVisitor)
+    }
+}
+
+struct ValueRefVisitor;
+
+macro_rules! visit_number {
+    ($($method:ident: $ty:ty),*) => {
+        $(
+            fn $method<E: de::Error>(self, v: $ty) -> Result<Self::Value, E> {
+                Ok(ValueRef::Number(Number::from(v)))
+            }
+        )*
+    };
+}
+
+impl<'de> Visitor<'de> for ValueRefVisitor {
+    type Value = ValueRef<'de>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "any self-describing value")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(ValueRef::Bool(v))
+    }
+
+    visit_number!(
+        visit_i8: i8, visit_i16: i16, visit_i32: i32, visit_i64: i64, visit_i128: i128,
+        visit_u8: u8, visit_u16: u16, visit_u32: u32, visit_u64: u64, visit_u128: u128,
+        visit_f32: f32, visit_f64: f64
+    );
+
+    fn visit_char<E: de::Error>(self, v: char) -> Result<Self::Value, E> {
+        Ok(ValueRef::Char(v))
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        Ok(ValueRef::String(Cow::Owned(v.to_string())))
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+        Ok(ValueRef::String(Cow::Borrowed(v)))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+        Ok(ValueRef::String(Cow::Owned(v)))
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        Ok(ValueRef::Bytes(Cow::Owned(v.to_vec())))
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(ValueRef::Bytes(Cow::Borrowed(v)))
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(ValueRef::Bytes(Cow::Owned(v)))
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(ValueRef::UnitStruct(UnitStruct { name: "None" }))
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        let value = ValueRef::deserialize(deserializer)?;
+        Ok(ValueRef::TupleStruct(TupleStruct { name: "Some", values: vec![value] }))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(ValueRef::Unit)
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        ValueRef::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut values = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+        Ok(ValueRef::Seq(values))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let mut values = BTreeMap::new();
+        while let Some((key, value)) = map.next_entry()? {
+            values.insert(key, value);
+        }
+        Ok(ValueRef::Map(values))
+    }
+}
+
+#[test]
+fn test_borrowed() {
+    use serde::de::IntoDeserializer;
+    use serde::de::value::{BorrowedBytesDeserializer, BorrowedStrDeserializer, Error, MapDeserializer, StrDeserializer};
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize)]
+    struct Request {
+        path: String,
+        query: Option<Vec<(String, u16)>>,
+    }
+
+    let value = to_value(&Request { path: "/a".to_string(), query: Some(vec![("b".to_string(), 1)]) }).unwrap();
+    let view = ValueRef::from(&value);
+    let ValueRef::NamedStruct(NamedStruct { fields, .. }) = &view else {
+        panic!("expected a struct, found {view:?}");
+    };
+    let (ValueRef::String(Cow::Borrowed(path)), Value::NamedStruct(owned)) = (&fields["path"], &value) else {
+        panic!("expected a borrowed path");
+    };
+    assert!(std::ptr::eq(*path, owned.fields["path"].as_string().unwrap()));
+    assert_eq!(view.to_owned(), value);
+
+    let text = String::from("borrowed");
+    let bytes = [1u8, 2];
+    let entries = vec![
+        (BorrowedStrDeserializer::<Error>::new(&text), BorrowedBytesDeserializer::<Error>::new(&bytes)),
+    ];
+    let map = ValueRef::deserialize(MapDeserializer::new(entries.into_iter())).unwrap();
+    let expected = [(
+        ValueRef::String(Cow::Borrowed(&text)),
+        ValueRef::Bytes(Cow::Borrowed(&bytes)),
+    )];
+    assert_eq!(map, ValueRef::Map(expected.into()));
+    assert_eq!(map.into_owned(), Value::Map([(Value::from("borrowed"), Value::from(&[1u8, 2]))].into()));
+
+    let copied = ValueRef::deserialize(StrDeserializer::<Error>::new("copied")).unwrap();
+    assert!(matches!(copied, ValueRef::String(Cow::Owned(_))));
+    let number = ValueRef::deserialize(5u16.into_deserializer() as serde::de::value::U16Deserializer<Error>).unwrap();
+    assert_eq!(number, ValueRef::Number(Number::from(5u16)));
+}
@@ -0,0 +1,465 @@
+use std::ops;
+use crate::value::{NamedStruct, NamedVariant, Number, SharedNode, SharedValue, TupleStruct, TupleVariant, Value};
+
+/// A type that can be used to index into a [`Value`] or a [`SharedValue`].
+///
+/// * [`usize`] indexes `Seq`, `Tuple`, `TupleStruct` and `TupleVariant` values by position,
+///   and `Map` values with an integer key of any width.
+/// * [`str`] and [`String`] index `NamedStruct` and `NamedVariant` values by field name,
+///   and `Map` values with a `String` key.
+/// * [`Value`] indexes `Map` values by key. A `String` or integer `Value` also falls back to
+///   the behaviour of `str` or `usize` on the other compound variants.
+///
+/// This trait is sealed and cannot be implemented outside of this crate.
+pub trait Index: private::Sealed {
+    #[doc(hidden)]
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value>;
+
+    #[doc(hidden)]
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value>;
+
+    #[doc(hidden)]
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value;
+
+    #[doc(hidden)]
+    fn index_into_shared<'v>(&self, node: &'v SharedNode) -> Option<&'v SharedValue>;
+
+    #[doc(hidden)]
+    fn index_into_shared_mut<'v>(&self, node: &'v mut SharedNode) -> Option<&'v mut SharedValue>;
+
+    #[doc(hidden)]
+    fn index_or_insert_shared<'v>(&self, node: &'v mut SharedNode) -> &'v mut SharedValue;
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for usize {}
+    impl Sealed for str {}
+    impl Sealed for String {}
+    impl Sealed for crate::value::Value {}
+    impl<T: ?Sized + Sealed> Sealed for &T {}
+}
+
+/// Returns true if `number` is an integer equal to `index`, regardless of its width.
+pub(crate) fn number_eq_index(number: &Number, index: usize) -> bool {
+    let index = index as u128;
+    match *number {
+        Number::U8(v) => v as u128 == index,
+        Number::U16(v) => v as u128 == index,
+        Number::U32(v) => v as u128 == index,
+        Number::U64(v) => v as u128 == index,
+        Number::U128(v) => v == index,
+        Number::I8(v) => v >= 0 && v as u128 == index,
+        Number::I16(v) => v >= 0 && v as u128 == index,
+        Number::I32(v) => v >= 0 && v as u128 == index,
+        Number::I64(v) => v >= 0 && v as u128 == index,
+        Number::I128(v) => v >= 0 && v as u128 == index,
+        Number::F32(_) | Number::F64(_) => false,
+    }
+}
+
+/// Returns the index held by an integer `number`, if it fits in a `usize`.
+pub(crate) fn number_as_index(number: &Number) -> Option<usize> {
+    match *number {
+        Number::U8(v) => Some(v as usize),
+        Number::U16(v) => Some(v as usize),
+        Number::U32(v) => usize::try_from(v).ok(),
+        Number::U64(v) => usize::try_from(v).ok(),
+        Number::U128(v) => usize::try_from(v).ok(),
+        Number::I8(v) => usize::try_from(v).ok(),
+        Number::I16(v) => usize::try_from(v).ok(),
+        Number::I32(v) => usize::try_from(v).ok(),
+        Number::I64(v) => usize::try_from(v).ok(),
+        Number::I128(v) => usize::try_from(v).ok(),
+        Number::F32(_) | Number::F64(_) => None,
+    }
+}
+
+/// The integer map keys equal to `index`, one for each width that can hold it
+fn index_keys(index: usize) -> impl Iterator<Item=Value> {
+    let index = index as u128;
+    [
+        u8::try_from(index).ok().map(Number::from),
+        u16::try_from(index).ok().map(Number::from),
+        u32::try_from(index).ok().map(Number::from),
+        u64::try_from(index).ok().map(Number::from),
+        Some(Number::from(index)),
+        i8::try_from(index).ok().map(Number::from),
+        i16::try_from(index).ok().map(Number::from),
+        i32::try_from(index).ok().map(Number::from),
+        i64::try_from(index).ok().map(Number::from),
+        i128::try_from(index).ok().map(Number::from),
+    ].into_iter().flatten().map(Value::Number)
+}
+
+/// The key a missing index is inserted under, as serde serializes a `usize`
+fn index_key(index: usize) -> Value {
+    Value::from(index as u64)
+}
+
+impl Index for usize {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match value {
+            Value::Seq(values) | Value::Tuple(values) => values.get(*self),
+            Value::TupleStruct(val) => val.values.get(*self),
+            Value::TupleVariant(val) => val.values.get(*self),
+            Value::Map(map) => index_keys(*self).find_map(|key| map.get(&key)),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match value {
+            Value::Seq(values) | Value::Tuple(values) => values.get_mut(*self),
+            Value::TupleStruct(val) => val.values.get_mut(*self),
+            Value::TupleVariant(val) => val.values.get_mut(*self),
+            Value::Map(map) => {
+                let key = index_keys(*self).find(|key| map.contains_key(key))?;
+                map.get_mut(&key)
+            }
+            _ => None,
+        }
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        if let Value::Map(map) = value {
+            let key = index_keys(*self).find(|key| map.contains_key(key)).unwrap_or_else(|| index_key(*self));
+            return map.entry(key).or_insert(Value::Unit);
+        }
+
+        let kind = kind_name(value);
+        match self.index_into_mut(value) {
+            Some(value) => value,
+            None => panic!("cannot access index {self} of Value::{kind}"),
+        }
+    }
+
+    fn index_into_shared<'v>(&self, node: &'v SharedNode) -> Option<&'v SharedValue> {
+        match node {
+            SharedNode::Seq(values)
+            | SharedNode::Tuple(values)
+            | SharedNode::TupleStruct(TupleStruct { values, .. })
+            | SharedNode::TupleVariant(TupleVariant { values, .. }) => values.get(*self),
+            SharedNode::Map(map) => index_keys(*self).find_map(|key| map.get(&SharedValue::from(key))),
+            _ => None,
+        }
+    }
+
+    fn index_into_shared_mut<'v>(&self, node: &'v mut SharedNode) -> Option<&'v mut SharedValue> {
+        match node {
+            SharedNode::Seq(values)
+            | SharedNode::Tuple(values)
+            | SharedNode::TupleStruct(TupleStruct { values, .. })
+            | SharedNode::TupleVariant(TupleVariant { values, .. }) => values.get_mut(*self),
+            SharedNode::Map(map) => {
+                let key = index_keys(*self).map(SharedValue::from).find(|key| map.contains_key(key))?;
+                map.get_mut(&key)
+            }
+            _ => None,
+        }
+    }
+
+    fn index_or_insert_shared<'v>(&self, node: &'v mut SharedNode) -> &'v mut SharedValue {
+        if let SharedNode::Map(map) = node {
+            let key = index_keys(*self).map(SharedValue::from).find(|key| map.contains_key(key))
+                .unwrap_or_else(|| SharedValue::from(index_key(*self)));
+            return map.entry(key).or_insert_with(|| SharedValue::from(Value::Unit));
+        }
+
+        let kind = shared_kind_name(node);
+        match self.index_into_shared_mut(node) {
+            Some(value) => value,
+            None => panic!("cannot access index {self} of SharedNode::{kind}"),
+        }
+    }
+}
+
+impl Index for str {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match value {
+            Value::NamedStruct(val) => val.fields.get(self),
+            Value::NamedVariant(val) => val.fields.get(self),
+            Value::Map(map) => map.get(&Value::String(self.to_string())),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match value {
+            Value::NamedStruct(val) => val.fields.get_mut(self),
+            Value::NamedVariant(val) => val.fields.get_mut(self),
+            Value::Map(map) => map.get_mut(&Value::String(self.to_string())),
+            _ => None,
+        }
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        if let Value::Map(map) = value {
+            return map.entry(Value::String(self.to_string())).or_insert(Value::Unit);
+        }
+
+        let kind = kind_name(value);
+        match self.index_into_mut(value) {
+            Some(value) => value,
+            None => panic!("cannot access field {self:?} of Value::{kind}"),
+        }
+    }
+
+    fn index_into_shared<'v>(&self, node: &'v SharedNode) -> Option<&'v SharedValue> {
+        match node {
+            SharedNode::NamedStruct(NamedStruct { fields, .. }) | SharedNode::NamedVariant(NamedVariant { fields, .. }) => fields.get(self),
+            SharedNode::Map(map) => map.get(&SharedValue::from(Value::String(self.to_string()))),
+            _ => None,
+        }
+    }
+
+    fn index_into_shared_mut<'v>(&self, node: &'v mut SharedNode) -> Option<&'v mut SharedValue> {
+        match node {
+            SharedNode::NamedStruct(NamedStruct { fields, .. }) | SharedNode::NamedVariant(NamedVariant { fields, .. }) => fields.get_mut(self),
+            SharedNode::Map(map) => map.get_mut(&SharedValue::from(Value::String(self.to_string()))),
+            _ => None,
+        }
+    }
+
+    fn index_or_insert_shared<'v>(&self, node: &'v mut SharedNode) -> &'v mut SharedValue {
+        if let SharedNode::Map(map) = node {
+            return map.entry(SharedValue::from(Value::String(self.to_string())))
+                .or_insert_with(|| SharedValue::from(Value::Unit));
+        }
+
+        let kind = shared_kind_name(node);
+        match self.index_into_shared_mut(node) {
+            Some(value) => value,
+            None => panic!("cannot access field {self:?} of SharedNode::{kind}"),
+        }
+    }
+}
+
+impl Index for String {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        self.as_str().index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        self.as_str().index_into_mut(value)
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        self.as_str().index_or_insert(value)
+    }
+
+    fn index_into_shared<'v>(&self, node: &'v SharedNode) -> Option<&'v SharedValue> {
+        self.as_str().index_into_shared(node)
+    }
+
+    fn index_into_shared_mut<'v>(&self, node: &'v mut SharedNode) -> Option<&'v mut SharedValue> {
+        self.as_str().index_into_shared_mut(node)
+    }
+
+    fn index_or_insert_shared<'v>(&self, node: &'v mut SharedNode) -> &'v mut SharedValue {
+        self.as_str().index_or_insert_shared(node)
+    }
+}
+
+impl Index for Value {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        if let Value::Map(map) = value {
+            return map.get(self);
+        }
+
+        match self {
+            Value::String(key) => key.index_into(value),
+            Value::Number(n) => number_as_index(n).and_then(|i| i.index_into(value)),
+            _ => None,
+        }
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        if let Value::Map(map) = value {
+            return map.get_mut(self);
+        }
+
+        match self {
+            Value::String(key) => key.index_into_mut(value),
+            Value::Number(n) => number_as_index(n).and_then(|i| i.index_into_mut(value)),
+            _ => None,
+        }
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        if let Value::Map(map) = value {
+            return map.entry(self.clone()).or_insert(Value::Unit);
+        }
+
+        let kind = kind_name(value);
+        match self.index_into_mut(value) {
+            Some(value) => value,
+            None => panic!("cannot access key {self:?} of Value::{kind}"),
+        }
+    }
+
+    fn index_into_shared<'v>(&self, node: &'v SharedNode) -> Option<&'v SharedValue> {
+        if let SharedNode::Map(map) = node {
+            return map.get(&SharedValue::from(self));
+        }
+
+        match self {
+            Value::String(key) => key.index_into_shared(node),
+            Value::Number(n) => number_as_index(n).and_then(|i| i.index_into_shared(node)),
+            _ => None,
+        }
+    }
+
+    fn index_into_shared_mut<'v>(&self, node: &'v mut SharedNode) -> Option<&'v mut SharedValue> {
+        if let SharedNode::Map(map) = node {
+            return map.get_mut(&SharedValue::from(self));
+        }
+
+        match self {
+            Value::String(key) => key.index_into_shared_mut(node),
+            Value::Number(n) => number_as_index(n).and_then(|i| i.index_into_shared_mut(node)),
+            _ => None,
+        }
+    }
+
+    fn index_or_insert_shared<'v>(&self, node: &'v mut SharedNode) -> &'v mut SharedValue {
+        if let SharedNode::Map(map) = node {
+            return map.entry(SharedValue::from(self)).or_insert_with(|| SharedValue::from(Value::Unit));
+        }
+
+        let kind = shared_kind_name(node);
+        match self.index_into_shared_mut(node) {
+            Some(value) => value,
+            None => panic!("cannot access key {self:?} of SharedNode::{kind}"),
+        }
+    }
+}
+
+impl<T: ?Sized + Index> Index for &T {
+    fn index_into<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        (**self).index_into(value)
+    }
+
+    fn index_into_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        (**self).index_into_mut(value)
+    }
+
+    fn index_or_insert<'v>(&self, value: &'v mut Value) -> &'v mut Value {
+        (**self).index_or_insert(value)
+    }
+
+    fn index_into_shared<'v>(&self, node: &'v SharedNode) -> Option<&'v SharedValue> {
+        (**self).index_into_shared(node)
+    }
+
+    fn index_into_shared_mut<'v>(&self, node: &'v mut SharedNode) -> Option<&'v mut SharedValue> {
+        (**self).index_into_shared_mut(node)
+    }
+
+    fn index_or_insert_shared<'v>(&self, node: &'v mut SharedNode) -> &'v mut SharedValue {
+        (**self).index_or_insert_shared(node)
+    }
+}
+
+fn kind_name(value: &Value) -> &'static str {
+    match value {
+        Value::Unit => "Unit",
+        Value::Bool(_) => "Bool",
+        Value::Char(_) => "Char",
+        Value::Number(_) => "Number",
+        Value::String(_) => "String",
+        Value::Seq(_) => "Seq",
+        Value::Map(_) => "Map",
+        Value::Tuple(_) => "Tuple",
+        Value::UnitStruct(_) => "UnitStruct",
+        Value::TupleStruct(_) => "TupleStruct",
+        Value::NamedStruct(_) => "NamedStruct",
+        Value::UnitVariant(_) => "UnitVariant",
+        Value::TupleVariant(_) => "TupleVariant",
+        Value::NamedVariant(_) => "NamedVariant",
+    }
+}
+
+fn shared_kind_name(node: &SharedNode) -> &'static str {
+    match node {
+        SharedNode::Unit => "Unit",
+        SharedNode::Bool(_) => "Bool",
+        SharedNode::Char(_) => "Char",
+        SharedNode::Number(_) => "Number",
+        SharedNode::String(_) => "String",
+        SharedNode::Seq(_) => "Seq",
+        SharedNode::Map(_) => "Map",
+        SharedNode::Tuple(_) => "Tuple",
+        SharedNode::UnitStruct(_) => "UnitStruct",
+        SharedNode::TupleStruct(_) => "TupleStruct",
+        SharedNode::NamedStruct(_) => "NamedStruct",
+        SharedNode::UnitVariant(_) => "UnitVariant",
+        SharedNode::TupleVariant(_) => "TupleVariant",
+        SharedNode::NamedVariant(_) => "NamedVariant",
+    }
+}
+
+static UNIT: Value = Value::Unit;
+
+/// Index into a `Value` using `value[index]`.
+///
+/// Returns a reference to `Value::Unit` if the index is missing or the value is not a
+/// compound that can be indexed by `I`. Use [`Value::get`] to tell the two cases apart.
+impl<I: Index> ops::Index<I> for Value {
+    type Output = Value;
+
+    fn index(&self, index: I) -> &Self::Output {
+        index.index_into(self).unwrap_or(&UNIT)
+    }
+}
+
+/// Mutably index into a `Value` using `value[index] = ...`.
+///
+/// Indexing a `Map` with a missing key inserts `Value::Unit` under that key, or under a `u64`
+/// key for a missing `usize` index.
+///
+/// # Panics
+///
+/// Panics if the index is missing from any other compound variant, or if the value cannot
+/// be indexed by `I`.
+impl<I: Index> ops::IndexMut<I> for Value {
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        index.index_or_insert(self)
+    }
+}
+
+#[test]
+fn test_index() {
+    use std::collections::BTreeMap;
+
+    let mut fields = BTreeMap::new();
+    fields.insert("port", Value::from(8080u16));
+    fields.insert("hosts", Value::from(vec!["a", "b"]));
+    let mut value = Value::NamedStruct(NamedStruct { name: "Server", fields });
+
+    assert_eq!(value["port"].as_u16(), Some(8080));
+    assert_eq!(value["hosts"][1].as_string(), Some("b"));
+    assert!(value["missing"].is_unit());
+    assert!(value.get("missing").is_none());
+    assert!(value["hosts"][7].is_unit());
+
+    value["hosts"][0] = Value::from("c");
+    assert_eq!(value["hosts"][0].as_string(), Some("c"));
+
+    let variant = Value::TupleVariant(TupleVariant { name: "E", variant: "A", values: vec![Value::from(true)] });
+    assert_eq!(variant[0].as_bool(), Some(true));
+    assert_eq!(variant.get(Value::from(0u8)).and_then(Value::as_bool), Some(true));
+
+    let mut map = Value::Map(BTreeMap::new());
+    map["key"] = Value::from(1u8);
+    map[Value::from(3u32)] = Value::from(2u8);
+    assert_eq!(map["key"].as_u8(), Some(1));
+    assert_eq!(map[3].as_u8(), Some(2));
+    assert_eq!(map.get_mut("key").map(|v| std::mem::replace(v, Value::Unit)), Some(Value::from(1u8)));
+    map[3] = Value::from(4u8);
+    map[5] = Value::from(6u8);
+    assert_eq!(map[Value::from(3u32)].as_u8(), Some(4));
+    assert_eq!(map[Value::from(5u64)].as_u8(), Some(6));
+    assert!(map.get(Value::from(5u8)).is_none());
+}
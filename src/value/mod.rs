@@ -1,9 +1,18 @@
+mod borrowed;
 mod from;
-
+mod index;
 mod number;
+mod shared;
+mod symbol;
+pub(crate) mod path;
 
 use std::collections::BTreeMap;
-pub use number::Number;
+pub use borrowed::ValueRef;
+pub use index::Index;
+pub use number::{Number, NumberKind, F32, F64};
+pub use path::{Path, PathError, Segment};
+pub use shared::{SharedNode, SharedValue};
+pub use symbol::{Symbol, SymbolTable};
 
 /// An intermediate value
 #[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
@@ -360,54 +369,78 @@ impl Value {
             _ => None,
         }
     }
+
+    /// Returns the element, field or map entry addressed by `index`, see [`Index`].
+    ///
+    /// Returns `None` if the index is missing or this value cannot be indexed by `I`.
+    #[inline]
+    pub fn get<I: Index>(&self, index: I) -> Option<&Value> {
+        index.index_into(self)
+    }
+
+    /// Mutable version of [`Value::get`].
+    #[inline]
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut Value> {
+        index.index_into_mut(self)
+    }
 }
 
 #[non_exhaustive]
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct UnitStruct {
     pub name: &'static str,
 }
 
+/// A tuple struct, whose `values` are the children of a [`Value`] by default, or of a mirror
+/// such as [`ValueRef`], [`SharedNode`] or [`ArenaValue`](crate::arena::ArenaValue)
 #[non_exhaustive]
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
-pub struct TupleStruct {
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct TupleStruct<V = Vec<Value>> {
     pub name: &'static str,
-    pub values: Vec<Value>,
+    pub values: V,
 }
 
+/// A named struct, whose `fields` are the children of a [`Value`] by default, or of a mirror
+/// such as [`ValueRef`], [`SharedNode`] or [`ArenaValue`](crate::arena::ArenaValue)
 #[non_exhaustive]
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
-pub struct NamedStruct {
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct NamedStruct<F = BTreeMap<&'static str, Value>> {
     pub name: &'static str,
-    pub fields: BTreeMap<&'static str, Value>,
+    pub fields: F,
 }
 
 #[non_exhaustive]
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
 pub struct UnitVariant {
     pub name: &'static str,
     pub variant: &'static str,
 }
 
+/// A tuple variant, whose `values` are the children of a [`Value`] by default, or of a mirror
+/// such as [`ValueRef`], [`SharedNode`] or [`ArenaValue`](crate::arena::ArenaValue)
 #[non_exhaustive]
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
-pub struct TupleVariant {
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct TupleVariant<V = Vec<Value>> {
     pub name: &'static str,
     pub variant: &'static str,
-    pub values: Vec<Value>,
+    pub values: V,
 }
 
+/// A named variant, whose `fields` are the children of a [`Value`] by default, or of a mirror
+/// such as [`ValueRef`], [`SharedNode`] or [`ArenaValue`](crate::arena::ArenaValue)
 #[non_exhaustive]
-#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
-pub struct NamedVariant {
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct NamedVariant<F = BTreeMap<&'static str, Value>> {
     pub name: &'static str,
     pub variant: &'static str,
-    pub fields: BTreeMap<&'static str, Value>,
+    pub fields: F,
 }
 
 mod debug {
+    use std::collections::BTreeMap;
     use std::fmt::{Debug, Formatter};
-    use crate::value::{NamedStruct, NamedVariant, TupleStruct, TupleVariant, UnitStruct, UnitVariant, Value};
+    use crate::arena::{Elements, Fields};
+    use crate::value::{NamedStruct, NamedVariant, SharedValue, TupleStruct, TupleVariant, UnitStruct, UnitVariant, Value, ValueRef};
 
     impl Debug for Value {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -454,58 +487,63 @@ mod debug {
         }
     }
 
-    impl Debug for TupleStruct {
+    impl Debug for UnitVariant {
         fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            let mut builder = f.debug_tuple(self.name);
-
-            for value in &self.values {
-                builder.field(value);
-            }
-
-            builder.finish()
+            write!(f, "{}::{}", self.name, self.variant)
         }
     }
 
-    impl Debug for NamedStruct {
-        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            let mut builder = f.debug_struct(self.name);
-
-            for (name, value) in self.fields.iter() {
-                builder.field(name, value);
+    /// Implements `Debug` for the payloads holding `$values` and `$fields`
+    macro_rules! debug_payloads {
+        ($values:ty, $fields:ty) => {
+            impl Debug for TupleStruct<$values> {
+                fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                    debug_tuple(f, self.name, self.values.iter())
+                }
             }
 
-            builder.finish()
-        }
-    }
+            impl Debug for NamedStruct<$fields> {
+                fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                    debug_struct(f, self.name, self.fields.iter())
+                }
+            }
 
+            impl Debug for TupleVariant<$values> {
+                fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                    debug_tuple(f, &format!("{}::{}", self.name, self.variant), self.values.iter())
+                }
+            }
 
-    impl Debug for UnitVariant {
-        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            write!(f, "{}::{}", self.name, self.variant)
-        }
+            impl Debug for NamedVariant<$fields> {
+                fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+                    debug_struct(f, &format!("{}::{}", self.name, self.variant), self.fields.iter())
+                }
+            }
+        };
     }
 
-    impl Debug for TupleVariant {
-        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            let mut builder = f.debug_tuple(&format!("{}::{}", self.name, self.variant));
+    debug_payloads!(Vec<Value>, BTreeMap<&'static str, Value>);
+    debug_payloads!(Vec<ValueRef<'_>>, BTreeMap<&'static str, ValueRef<'_>>);
+    debug_payloads!(Elements<'_>, Fields<'_>);
+    debug_payloads!(Vec<SharedValue>, BTreeMap<&'static str, SharedValue>);
 
-            for value in &self.values {
-                builder.field(value);
-            }
+    fn debug_tuple<T: Debug>(f: &mut Formatter<'_>, name: &str, values: impl Iterator<Item=T>) -> std::fmt::Result {
+        let mut builder = f.debug_tuple(name);
 
-            builder.finish()
+        for value in values {
+            builder.field(&value);
         }
-    }
 
-    impl Debug for NamedVariant {
-        fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-            let mut builder = f.debug_struct(&format!("{}::{}", self.name, self.variant));
+        builder.finish()
+    }
 
-            for (name, value) in self.fields.iter() {
-                builder.field(name, value);
-            }
+    fn debug_struct<N: AsRef<str>, T: Debug>(f: &mut Formatter<'_>, name: &str, fields: impl Iterator<Item=(N, T)>) -> std::fmt::Result {
+        let mut builder = f.debug_struct(name);
 
-            builder.finish()
+        for (name, value) in fields {
+            builder.field(name.as_ref(), &value);
         }
+
+        builder.finish()
     }
-}
\ No newline at end of file
+}
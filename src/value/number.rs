@@ -1,7 +1,6 @@
 use std::hash::{Hash, Hasher};
 use std::cmp::Ordering;
 use std::fmt::{Debug, Display, Formatter};
-use std::ops::Deref;
 
 /// A numeric value wrapper, supports u8-u128, i8-i128, f32, & f64
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
@@ -106,7 +105,7 @@ macro_rules! float_ty {
                 )]
         ///
         /// ```
-        #[doc = concat!("use ron::value::", stringify!($ty), ";")]
+        #[doc = concat!("use serde_value::", stringify!($ty), ";")]
         #[doc = concat!(
                     "assert!(", stringify!($ty), "::new(", stringify!($float), "::NAN) > ",
                     stringify!($ty), "::new(", stringify!($float), "::INFINITY));",
@@ -371,6 +370,213 @@ impl Number {
             _ => None,
         }
     }
+
+    /// Compares the numeric values of two numbers regardless of their widths.
+    ///
+    /// Integers are compared exactly, comparisons involving a float go through [`f64`].
+    /// Returns `None` if either side is NaN.
+    ///
+    /// ```
+    /// use std::cmp::Ordering;
+    /// use serde_value::Number;
+    ///
+    /// assert_eq!(Number::U8(3).numeric_cmp(&Number::I64(3)), Some(Ordering::Equal));
+    /// assert_eq!(Number::I8(-1).numeric_cmp(&Number::U128(u128::MAX)), Some(Ordering::Less));
+    /// assert_eq!(Number::F32(2.5.into()).numeric_cmp(&Number::U16(2)), Some(Ordering::Greater));
+    /// ```
+    pub fn numeric_cmp(&self, other: &Number) -> Option<Ordering> {
+        match (self.widened(), other.widened()) {
+            (Widened::Signed(a), Widened::Signed(b)) => Some(a.cmp(&b)),
+            (Widened::Unsigned(a), Widened::Unsigned(b)) => Some(a.cmp(&b)),
+            (Widened::Signed(a), Widened::Unsigned(b)) => match u128::try_from(a) {
+                Ok(a) => Some(a.cmp(&b)),
+                Err(_) => Some(Ordering::Less),
+            },
+            (Widened::Unsigned(a), Widened::Signed(b)) => match u128::try_from(b) {
+                Ok(b) => Some(a.cmp(&b)),
+                Err(_) => Some(Ordering::Greater),
+            },
+            (a, b) => a.as_f64().partial_cmp(&b.as_f64()),
+        }
+    }
+
+    /// The kind of this number
+    pub fn kind(&self) -> NumberKind {
+        match self {
+            Number::U8(_) => NumberKind::U8,
+            Number::U16(_) => NumberKind::U16,
+            Number::U32(_) => NumberKind::U32,
+            Number::U64(_) => NumberKind::U64,
+            Number::U128(_) => NumberKind::U128,
+            Number::I8(_) => NumberKind::I8,
+            Number::I16(_) => NumberKind::I16,
+            Number::I32(_) => NumberKind::I32,
+            Number::I64(_) => NumberKind::I64,
+            Number::I128(_) => NumberKind::I128,
+            Number::F32(_) => NumberKind::F32,
+            Number::F64(_) => NumberKind::F64,
+        }
+    }
+
+    fn widened(&self) -> Widened {
+        match *self {
+            Number::U8(v) => Widened::Unsigned(v as u128),
+            Number::U16(v) => Widened::Unsigned(v as u128),
+            Number::U32(v) => Widened::Unsigned(v as u128),
+            Number::U64(v) => Widened::Unsigned(v as u128),
+            Number::U128(v) => Widened::Unsigned(v),
+            Number::I8(v) => Widened::Signed(v as i128),
+            Number::I16(v) => Widened::Signed(v as i128),
+            Number::I32(v) => Widened::Signed(v as i128),
+            Number::I64(v) => Widened::Signed(v as i128),
+            Number::I128(v) => Widened::Signed(v),
+            Number::F32(v) => Widened::Float(v.0 as f64),
+            Number::F64(v) => Widened::Float(v.0),
+        }
+    }
+}
+
+/// The width and signedness of a [`Number`], without its value
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum NumberKind {
+    U8,
+    U16,
+    U32,
+    U64,
+    U128,
+    I8,
+    I16,
+    I32,
+    I64,
+    I128,
+    F32,
+    F64,
+}
+
+impl NumberKind {
+    /// Every kind, from `U8` to `F64`
+    pub const ALL: [NumberKind; 12] = [
+        NumberKind::U8, NumberKind::U16, NumberKind::U32, NumberKind::U64, NumberKind::U128,
+        NumberKind::I8, NumberKind::I16, NumberKind::I32, NumberKind::I64, NumberKind::I128,
+        NumberKind::F32, NumberKind::F64,
+    ];
+
+    /// The name of the matching Rust primitive, e.g. `"u8"`
+    pub fn name(self) -> &'static str {
+        match self {
+            NumberKind::U8 => "u8",
+            NumberKind::U16 => "u16",
+            NumberKind::U32 => "u32",
+            NumberKind::U64 => "u64",
+            NumberKind::U128 => "u128",
+            NumberKind::I8 => "i8",
+            NumberKind::I16 => "i16",
+            NumberKind::I32 => "i32",
+            NumberKind::I64 => "i64",
+            NumberKind::I128 => "i128",
+            NumberKind::F32 => "f32",
+            NumberKind::F64 => "f64",
+        }
+    }
+
+    /// The smallest value of this kind, the most negative finite value for floats
+    pub fn min_value(self) -> Number {
+        match self {
+            NumberKind::U8 => Number::U8(u8::MIN),
+            NumberKind::U16 => Number::U16(u16::MIN),
+            NumberKind::U32 => Number::U32(u32::MIN),
+            NumberKind::U64 => Number::U64(u64::MIN),
+            NumberKind::U128 => Number::U128(u128::MIN),
+            NumberKind::I8 => Number::I8(i8::MIN),
+            NumberKind::I16 => Number::I16(i16::MIN),
+            NumberKind::I32 => Number::I32(i32::MIN),
+            NumberKind::I64 => Number::I64(i64::MIN),
+            NumberKind::I128 => Number::I128(i128::MIN),
+            NumberKind::F32 => Number::F32(F32(f32::MIN)),
+            NumberKind::F64 => Number::F64(F64(f64::MIN)),
+        }
+    }
+
+    /// The largest value of this kind, the largest finite value for floats
+    pub fn max_value(self) -> Number {
+        match self {
+            NumberKind::U8 => Number::U8(u8::MAX),
+            NumberKind::U16 => Number::U16(u16::MAX),
+            NumberKind::U32 => Number::U32(u32::MAX),
+            NumberKind::U64 => Number::U64(u64::MAX),
+            NumberKind::U128 => Number::U128(u128::MAX),
+            NumberKind::I8 => Number::I8(i8::MAX),
+            NumberKind::I16 => Number::I16(i16::MAX),
+            NumberKind::I32 => Number::I32(i32::MAX),
+            NumberKind::I64 => Number::I64(i64::MAX),
+            NumberKind::I128 => Number::I128(i128::MAX),
+            NumberKind::F32 => Number::F32(F32(f32::MAX)),
+            NumberKind::F64 => Number::F64(F64(f64::MAX)),
+        }
+    }
+
+    /// Whether the value of `number` can be represented by this kind without loss.
+    ///
+    /// Integer kinds accept integers in their range, float kinds accept integers they
+    /// represent exactly and floats that survive the conversion.
+    ///
+    /// ```
+    /// use serde_value::{Number, NumberKind};
+    ///
+    /// assert!(NumberKind::U8.fits(&Number::I64(200)));
+    /// assert!(!NumberKind::I8.fits(&Number::U8(200)));
+    /// assert!(NumberKind::F64.fits(&Number::U32(7)));
+    /// assert!(!NumberKind::U32.fits(&Number::F32(7.0.into())));
+    /// ```
+    pub fn fits(self, number: &Number) -> bool {
+        if self.is_float() {
+            let exact = match self {
+                NumberKind::F32 => 1u128 << f32::MANTISSA_DIGITS,
+                _ => 1u128 << f64::MANTISSA_DIGITS,
+            };
+            return match number.widened() {
+                Widened::Unsigned(v) => v <= exact,
+                Widened::Signed(v) => v.unsigned_abs() <= exact,
+                Widened::Float(v) => self == NumberKind::F64 || v.is_nan() || v as f32 as f64 == v,
+            };
+        }
+        if number.kind().is_float() {
+            return false;
+        }
+        number.numeric_cmp(&self.min_value()) != Some(Ordering::Less)
+            && number.numeric_cmp(&self.max_value()) != Some(Ordering::Greater)
+    }
+
+    pub fn is_float(self) -> bool {
+        matches!(self, NumberKind::F32 | NumberKind::F64)
+    }
+
+    pub fn is_signed(self) -> bool {
+        !matches!(self, NumberKind::U8 | NumberKind::U16 | NumberKind::U32 | NumberKind::U64 | NumberKind::U128)
+    }
+}
+
+impl Display for NumberKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+#[derive(Copy, Clone)]
+enum Widened {
+    Signed(i128),
+    Unsigned(u128),
+    Float(f64),
+}
+
+impl Widened {
+    fn as_f64(self) -> f64 {
+        match self {
+            Widened::Signed(v) => v as f64,
+            Widened::Unsigned(v) => v as f64,
+            Widened::Float(v) => v,
+        }
+    }
 }
 
 impl Debug for Number {
@@ -0,0 +1,699 @@
+use std::fmt::{Display, Formatter, Write};
+use std::str::FromStr;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Mutex;
+use crate::value::{Index, NamedStruct, NamedVariant, Number, TupleStruct, TupleVariant, Value};
+use crate::value::index::{number_as_index, number_eq_index};
+
+/// A single step of a [`Path`]
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum Segment {
+    /// A struct or variant field, or a `String` map key
+    Field(String),
+    /// A position in a `Seq`, `Tuple`, `TupleStruct` or `TupleVariant`, or an integer map key
+    Index(usize),
+    /// A map key of any type
+    Key(Value),
+    /// The position past the last element of a sequence, only valid when inserting
+    End,
+}
+
+/// A location inside a [`Value`] tree, made of [`Segment`]s.
+///
+/// A path can be parsed from, and displayed as, a JSON-Pointer-style string such as
+/// `/servers/0/ports/http`:
+///
+/// * `~0` and `~1` escape `~` and `/`, and `~2` escapes a leading `{`
+/// * a segment of ASCII digits is an [`Segment::Index`], and `-` is [`Segment::End`]
+/// * a segment wrapped in braces is a [`Segment::Key`] holding a literal: `{()}`, `{true}`,
+///   `{'c'}`, `{"text"}`, or a number with its width suffix such as `{5u32}` or `{-1.5f64}`
+/// * anything else is a [`Segment::Field`]
+///
+/// Lookups are lenient about how a segment was written: an index also matches an integer map
+/// key of any width or a `String` key holding the digits, and a key falls back to a field name
+/// or position when the node is not a map.
+///
+/// ```
+/// use serde_value::{Path, Segment, Value};
+///
+/// let path: Path = "/servers/0/{8080u16}".parse().unwrap();
+/// assert_eq!(path.segments()[0], Segment::Field("servers".to_string()));
+/// assert_eq!(path.segments()[1], Segment::Index(0));
+/// assert_eq!(path.segments()[2], Segment::Key(Value::from(8080u16)));
+/// assert_eq!(path.to_string(), "/servers/0/{8080u16}");
+/// ```
+#[derive(Clone, Debug, Default, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Path {
+    segments: Vec<Segment>,
+}
+
+/// An error produced while parsing or following a [`Path`]
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathError {
+    /// The pointer string is malformed
+    Syntax {
+        pointer: String,
+        message: String,
+    },
+    /// Nothing exists at the path
+    NotFound(Path),
+    /// An index is past the end of the sequence at the path
+    OutOfBounds {
+        path: Path,
+        len: usize,
+    },
+    /// A new field cannot be added to the struct or variant at the path
+    UnknownField(Path),
+    /// The value at the path has no children
+    NotAContainer(Path),
+    /// The operation requires a non-empty path
+    EmptyPath,
+}
+
+impl std::error::Error for PathError {}
+
+impl Display for PathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathError::Syntax { pointer, message } => write!(f, "invalid pointer {pointer:?}: {message}"),
+            PathError::NotFound(path) => write!(f, "no value at {path:?}", path = path.to_string()),
+            PathError::OutOfBounds { path, len } => write!(f, "index {path:?} is out of bounds for length {len}", path = path.to_string()),
+            PathError::UnknownField(path) => write!(f, "cannot add field {path:?}", path = path.to_string()),
+            PathError::NotAContainer(path) => write!(f, "value at {path:?} has no children", path = path.to_string()),
+            PathError::EmptyPath => write!(f, "path is empty"),
+        }
+    }
+}
+
+impl Path {
+    /// The empty path, addressing the root value
+    #[inline]
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Parse a JSON-Pointer-style string, see [`Path`] for the syntax
+    pub fn parse(pointer: &str) -> Result<Self, PathError> {
+        if pointer.is_empty() {
+            return Ok(Self::root());
+        }
+
+        let Some(rest) = pointer.strip_prefix('/') else {
+            return Err(syntax_error(pointer, "must be empty or start with '/'"));
+        };
+
+        rest.split('/')
+            .map(|raw| parse_segment(raw).map_err(|message| syntax_error(pointer, message)))
+            .collect()
+    }
+
+    #[inline]
+    pub fn segments(&self) -> &[Segment] {
+        &self.segments
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.segments.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.segments.is_empty()
+    }
+
+    #[inline]
+    pub fn push(&mut self, segment: Segment) {
+        self.segments.push(segment);
+    }
+
+    #[inline]
+    pub fn pop(&mut self) -> Option<Segment> {
+        self.segments.pop()
+    }
+
+    #[inline]
+    pub fn last(&self) -> Option<&Segment> {
+        self.segments.last()
+    }
+
+    /// Returns a copy of this path extended by `segment`
+    pub fn child(&self, segment: Segment) -> Self {
+        let mut path = self.clone();
+        path.push(segment);
+        path
+    }
+
+    /// Returns the path without its last segment, or `None` for the root path
+    pub fn parent(&self) -> Option<Self> {
+        let (_, parent) = self.segments.split_last()?;
+        Some(Self { segments: parent.to_vec() })
+    }
+
+    /// Returns true if `prefix` is an ancestor of, or equal to, this path
+    pub fn starts_with(&self, prefix: &Path) -> bool {
+        self.segments.starts_with(&prefix.segments)
+    }
+}
+
+impl From<Vec<Segment>> for Path {
+    fn from(segments: Vec<Segment>) -> Self {
+        Self { segments }
+    }
+}
+
+impl FromIterator<Segment> for Path {
+    fn from_iter<I: IntoIterator<Item=Segment>>(iter: I) -> Self {
+        Self { segments: iter.into_iter().collect() }
+    }
+}
+
+impl FromStr for Path {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl From<&str> for Segment {
+    fn from(value: &str) -> Self {
+        Self::Field(value.to_string())
+    }
+}
+
+impl From<String> for Segment {
+    fn from(value: String) -> Self {
+        Self::Field(value)
+    }
+}
+
+impl From<usize> for Segment {
+    fn from(value: usize) -> Self {
+        Self::Index(value)
+    }
+}
+
+impl From<Value> for Segment {
+    fn from(value: Value) -> Self {
+        Self::Key(value)
+    }
+}
+
+impl Display for Path {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for segment in &self.segments {
+            f.write_char('/')?;
+            write!(f, "{segment}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for Segment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Field(name) if name == "-" || is_index(name) => {
+                write_escaped(f, &format!("{{{name:?}}}"))
+            }
+            Segment::Field(name) => match name.strip_prefix('{') {
+                Some(rest) => {
+                    f.write_str("~2")?;
+                    write_escaped(f, rest)
+                }
+                None => write_escaped(f, name),
+            },
+            Segment::Index(index) => write!(f, "{index}"),
+            Segment::Key(key) => {
+                let literal = match key {
+                    Value::Unit => "()".to_string(),
+                    Value::Bool(val) => format!("{val}"),
+                    other => format!("{other:?}"),
+                };
+                write_escaped(f, &format!("{{{literal}}}"))
+            }
+            Segment::End => f.write_char('-'),
+        }
+    }
+}
+
+fn write_escaped(f: &mut Formatter<'_>, text: &str) -> std::fmt::Result {
+    for c in text.chars() {
+        match c {
+            '~' => f.write_str("~0")?,
+            '/' => f.write_str("~1")?,
+            c => f.write_char(c)?,
+        }
+    }
+    Ok(())
+}
+
+fn syntax_error(pointer: &str, message: impl Into<String>) -> PathError {
+    PathError::Syntax {
+        pointer: pointer.to_string(),
+        message: message.into(),
+    }
+}
+
+fn is_index(text: &str) -> bool {
+    !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit()) && (text == "0" || !text.starts_with('0'))
+}
+
+fn parse_segment(raw: &str) -> Result<Segment, String> {
+    if raw == "-" {
+        return Ok(Segment::End);
+    }
+
+    if is_index(raw) {
+        return raw.parse()
+            .map(Segment::Index)
+            .map_err(|_| format!("index {raw} is too large"));
+    }
+
+    let braced = raw.starts_with('{') && raw.ends_with('}') && raw.len() >= 2;
+
+    let mut text = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '~' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('0') => text.push('~'),
+            Some('1') => text.push('/'),
+            Some('2') => text.push('{'),
+            Some(c) => return Err(format!("invalid escape '~{c}'")),
+            None => return Err("unterminated escape '~'".to_string()),
+        }
+    }
+
+    if braced {
+        parse_literal(&text[1..text.len() - 1]).map(Segment::Key)
+    } else {
+        Ok(Segment::Field(text))
+    }
+}
+
+/// Parse a key literal as written by the [`Debug`] implementation of [`Value`]
+pub(crate) fn parse_literal(text: &str) -> Result<Value, String> {
+    match text {
+        "()" => return Ok(Value::Unit),
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+
+    if let Some(inner) = text.strip_prefix('\'').and_then(|t| t.strip_suffix('\'')) {
+        let unescaped = unescape(inner)?;
+        let mut chars = unescaped.chars();
+        return match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(Value::Char(c)),
+            _ => Err(format!("invalid char literal {text}")),
+        };
+    }
+
+    if let Some(inner) = text.strip_prefix('"').and_then(|t| t.strip_suffix('"')) {
+        return unescape(inner).map(Value::String);
+    }
+
+    parse_number(text)
+        .map(Value::Number)
+        .ok_or_else(|| format!("invalid key literal {text:?}"))
+}
+
+/// Parse a number with a width suffix, such as `5u32` or `-1.5f64`
+pub(crate) fn parse_number(text: &str) -> Option<Number> {
+    const SUFFIXES: [&str; 12] = ["u128", "i128", "u16", "u32", "u64", "i16", "i32", "i64", "f32", "f64", "u8", "i8"];
+
+    let suffix = SUFFIXES.iter().find(|s| text.ends_with(*s))?;
+    let digits = &text[..text.len() - suffix.len()];
+    if digits.is_empty() {
+        return None;
+    }
+
+    Some(match *suffix {
+        "u8" => Number::from(digits.parse::<u8>().ok()?),
+        "u16" => Number::from(digits.parse::<u16>().ok()?),
+        "u32" => Number::from(digits.parse::<u32>().ok()?),
+        "u64" => Number::from(digits.parse::<u64>().ok()?),
+        "u128" => Number::from(digits.parse::<u128>().ok()?),
+        "i8" => Number::from(digits.parse::<i8>().ok()?),
+        "i16" => Number::from(digits.parse::<i16>().ok()?),
+        "i32" => Number::from(digits.parse::<i32>().ok()?),
+        "i64" => Number::from(digits.parse::<i64>().ok()?),
+        "i128" => Number::from(digits.parse::<i128>().ok()?),
+        "f32" => Number::from(digits.parse::<f32>().ok()?),
+        _ => Number::from(digits.parse::<f64>().ok()?),
+    })
+}
+
+fn unescape(text: &str) -> Result<String, String> {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('\\') => out.push('\\'),
+            Some('"') => out.push('"'),
+            Some('\'') => out.push('\''),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('0') => out.push('\0'),
+            Some('u') => {
+                let rest = chars.as_str();
+                let hex = rest.strip_prefix('{')
+                    .and_then(|r| r.split_once('}'))
+                    .map(|(hex, _)| hex)
+                    .ok_or_else(|| "invalid unicode escape".to_string())?;
+                let c = u32::from_str_radix(hex, 16).ok()
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("invalid unicode escape \\u{{{hex}}}"))?;
+                out.push(c);
+                chars = rest[hex.len() + 2..].chars();
+            }
+            Some(c) => return Err(format!("invalid escape '\\{c}'")),
+            None => return Err("unterminated escape '\\'".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+impl Segment {
+    /// Returns the child of `value` addressed by this segment
+    pub fn resolve<'v>(&self, value: &'v Value) -> Option<&'v Value> {
+        match self {
+            Segment::Field(name) => name.index_into(value),
+            Segment::Index(index) => index.index_into(value).or_else(|| match value {
+                Value::Map(_) | Value::NamedStruct(_) | Value::NamedVariant(_) => index.to_string().index_into(value),
+                _ => None,
+            }),
+            Segment::Key(key) => key.index_into(value),
+            Segment::End => None,
+        }
+    }
+
+    /// Mutable version of [`Segment::resolve`]
+    pub fn resolve_mut<'v>(&self, value: &'v mut Value) -> Option<&'v mut Value> {
+        match self {
+            Segment::Field(name) => name.index_into_mut(value),
+            Segment::Index(index) => {
+                if index.index_into(value).is_some() {
+                    index.index_into_mut(value)
+                } else {
+                    index.to_string().index_into_mut(value)
+                }
+            }
+            Segment::Key(key) => key.index_into_mut(value),
+            Segment::End => None,
+        }
+    }
+
+    /// Returns the existing map key addressed by this segment, if any
+    fn resolve_key(&self, map: &BTreeMap<Value, Value>) -> Option<Value> {
+        let key = match self {
+            Segment::Field(name) => Value::String(name.clone()),
+            Segment::Key(key) => key.clone(),
+            Segment::Index(index) => {
+                return map.keys()
+                    .find(|k| matches!(k, Value::Number(n) if number_eq_index(n, *index)))
+                    .or_else(|| map.get_key_value(&Value::String(index.to_string())).map(|(k, _)| k))
+                    .cloned();
+            }
+            Segment::End => return None,
+        };
+        map.contains_key(&key).then_some(key)
+    }
+
+    /// Returns the key used to insert a new map entry addressed by this segment
+    fn to_key(&self) -> Option<Value> {
+        match self {
+            Segment::Field(name) => Some(Value::String(name.clone())),
+            Segment::Index(index) => Some(Value::String(index.to_string())),
+            Segment::Key(key) => Some(key.clone()),
+            Segment::End => None,
+        }
+    }
+
+    /// Returns the position addressed by this segment in a sequence of `len` elements
+    fn position(&self, len: usize) -> Option<usize> {
+        match self {
+            Segment::Index(index) => Some(*index),
+            Segment::End => Some(len),
+            Segment::Key(Value::Number(n)) => number_as_index(n),
+            _ => None,
+        }
+    }
+}
+
+/// Returns the direct children of `value` with the segments addressing them.
+///
+/// `String` map keys are addressed by [`Segment::Field`], other keys by [`Segment::Key`].
+pub(crate) fn children(value: &Value) -> Vec<(Segment, &Value)> {
+    match value {
+        Value::Seq(values)
+        | Value::Tuple(values)
+        | Value::TupleStruct(TupleStruct { values, .. })
+        | Value::TupleVariant(TupleVariant { values, .. }) => {
+            values.iter().enumerate().map(|(i, v)| (Segment::Index(i), v)).collect()
+        }
+        Value::Map(map) => map.iter().map(|(k, v)| (key_segment(k), v)).collect(),
+        Value::NamedStruct(NamedStruct { fields, .. })
+        | Value::NamedVariant(NamedVariant { fields, .. }) => {
+            fields.iter().map(|(name, v)| (Segment::Field(name.to_string()), v)).collect()
+        }
+        _ => Vec::new(),
+    }
+}
+
+/// Returns the segment addressing the map entry with `key`
+pub(crate) fn key_segment(key: &Value) -> Segment {
+    match key {
+        Value::String(key) => Segment::Field(key.clone()),
+        key => Segment::Key(key.clone()),
+    }
+}
+
+impl Value {
+    /// Looks up a value by a JSON-Pointer-style string, see [`Path`] for the syntax.
+    ///
+    /// Returns `None` if the pointer is malformed or nothing exists at it.
+    ///
+    /// ```
+    /// use serde_value::Value;
+    ///
+    /// let value = Value::from(vec![vec![1u8, 2], vec![3, 4]]);
+    /// assert_eq!(value.pointer("/1/0"), Some(&Value::from(3u8)));
+    /// assert_eq!(value.pointer("/2"), None);
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        self.get_path(&Path::parse(pointer).ok()?)
+    }
+
+    /// Mutable version of [`Value::pointer`]
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        self.get_path_mut(&Path::parse(pointer).ok()?)
+    }
+
+    /// Looks up a value by a typed [`Path`]
+    pub fn get_path(&self, path: &Path) -> Option<&Value> {
+        path.segments.iter().try_fold(self, |value, segment| segment.resolve(value))
+    }
+
+    /// Mutable version of [`Value::get_path`]
+    pub fn get_path_mut(&mut self, path: &Path) -> Option<&mut Value> {
+        path.segments.iter().try_fold(self, |value, segment| segment.resolve_mut(value))
+    }
+
+    /// Inserts `value` at `path`, returning the value it replaced.
+    ///
+    /// * in a `Map`, the entry is inserted or replaced
+    /// * in a `Seq`, `Tuple`, `TupleStruct` or `TupleVariant`, the value is inserted before the
+    ///   element at the index, shifting the following elements, or appended for [`Segment::End`]
+    /// * in a `NamedStruct` or `NamedVariant`, the field named by a [`Segment::Field`] is inserted
+    ///   or replaced, while other segments can only replace an existing field. Field names are
+    ///   `&'static str`, so the name of a new field is kept for the rest of the process, and
+    ///   [`PathError::UnknownField`] is returned once a megabyte of such names is kept
+    /// * an empty path replaces `self`
+    pub fn insert_at(&mut self, path: &Path, value: Value) -> Result<Option<Value>, PathError> {
+        let Some((last, parent_path)) = path.segments.split_last() else {
+            return Ok(Some(std::mem::replace(self, value)));
+        };
+
+        let parent = self.get_path_mut(&Path::from(parent_path.to_vec()))
+            .ok_or_else(|| PathError::NotFound(Path::from(parent_path.to_vec())))?;
+
+        match parent {
+            Value::Map(map) => {
+                let key = last.resolve_key(map)
+                    .or_else(|| last.to_key())
+                    .ok_or_else(|| PathError::NotFound(path.clone()))?;
+                Ok(map.insert(key, value))
+            }
+            Value::Seq(values)
+            | Value::Tuple(values)
+            | Value::TupleStruct(TupleStruct { values, .. })
+            | Value::TupleVariant(TupleVariant { values, .. }) => {
+                let index = last.position(values.len()).ok_or_else(|| PathError::NotFound(path.clone()))?;
+                if index > values.len() {
+                    return Err(PathError::OutOfBounds { path: path.clone(), len: values.len() });
+                }
+                values.insert(index, value);
+                Ok(None)
+            }
+            Value::NamedStruct(NamedStruct { fields, .. })
+            | Value::NamedVariant(NamedVariant { fields, .. }) => match last {
+                Segment::Field(name) => match fields.get_mut(name.as_str()) {
+                    Some(field) => Ok(Some(std::mem::replace(field, value))),
+                    None => {
+                        let name = intern(name).ok_or_else(|| PathError::UnknownField(path.clone()))?;
+                        Ok(fields.insert(name, value))
+                    }
+                },
+                _ => {
+                    let field = last.resolve_mut(parent).ok_or_else(|| PathError::UnknownField(path.clone()))?;
+                    Ok(Some(std::mem::replace(field, value)))
+                }
+            },
+            _ => Err(PathError::NotAContainer(Path::from(parent_path.to_vec()))),
+        }
+    }
+
+    /// Removes and returns the value at `path`.
+    ///
+    /// Removing from a `Seq`, `Tuple`, `TupleStruct` or `TupleVariant` shifts the following
+    /// elements, and removing from a `NamedStruct` or `NamedVariant` drops the field.
+    pub fn remove_at(&mut self, path: &Path) -> Result<Value, PathError> {
+        let Some((last, parent_path)) = path.segments.split_last() else {
+            return Err(PathError::EmptyPath);
+        };
+
+        let parent = self.get_path_mut(&Path::from(parent_path.to_vec()))
+            .ok_or_else(|| PathError::NotFound(Path::from(parent_path.to_vec())))?;
+
+        let removed = match parent {
+            Value::Map(map) => last.resolve_key(map).and_then(|key| map.remove(&key)),
+            Value::Seq(values)
+            | Value::Tuple(values)
+            | Value::TupleStruct(TupleStruct { values, .. })
+            | Value::TupleVariant(TupleVariant { values, .. }) => {
+                match last.position(values.len()) {
+                    Some(index) if index < values.len() => Some(values.remove(index)),
+                    _ => None,
+                }
+            }
+            Value::NamedStruct(NamedStruct { fields, .. })
+            | Value::NamedVariant(NamedVariant { fields, .. }) => {
+                let name = match last {
+                    Segment::Field(name) => Some(name.clone()),
+                    Segment::Index(index) => Some(index.to_string()),
+                    Segment::Key(Value::String(name)) => Some(name.clone()),
+                    _ => None,
+                };
+                name.and_then(|name| fields.remove(name.as_str()))
+            }
+            _ => return Err(PathError::NotAContainer(Path::from(parent_path.to_vec()))),
+        };
+
+        removed.ok_or_else(|| PathError::NotFound(path.clone()))
+    }
+}
+
+/// How many bytes of names [`intern`] leaks at most, so that names built from input cannot grow
+/// memory without bound
+const MAX_INTERNED: usize = 1 << 20;
+
+/// Returns a `&'static str` equal to `name`, leaking each distinct name once, or `None` once
+/// `MAX_INTERNED` bytes of names are leaked
+pub(crate) fn intern(name: &str) -> Option<&'static str> {
+    static NAMES: Mutex<Interned> = Mutex::new(Interned::new());
+    NAMES.lock().unwrap().intern(name, MAX_INTERNED)
+}
+
+/// Leaked names, with their total length
+struct Interned {
+    names: BTreeSet<&'static str>,
+    len: usize,
+}
+
+impl Interned {
+    const fn new() -> Self {
+        Interned { names: BTreeSet::new(), len: 0 }
+    }
+
+    fn intern(&mut self, name: &str, max_len: usize) -> Option<&'static str> {
+        if let Some(name) = self.names.get(name) {
+            return Some(name);
+        }
+        if self.len + name.len() > max_len {
+            return None;
+        }
+        let name: &'static str = Box::leak(name.into());
+        self.names.insert(name);
+        self.len += name.len();
+        Some(name)
+    }
+}
+
+#[test]
+fn test_path() {
+    let mut ports = BTreeMap::new();
+    ports.insert(Value::from("http"), Value::from(80u16));
+    ports.insert(Value::from(8443u32), Value::from("alt"));
+    ports.insert(Value::from('/'), Value::from("slash"));
+
+    let mut fields = BTreeMap::new();
+    fields.insert("ports", Value::Map(ports));
+    fields.insert("tags", Value::from(vec!["a", "b"]));
+    let server = Value::NamedVariant(NamedVariant { name: "Node", variant: "Server", fields });
+
+    let mut fields = BTreeMap::new();
+    fields.insert("servers", Value::Seq(vec![server]));
+    let mut value = Value::NamedStruct(NamedStruct { name: "Config", fields });
+
+    assert_eq!(value.pointer("/servers/0/ports/http"), Some(&Value::from(80u16)));
+    assert_eq!(value.pointer("/servers/0/ports/8443"), Some(&Value::from("alt")));
+    assert_eq!(value.pointer("/servers/0/ports/{8443u32}"), Some(&Value::from("alt")));
+    assert_eq!(value.pointer("/servers/0/ports/{'~1'}"), Some(&Value::from("slash")));
+    assert_eq!(value.pointer("/servers/0/ports/{8443u16}"), None);
+    assert_eq!(value.pointer("servers"), None);
+
+    let path = Path::from(vec![Segment::from("servers"), Segment::from(0), Segment::from("ports"), Segment::Key(Value::from('/'))]);
+    assert_eq!(path.to_string(), "/servers/0/ports/{'~1'}");
+    assert_eq!(Path::parse(&path.to_string()), Ok(path.clone()));
+    assert_eq!(Path::parse("/~2a~0b/-").unwrap().segments(), &[Segment::from("{a~b"), Segment::End]);
+    assert_eq!(Path::parse(&Path::from(vec![Segment::from("12")]).to_string()).unwrap().segments(), &[Segment::Key(Value::from("12"))]);
+
+    *value.pointer_mut("/servers/0/ports/http").unwrap() = Value::from(8080u16);
+    assert_eq!(value["servers"][0]["ports"]["http"], Value::from(8080u16));
+
+    let tags = Path::parse("/servers/0/tags").unwrap();
+    value.insert_at(&tags.child(Segment::Index(1)), Value::from("x")).unwrap();
+    value.insert_at(&tags.child(Segment::End), Value::from("z")).unwrap();
+    assert_eq!(value.get_path(&tags), Some(&Value::from(vec!["a", "x", "b", "z"])));
+    assert_eq!(value.insert_at(&tags.child(Segment::Index(9)), Value::Unit), Err(PathError::OutOfBounds { path: tags.child(Segment::Index(9)), len: 4 }));
+
+    assert_eq!(value.remove_at(&tags.child(Segment::Index(0))), Ok(Value::from("a")));
+    assert_eq!(value.remove_at(&Path::parse("/servers/0/ports/{8443u32}").unwrap()), Ok(Value::from("alt")));
+    assert_eq!(value.remove_at(&tags), Ok(Value::from(vec!["x", "b", "z"])));
+    assert!(value.pointer("/servers/0/tags").is_none());
+    assert_eq!(value.insert_at(&tags, Value::Unit), Ok(None));
+    assert_eq!(value.insert_at(&Path::from(vec![Segment::from("servers"), Segment::from(0), Segment::from("tags")]), Value::from('t')), Ok(Some(Value::Unit)));
+    assert_eq!(value.pointer("/servers/0/tags"), Some(&Value::from('t')));
+    let key = Path::parse("/servers/0/{1u8}").unwrap();
+    assert_eq!(value.insert_at(&key, Value::Unit), Err(PathError::UnknownField(key.clone())));
+
+    let new_port = Path::parse("/servers/0/ports/{9000u32}").unwrap();
+    assert_eq!(value.insert_at(&new_port, Value::from("new")), Ok(None));
+    assert_eq!(value.pointer("/servers/0/ports/9000"), Some(&Value::from("new")));
+
+    let mut interned = Interned::new();
+    let name = interned.intern("ab", 3).unwrap();
+    assert!(std::ptr::eq(interned.intern(&String::from("ab"), 3).unwrap(), name));
+    assert_eq!(interned.intern("cd", 3), None);
+    assert_eq!(interned.intern("c", 3), Some("c"));
+}
@@ -0,0 +1,559 @@
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::ops;
+use std::sync::{Arc, OnceLock};
+use crate::value::{Index, NamedStruct, NamedVariant, Number, Path, Segment, TupleStruct, TupleVariant, UnitStruct, UnitVariant, Value};
+
+/// An immutable value sharing its nodes through `Arc`s
+///
+/// Every node of a `SharedValue` tree is reference counted, so cloning a value is O(1) and
+/// clones share their nodes, across threads as well. Modifying a value copies only the nodes on
+/// the way to the change, which stay shared with the other clones otherwise:
+///
+/// - [`SharedValue::make_mut`] gives mutable access to the [`SharedNode`] of a value, copying
+///   the node first when it is shared, without copying its children
+/// - [`SharedValue::get_mut`], [`SharedValue::get_path_mut`] and `shared[index] = ...` copy each
+///   shared node along the way in the same manner
+///
+/// The accessors mirror those of [`Value`], and a `SharedValue` converts from and into a
+/// `Value`, and orders and compares like the `Value` it was made from.
+///
+/// ```
+/// use serde_value::{SharedValue, Value};
+///
+/// let value = Value::from(vec![vec![1u8, 2], vec![3]]);
+/// let original = SharedValue::from(value.clone());
+///
+/// let mut edited = original.clone();
+/// edited[1][0] = SharedValue::from(Value::from(4u8));
+///
+/// assert_eq!(original.to_value(), value);
+/// assert_eq!(edited.pointer("/1/0").and_then(SharedValue::as_u8), Some(4));
+/// assert!(SharedValue::ptr_eq(&original[0], &edited[0]));
+/// ```
+#[derive(Clone, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct SharedValue(Arc<SharedNode>);
+
+/// A node of a [`SharedValue`] tree, mirroring [`Value`]
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub enum SharedNode {
+    /// A unit value: ()
+    Unit,
+    /// A boolean value
+    Bool(bool),
+    /// A character value
+    Char(char),
+    /// A numeric value
+    Number(Number),
+    /// A string value
+    String(String),
+    /// A collection of values
+    Seq(Vec<SharedValue>),
+    /// A map of values
+    Map(BTreeMap<SharedValue, SharedValue>),
+    /// A tuple of values
+    Tuple(Vec<SharedValue>),
+    /// A struct with no fields
+    UnitStruct(UnitStruct),
+    /// A struct with unnamed fields
+    TupleStruct(TupleStruct<Vec<SharedValue>>),
+    /// A struct with named fields
+    NamedStruct(NamedStruct<BTreeMap<&'static str, SharedValue>>),
+    /// An enum variant with no fields
+    UnitVariant(UnitVariant),
+    /// An enum variant with unnamed fields
+    TupleVariant(TupleVariant<Vec<SharedValue>>),
+    /// An enum variant with named fields
+    NamedVariant(NamedVariant<BTreeMap<&'static str, SharedValue>>),
+}
+
+macro_rules! number_accessors {
+    ($($is:ident $as:ident: $ty:ty),*) => {
+        $(
+            #[inline]
+            pub fn $is(&self) -> bool {
+                self.as_number().is_some_and(|num| num.$is())
+            }
+
+            #[inline]
+            pub fn $as(&self) -> Option<$ty> {
+                self.as_number()?.$as()
+            }
+        )*
+    };
+}
+
+impl SharedValue {
+    /// A value made of a single new node
+    pub fn new(node: SharedNode) -> Self {
+        SharedValue(Arc::new(node))
+    }
+
+    /// The node of this value
+    #[inline]
+    pub fn node(&self) -> &SharedNode {
+        &self.0
+    }
+
+    /// Mutable access to the node of this value, copying the node first if it is shared with
+    /// another clone. The children of a copied node stay shared.
+    pub fn make_mut(&mut self) -> &mut SharedNode {
+        Arc::make_mut(&mut self.0)
+    }
+
+    /// Returns true if both values are the same node, rather than equal nodes
+    pub fn ptr_eq(this: &Self, other: &Self) -> bool {
+        Arc::ptr_eq(&this.0, &other.0)
+    }
+
+    /// The address of the node of this value, which identifies it while it is alive
+    pub(crate) fn as_ptr(&self) -> *const SharedNode {
+        Arc::as_ptr(&self.0)
+    }
+
+    /// Copies this tree into a `Value`
+    pub fn to_value(&self) -> Value {
+        self.clone().into_value()
+    }
+
+    /// Converts this tree into a `Value`, moving the nodes that are not shared and copying the
+    /// other ones
+    pub fn into_value(self) -> Value {
+        let node = Arc::try_unwrap(self.0).unwrap_or_else(|shared| (*shared).clone());
+        let values = |values: Vec<SharedValue>| values.into_iter().map(SharedValue::into_value).collect();
+        let fields = |fields: BTreeMap<&'static str, SharedValue>| {
+            fields.into_iter().map(|(name, value)| (name, value.into_value())).collect()
+        };
+        match node {
+            SharedNode::Unit => Value::Unit,
+            SharedNode::Bool(val) => Value::Bool(val),
+            SharedNode::Char(val) => Value::Char(val),
+            SharedNode::Number(val) => Value::Number(val),
+            SharedNode::String(val) => Value::String(val),
+            SharedNode::Seq(vals) => Value::Seq(values(vals)),
+            SharedNode::Map(map) => Value::Map(map.into_iter().map(|(k, v)| (k.into_value(), v.into_value())).collect()),
+            SharedNode::Tuple(vals) => Value::Tuple(values(vals)),
+            SharedNode::UnitStruct(val) => Value::UnitStruct(val),
+            SharedNode::TupleStruct(TupleStruct { name, values: vals }) => Value::TupleStruct(TupleStruct { name, values: values(vals) }),
+            SharedNode::NamedStruct(NamedStruct { name, fields: vals }) => Value::NamedStruct(NamedStruct { name, fields: fields(vals) }),
+            SharedNode::UnitVariant(val) => Value::UnitVariant(val),
+            SharedNode::TupleVariant(TupleVariant { name, variant, values: vals }) => {
+                Value::TupleVariant(TupleVariant { name, variant, values: values(vals) })
+            }
+            SharedNode::NamedVariant(NamedVariant { name, variant, fields: vals }) => {
+                Value::NamedVariant(NamedVariant { name, variant, fields: fields(vals) })
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_unit(&self) -> bool {
+        matches!(self.node(), SharedNode::Unit)
+    }
+
+    #[inline]
+    pub fn as_unit(&self) -> Option<()> {
+        self.is_unit().then_some(())
+    }
+
+    #[inline]
+    pub fn is_bool(&self) -> bool {
+        matches!(self.node(), SharedNode::Bool(_))
+    }
+
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self.node() {
+            SharedNode::Bool(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_char(&self) -> bool {
+        matches!(self.node(), SharedNode::Char(_))
+    }
+
+    #[inline]
+    pub fn as_char(&self) -> Option<char> {
+        match self.node() {
+            SharedNode::Char(val) => Some(*val),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_number(&self) -> bool {
+        matches!(self.node(), SharedNode::Number(_))
+    }
+
+    #[inline]
+    pub fn as_number(&self) -> Option<Number> {
+        match self.node() {
+            SharedNode::Number(num) => Some(*num),
+            _ => None,
+        }
+    }
+
+    number_accessors!(
+        is_u8 as_u8: u8, is_u16 as_u16: u16, is_u32 as_u32: u32, is_u64 as_u64: u64, is_u128 as_u128: u128,
+        is_i8 as_i8: i8, is_i16 as_i16: i16, is_i32 as_i32: i32, is_i64 as_i64: i64, is_i128 as_i128: i128,
+        is_f32 as_f32: f32, is_f64 as_f64: f64
+    );
+
+    #[inline]
+    pub fn is_string(&self) -> bool {
+        matches!(self.node(), SharedNode::String(_))
+    }
+
+    #[inline]
+    pub fn as_string(&self) -> Option<&str> {
+        match self.node() {
+            SharedNode::String(s) => Some(s.as_str()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_seq(&self) -> bool {
+        matches!(self.node(), SharedNode::Seq(_))
+    }
+
+    #[inline]
+    pub fn as_seq(&self) -> Option<&[SharedValue]> {
+        match self.node() {
+            SharedNode::Seq(values) => Some(values.as_slice()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_map(&self) -> bool {
+        matches!(self.node(), SharedNode::Map(_))
+    }
+
+    pub fn as_map(&self) -> Option<&BTreeMap<SharedValue, SharedValue>> {
+        match self.node() {
+            SharedNode::Map(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_tuple(&self) -> bool {
+        matches!(self.node(), SharedNode::Tuple(_))
+    }
+
+    pub fn as_tuple(&self) -> Option<&[SharedValue]> {
+        match self.node() {
+            SharedNode::Tuple(values) => Some(values.as_slice()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn is_unit_struct(&self) -> bool {
+        matches!(self.node(), SharedNode::UnitStruct(_))
+    }
+
+    #[inline]
+    pub fn is_tuple_struct(&self) -> bool {
+        matches!(self.node(), SharedNode::TupleStruct(_))
+    }
+
+    #[inline]
+    pub fn is_named_struct(&self) -> bool {
+        matches!(self.node(), SharedNode::NamedStruct(_))
+    }
+
+    #[inline]
+    pub fn is_unit_variant(&self) -> bool {
+        matches!(self.node(), SharedNode::UnitVariant(_))
+    }
+
+    #[inline]
+    pub fn is_tuple_variant(&self) -> bool {
+        matches!(self.node(), SharedNode::TupleVariant(_))
+    }
+
+    #[inline]
+    pub fn is_named_variant(&self) -> bool {
+        matches!(self.node(), SharedNode::NamedVariant(_))
+    }
+
+    pub fn as_unit_struct(&self) -> Option<&UnitStruct> {
+        match self.node() {
+            SharedNode::UnitStruct(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_tuple_struct(&self) -> Option<&TupleStruct<Vec<SharedValue>>> {
+        match self.node() {
+            SharedNode::TupleStruct(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_named_struct(&self) -> Option<&NamedStruct<BTreeMap<&'static str, SharedValue>>> {
+        match self.node() {
+            SharedNode::NamedStruct(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_unit_variant(&self) -> Option<&UnitVariant> {
+        match self.node() {
+            SharedNode::UnitVariant(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_tuple_variant(&self) -> Option<&TupleVariant<Vec<SharedValue>>> {
+        match self.node() {
+            SharedNode::TupleVariant(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    pub fn as_named_variant(&self) -> Option<&NamedVariant<BTreeMap<&'static str, SharedValue>>> {
+        match self.node() {
+            SharedNode::NamedVariant(val) => Some(val),
+            _ => None,
+        }
+    }
+
+    /// Returns the element, field or map entry addressed by `index`, see [`Index`].
+    ///
+    /// Returns `None` if the index is missing or this value cannot be indexed by `I`.
+    #[inline]
+    pub fn get<I: Index>(&self, index: I) -> Option<&SharedValue> {
+        index.index_into_shared(self.node())
+    }
+
+    /// Mutable version of [`SharedValue::get`], which copies this node first if it is shared
+    /// and `index` is found
+    pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut SharedValue> {
+        index.index_into_shared(self.node())?;
+        index.index_into_shared_mut(self.make_mut())
+    }
+
+    /// Looks up a value by a JSON-Pointer-style string, see [`Path`] for the syntax.
+    ///
+    /// Returns `None` if the pointer is malformed or nothing exists at it.
+    pub fn pointer(&self, pointer: &str) -> Option<&SharedValue> {
+        self.get_path(&Path::parse(pointer).ok()?)
+    }
+
+    /// Mutable version of [`SharedValue::pointer`]
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut SharedValue> {
+        self.get_path_mut(&Path::parse(pointer).ok()?)
+    }
+
+    /// Looks up a value by a typed [`Path`]
+    pub fn get_path(&self, path: &Path) -> Option<&SharedValue> {
+        path.segments().iter().try_fold(self, |value, segment| resolve(segment, value))
+    }
+
+    /// Mutable version of [`SharedValue::get_path`], which copies the shared nodes on the way
+    /// only if the whole path is found
+    pub fn get_path_mut(&mut self, path: &Path) -> Option<&mut SharedValue> {
+        self.get_path(path)?;
+        path.segments().iter().try_fold(self, |value, segment| resolve_mut(segment, value))
+    }
+}
+
+/// Returns the child of `value` addressed by `segment`, as [`Segment::resolve`] does
+fn resolve<'v>(segment: &Segment, value: &'v SharedValue) -> Option<&'v SharedValue> {
+    match segment {
+        Segment::Field(name) => value.get(name.as_str()),
+        Segment::Index(index) => value.get(*index).or_else(|| match value.node() {
+            SharedNode::Map(_) | SharedNode::NamedStruct(_) | SharedNode::NamedVariant(_) => value.get(index.to_string()),
+            _ => None,
+        }),
+        Segment::Key(key) => value.get(key),
+        Segment::End => None,
+    }
+}
+
+/// Mutable version of [`resolve`]
+fn resolve_mut<'v>(segment: &Segment, value: &'v mut SharedValue) -> Option<&'v mut SharedValue> {
+    match segment {
+        Segment::Field(name) => value.get_mut(name.as_str()),
+        Segment::Index(index) => {
+            if value.get(*index).is_some() {
+                value.get_mut(*index)
+            } else {
+                value.get_mut(index.to_string())
+            }
+        }
+        Segment::Key(key) => value.get_mut(key),
+        Segment::End => None,
+    }
+}
+
+impl From<Value> for SharedValue {
+    fn from(value: Value) -> Self {
+        let values = |values: Vec<Value>| values.into_iter().map(SharedValue::from).collect();
+        let fields = |fields: BTreeMap<&'static str, Value>| {
+            fields.into_iter().map(|(name, value)| (name, SharedValue::from(value))).collect()
+        };
+        SharedValue::new(match value {
+            Value::Unit => SharedNode::Unit,
+            Value::Bool(val) => SharedNode::Bool(val),
+            Value::Char(val) => SharedNode::Char(val),
+            Value::Number(val) => SharedNode::Number(val),
+            Value::String(val) => SharedNode::String(val),
+            Value::Seq(vals) => SharedNode::Seq(values(vals)),
+            Value::Map(map) => SharedNode::Map(map.into_iter().map(|(k, v)| (k.into(), v.into())).collect()),
+            Value::Tuple(vals) => SharedNode::Tuple(values(vals)),
+            Value::UnitStruct(val) => SharedNode::UnitStruct(val),
+            Value::TupleStruct(val) => SharedNode::TupleStruct(TupleStruct { name: val.name, values: values(val.values) }),
+            Value::NamedStruct(val) => SharedNode::NamedStruct(NamedStruct { name: val.name, fields: fields(val.fields) }),
+            Value::UnitVariant(val) => SharedNode::UnitVariant(val),
+            Value::TupleVariant(val) => {
+                SharedNode::TupleVariant(TupleVariant { name: val.name, variant: val.variant, values: values(val.values) })
+            }
+            Value::NamedVariant(val) => {
+                SharedNode::NamedVariant(NamedVariant { name: val.name, variant: val.variant, fields: fields(val.fields) })
+            }
+        })
+    }
+}
+
+impl From<&Value> for SharedValue {
+    fn from(value: &Value) -> Self {
+        SharedValue::from(value.clone())
+    }
+}
+
+impl From<SharedNode> for SharedValue {
+    fn from(node: SharedNode) -> Self {
+        SharedValue::new(node)
+    }
+}
+
+impl From<SharedValue> for Value {
+    fn from(value: SharedValue) -> Self {
+        value.into_value()
+    }
+}
+
+/// Prints the same notation as the `Debug` implementation of [`Value`]
+impl Debug for SharedValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.node() {
+            SharedNode::Unit => write!(f, "{:?}", ()),
+            SharedNode::Bool(val) => write!(f, "{val:?}"),
+            SharedNode::Char(val) => write!(f, "{val:?}"),
+            SharedNode::Number(val) => write!(f, "{val:?}"),
+            SharedNode::String(val) => write!(f, "{val:?}"),
+            SharedNode::Seq(values) => f.debug_list().entries(values).finish(),
+            SharedNode::Map(map) => f.debug_map().entries(map).finish(),
+            SharedNode::Tuple(values) => debug_tuple(f, "", values),
+            SharedNode::UnitStruct(val) => write!(f, "{val:?}"),
+            SharedNode::TupleStruct(val) => write!(f, "{val:?}"),
+            SharedNode::NamedStruct(val) => write!(f, "{val:?}"),
+            SharedNode::UnitVariant(val) => write!(f, "{val:?}"),
+            SharedNode::TupleVariant(val) => write!(f, "{val:?}"),
+            SharedNode::NamedVariant(val) => write!(f, "{val:?}"),
+        }
+    }
+}
+
+fn debug_tuple(f: &mut Formatter<'_>, name: &str, values: &[SharedValue]) -> std::fmt::Result {
+    let mut builder = f.debug_tuple(name);
+    for value in values {
+        builder.field(value);
+    }
+    builder.finish()
+}
+
+/// Index into a `SharedValue` using `shared[index]`.
+///
+/// Returns a reference to a `Unit` value if the index is missing or the value is not a
+/// compound that can be indexed by `I`. Use [`SharedValue::get`] to tell the two cases apart.
+impl<I: Index> ops::Index<I> for SharedValue {
+    type Output = SharedValue;
+
+    fn index(&self, index: I) -> &Self::Output {
+        static UNIT: OnceLock<SharedValue> = OnceLock::new();
+        self.get(index).unwrap_or_else(|| UNIT.get_or_init(|| SharedValue::new(SharedNode::Unit)))
+    }
+}
+
+/// Mutably index into a `SharedValue` using `shared[index] = ...`, copying this node first if it
+/// is shared.
+///
+/// Indexing a `Map` with a missing key inserts a `Unit` value under that key, or under a `u64`
+/// key for a missing `usize` index.
+///
+/// # Panics
+///
+/// Panics if the index is missing from any other compound variant, or if the value cannot
+/// be indexed by `I`.
+impl<I: Index> ops::IndexMut<I> for SharedValue {
+    fn index_mut(&mut self, index: I) -> &mut Self::Output {
+        index.index_or_insert_shared(self.make_mut())
+    }
+}
+
+#[test]
+fn test_shared() {
+    use serde_derive::Serialize;
+    use crate::to_value;
+
+    #[derive(Serialize)]
+    struct Document {
+        title: String,
+        sections: Vec<(u8, Vec<String>)>,
+        tags: BTreeMap<String, bool>,
+    }
+
+    let value = to_value(&Document {
+        title: "draft".to_string(),
+        sections: vec![(1, vec!["a".to_string()]), (2, vec!["b".to_string(), "c".to_string()])],
+        tags: [("new".to_string(), true)].into(),
+    }).unwrap();
+    let original = SharedValue::from(&value);
+    assert_eq!(format!("{original:?}"), format!("{value:?}"));
+    assert_eq!(original["sections"][1][1][0].as_string(), Some("b"));
+    assert_eq!(original["tags"]["new"].as_bool(), Some(true));
+    assert!(original["missing"].is_unit());
+    assert!(original.get("missing").is_none());
+    assert_eq!(original.as_named_struct().map(|val| (val.name, val.fields.len())), Some(("Document", 3)));
+    assert!(original.as_tuple_struct().is_none() && original["title"].as_named_struct().is_none());
+    let some = SharedValue::from(Value::from(Some(Value::UnitVariant(UnitVariant { name: "E", variant: "A" }))));
+    assert_eq!(some.as_tuple_struct().map(|val| val.values[0].as_unit_variant()), Some(Some(&UnitVariant { name: "E", variant: "A" })));
+    assert_eq!(SharedValue::from(Value::UnitStruct(UnitStruct { name: "U" })).as_unit_struct(), Some(&UnitStruct { name: "U" }));
+
+    let snapshot = original.clone();
+    assert!(SharedValue::ptr_eq(&snapshot, &original));
+
+    let mut history = vec![original.clone()];
+    let mut edited = original.clone();
+    *edited.pointer_mut("/sections/1/1/0").unwrap() = SharedValue::from(Value::from("z"));
+    edited["tags"]["old"] = SharedValue::from(Value::from(false));
+    history.push(edited.clone());
+
+    assert_eq!(original.to_value(), value);
+    assert_eq!(edited.pointer("/sections/1/1/0").and_then(SharedValue::as_string), Some("z"));
+    assert!(SharedValue::ptr_eq(&original["title"], &edited["title"]));
+    assert!(SharedValue::ptr_eq(&original["sections"][0], &edited["sections"][0]));
+    assert!(!SharedValue::ptr_eq(&original["sections"][1], &edited["sections"][1]));
+    assert!(edited.get_path_mut(&Path::parse("/sections/9").unwrap()).is_none());
+
+    let SharedNode::NamedStruct(NamedStruct { fields, .. }) = edited.make_mut() else { unreachable!() };
+    fields.remove("tags");
+    assert_eq!(history[1].to_value()["tags"]["old"], Value::from(false));
+    assert!(edited.get("tags").is_none());
+
+    let threads: Vec<_> = (0..2)
+        .map(|_| {
+            let shared = original.clone();
+            std::thread::spawn(move || shared["sections"][0][0].as_u8())
+        })
+        .collect();
+    assert!(threads.into_iter().all(|thread| thread.join().unwrap() == Some(1)));
+    assert_eq!(Value::from(original), value);
+}
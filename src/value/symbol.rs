@@ -0,0 +1,249 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::value::{NamedStruct, NamedVariant, SharedNode, SharedValue, TupleStruct, TupleVariant, UnitStruct, UnitVariant, Value};
+use crate::value::path::intern;
+
+/// A struct, field, enum or variant name in a [`SymbolTable`]
+///
+/// Symbols are four bytes wide and compare and hash as integers. They are indices into the
+/// table that made them, in insertion order, and mean nothing to another table.
+#[derive(Copy, Clone, Debug, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// The index of this symbol in its table
+    pub fn id(self) -> u32 {
+        self.0
+    }
+}
+
+/// A table of struct, field, enum and variant names, each stored once and identified by a
+/// [`Symbol`]
+///
+/// A table is owned by its user and holds the `&'static str` names of [`Value`]s:
+///
+/// - [`SymbolTable::insert`] stores the `&'static str` names produced by serde as they are
+/// - [`SymbolTable::intern`] stores runtime names, which are copied once and kept for the rest
+///   of the process, up to a megabyte of such names in the process
+///
+/// A table is also the vocabulary of [`Value::from_binary`]: names read from the binary encoding
+/// are looked up in the table rather than copied, so that untrusted input cannot grow memory,
+/// and names missing from it are errors. [`SymbolTable::collect`] adds the names of a value,
+/// e.g. one captured from each type expected in the input.
+///
+/// ```
+/// use serde_derive::Serialize;
+/// use serde_value::{Path, SymbolTable, Value};
+///
+/// #[derive(Serialize)]
+/// struct User {
+///     name: String,
+/// }
+///
+/// let mut value = serde_value::to_value(&User { name: "a".to_string() }).unwrap();
+/// let mut symbols = SymbolTable::new();
+/// symbols.collect(&value);
+/// assert_eq!(symbols.get("name").map(|symbol| symbol.id()), Some(1));
+///
+/// let runtime = String::from("user_id");
+/// let field = symbols.intern(&runtime).unwrap();
+/// assert_eq!(symbols.get("user_id"), Some(field));
+/// value.insert_at(&Path::from(vec![symbols.name(field).into()]), Value::from(7u32)).unwrap();
+/// assert_eq!(value["user_id"], Value::from(7u32));
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable {
+    names: Vec<&'static str>,
+    symbols: HashMap<&'static str, Symbol>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of names stored
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    /// The symbol of a `&'static str` name, stored without copying it when it is new
+    pub fn insert(&mut self, name: &'static str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+        let symbol = Symbol(u32::try_from(self.names.len()).expect("symbol tables are limited to u32::MAX names"));
+        self.names.push(name);
+        self.symbols.insert(name, symbol);
+        symbol
+    }
+
+    /// The symbol of a runtime name, copied for the rest of the process when it is new, or
+    /// `None` once a megabyte of runtime names is kept
+    pub fn intern(&mut self, name: &str) -> Option<Symbol> {
+        match self.symbols.get(name) {
+            Some(symbol) => Some(*symbol),
+            None => Some(self.insert(intern(name)?)),
+        }
+    }
+
+    /// The symbol of `name` if it is stored
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    /// The name of `symbol`
+    ///
+    /// # Panics
+    /// if `symbol` was not made by this table
+    pub fn name(&self, symbol: Symbol) -> &'static str {
+        self.names[symbol.0 as usize]
+    }
+
+    /// The stored names, in the order of their symbols
+    pub fn names(&self) -> &[&'static str] {
+        &self.names
+    }
+
+    /// Stores the names of `value`, in order of first appearance
+    pub fn collect(&mut self, value: &Value) {
+        match value {
+            Value::Unit | Value::Bool(_) | Value::Char(_) | Value::Number(_) | Value::String(_) => {}
+            Value::Seq(values) | Value::Tuple(values) => values.iter().for_each(|value| self.collect(value)),
+            Value::Map(map) => {
+                for (key, value) in map {
+                    self.collect(key);
+                    self.collect(value);
+                }
+            }
+            Value::UnitStruct(val) => {
+                self.insert(val.name);
+            }
+            Value::TupleStruct(val) => {
+                self.insert(val.name);
+                val.values.iter().for_each(|value| self.collect(value));
+            }
+            Value::NamedStruct(val) => {
+                self.insert(val.name);
+                self.collect_fields(&val.fields);
+            }
+            Value::UnitVariant(val) => {
+                self.insert(val.name);
+                self.insert(val.variant);
+            }
+            Value::TupleVariant(val) => {
+                self.insert(val.name);
+                self.insert(val.variant);
+                val.values.iter().for_each(|value| self.collect(value));
+            }
+            Value::NamedVariant(val) => {
+                self.insert(val.name);
+                self.insert(val.variant);
+                self.collect_fields(&val.fields);
+            }
+        }
+    }
+
+    fn collect_fields(&mut self, fields: &BTreeMap<&'static str, Value>) {
+        for (name, value) in fields {
+            self.insert(name);
+            self.collect(value);
+        }
+    }
+
+    /// Stores the names of a shared value, visiting each shared node once
+    pub(crate) fn collect_shared(&mut self, value: &SharedValue, visited: &mut HashSet<*const SharedNode>) {
+        if !visited.insert(value.as_ptr()) {
+            return;
+        }
+        match value.node() {
+            SharedNode::Unit
+            | SharedNode::Bool(_)
+            | SharedNode::Char(_)
+            | SharedNode::Number(_)
+            | SharedNode::String(_) => {}
+            SharedNode::Seq(values) | SharedNode::Tuple(values) => values.iter().for_each(|value| self.collect_shared(value, visited)),
+            SharedNode::Map(map) => {
+                for (key, value) in map {
+                    self.collect_shared(key, visited);
+                    self.collect_shared(value, visited);
+                }
+            }
+            SharedNode::UnitStruct(UnitStruct { name }) => {
+                self.insert(name);
+            }
+            SharedNode::TupleStruct(TupleStruct { name, values }) => {
+                self.insert(name);
+                values.iter().for_each(|value| self.collect_shared(value, visited));
+            }
+            SharedNode::NamedStruct(NamedStruct { name, fields }) => {
+                self.insert(name);
+                self.collect_shared_fields(fields, visited);
+            }
+            SharedNode::UnitVariant(UnitVariant { name, variant }) => {
+                self.insert(name);
+                self.insert(variant);
+            }
+            SharedNode::TupleVariant(TupleVariant { name, variant, values }) => {
+                self.insert(name);
+                self.insert(variant);
+                values.iter().for_each(|value| self.collect_shared(value, visited));
+            }
+            SharedNode::NamedVariant(NamedVariant { name, variant, fields }) => {
+                self.insert(name);
+                self.insert(variant);
+                self.collect_shared_fields(fields, visited);
+            }
+        }
+    }
+
+    fn collect_shared_fields(&mut self, fields: &BTreeMap<&'static str, SharedValue>, visited: &mut HashSet<*const SharedNode>) {
+        for (name, value) in fields {
+            self.insert(name);
+            self.collect_shared(value, visited);
+        }
+    }
+}
+
+impl Extend<&'static str> for SymbolTable {
+    fn extend<I: IntoIterator<Item=&'static str>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|name| {
+            self.insert(name);
+        });
+    }
+}
+
+impl FromIterator<&'static str> for SymbolTable {
+    fn from_iter<I: IntoIterator<Item=&'static str>>(iter: I) -> Self {
+        let mut symbols = SymbolTable::new();
+        symbols.extend(iter);
+        symbols
+    }
+}
+
+#[test]
+fn test_symbol() {
+    static NAME: &str = "symbol_test_static";
+    let mut symbols = SymbolTable::new();
+    let first = symbols.insert(NAME);
+    assert!(std::ptr::eq(symbols.name(first), NAME));
+    assert_eq!(symbols.insert("symbol_test_static"), first);
+
+    let runtime = String::from("symbol_test_runtime");
+    assert_eq!(symbols.get(&runtime), None);
+    let second = symbols.intern(&runtime).unwrap();
+    assert_eq!(symbols.get("symbol_test_runtime"), Some(second));
+    assert_eq!((first.id(), second.id()), (0, 1));
+    assert!(first < second);
+    assert_eq!(symbols.names(), ["symbol_test_static", "symbol_test_runtime"]);
+
+    let value = Value::from(vec![Some(1u8), None]);
+    let collected: SymbolTable = ["None"].into_iter().collect();
+    let mut symbols = collected.clone();
+    symbols.collect(&value);
+    assert_eq!(symbols.names(), ["None", "Some"]);
+    assert_eq!(collected.len(), 1);
+}
@@ -0,0 +1,413 @@
+//! Traversal of [`Value`] trees
+//!
+//! [`Visit`] and [`VisitMut`] have one hook per [`Value`] variant, plus hooks for the children
+//! of compound values (`visit_element`, `visit_field` and `visit_entry`). Every hook has a
+//! default implementation that recurses into the children through the free function of the
+//! same name in this module, so an implementation only overrides the hooks it cares about and
+//! calls the free function to keep descending:
+//!
+//! ```
+//! use serde_value::{NamedStruct, Value};
+//! use serde_value::visit::{self, Visit};
+//!
+//! #[derive(Default)]
+//! struct StructNames(Vec<&'static str>);
+//!
+//! impl<'v> Visit<'v> for StructNames {
+//!     fn visit_named_struct(&mut self, value: &'v NamedStruct) {
+//!         self.0.push(value.name);
+//!         visit::visit_named_struct(self, value);
+//!     }
+//! }
+//! ```
+//!
+//! [`walk`] and [`walk_mut`] are shorthands for visiting every node along with its [`Path`].
+
+use std::collections::BTreeMap;
+use crate::value::{NamedStruct, NamedVariant, Number, Path, Segment, TupleStruct, TupleVariant, UnitStruct, UnitVariant, Value};
+use crate::value::path::key_segment;
+
+/// A read-only traversal of a [`Value`] tree, see the [module documentation](self)
+pub trait Visit<'v> {
+    fn visit_value(&mut self, value: &'v Value) {
+        visit_value(self, value)
+    }
+
+    fn visit_unit(&mut self) {}
+
+    fn visit_bool(&mut self, _value: bool) {}
+
+    fn visit_char(&mut self, _value: char) {}
+
+    fn visit_number(&mut self, _value: &'v Number) {}
+
+    fn visit_string(&mut self, _value: &'v str) {}
+
+    fn visit_seq(&mut self, values: &'v [Value]) {
+        visit_seq(self, values)
+    }
+
+    fn visit_map(&mut self, map: &'v BTreeMap<Value, Value>) {
+        visit_map(self, map)
+    }
+
+    fn visit_tuple(&mut self, values: &'v [Value]) {
+        visit_tuple(self, values)
+    }
+
+    fn visit_unit_struct(&mut self, _value: &'v UnitStruct) {}
+
+    fn visit_tuple_struct(&mut self, value: &'v TupleStruct) {
+        visit_tuple_struct(self, value)
+    }
+
+    fn visit_named_struct(&mut self, value: &'v NamedStruct) {
+        visit_named_struct(self, value)
+    }
+
+    fn visit_unit_variant(&mut self, _value: &'v UnitVariant) {}
+
+    fn visit_tuple_variant(&mut self, value: &'v TupleVariant) {
+        visit_tuple_variant(self, value)
+    }
+
+    fn visit_named_variant(&mut self, value: &'v NamedVariant) {
+        visit_named_variant(self, value)
+    }
+
+    /// Visits an element of a `Seq`, `Tuple`, `TupleStruct` or `TupleVariant`
+    fn visit_element(&mut self, _index: usize, value: &'v Value) {
+        self.visit_value(value)
+    }
+
+    /// Visits a field of a `NamedStruct` or `NamedVariant`
+    fn visit_field(&mut self, _name: &'static str, value: &'v Value) {
+        self.visit_value(value)
+    }
+
+    /// Visits an entry of a `Map`, by default the key and then the value
+    fn visit_entry(&mut self, key: &'v Value, value: &'v Value) {
+        visit_entry(self, key, value)
+    }
+}
+
+/// A mutating traversal of a [`Value`] tree, see the [module documentation](self)
+pub trait VisitMut {
+    fn visit_value_mut(&mut self, value: &mut Value) {
+        visit_value_mut(self, value)
+    }
+
+    fn visit_unit_mut(&mut self) {}
+
+    fn visit_bool_mut(&mut self, _value: &mut bool) {}
+
+    fn visit_char_mut(&mut self, _value: &mut char) {}
+
+    fn visit_number_mut(&mut self, _value: &mut Number) {}
+
+    fn visit_string_mut(&mut self, _value: &mut String) {}
+
+    fn visit_seq_mut(&mut self, values: &mut Vec<Value>) {
+        visit_seq_mut(self, values)
+    }
+
+    fn visit_map_mut(&mut self, map: &mut BTreeMap<Value, Value>) {
+        visit_map_mut(self, map)
+    }
+
+    fn visit_tuple_mut(&mut self, values: &mut Vec<Value>) {
+        visit_tuple_mut(self, values)
+    }
+
+    fn visit_unit_struct_mut(&mut self, _value: &mut UnitStruct) {}
+
+    fn visit_tuple_struct_mut(&mut self, value: &mut TupleStruct) {
+        visit_tuple_struct_mut(self, value)
+    }
+
+    fn visit_named_struct_mut(&mut self, value: &mut NamedStruct) {
+        visit_named_struct_mut(self, value)
+    }
+
+    fn visit_unit_variant_mut(&mut self, _value: &mut UnitVariant) {}
+
+    fn visit_tuple_variant_mut(&mut self, value: &mut TupleVariant) {
+        visit_tuple_variant_mut(self, value)
+    }
+
+    fn visit_named_variant_mut(&mut self, value: &mut NamedVariant) {
+        visit_named_variant_mut(self, value)
+    }
+
+    /// Visits an element of a `Seq`, `Tuple`, `TupleStruct` or `TupleVariant`
+    fn visit_element_mut(&mut self, _index: usize, value: &mut Value) {
+        self.visit_value_mut(value)
+    }
+
+    /// Visits a field of a `NamedStruct` or `NamedVariant`
+    fn visit_field_mut(&mut self, _name: &'static str, value: &mut Value) {
+        self.visit_value_mut(value)
+    }
+
+    /// Visits an entry of a `Map`. Keys cannot be mutated in place, so only the value is visited
+    fn visit_entry_mut(&mut self, _key: &Value, value: &mut Value) {
+        self.visit_value_mut(value)
+    }
+}
+
+pub fn visit_value<'v, V: Visit<'v> + ?Sized>(v: &mut V, value: &'v Value) {
+    match value {
+        Value::Unit => v.visit_unit(),
+        Value::Bool(val) => v.visit_bool(*val),
+        Value::Char(val) => v.visit_char(*val),
+        Value::Number(val) => v.visit_number(val),
+        Value::String(val) => v.visit_string(val),
+        Value::Seq(values) => v.visit_seq(values),
+        Value::Map(map) => v.visit_map(map),
+        Value::Tuple(values) => v.visit_tuple(values),
+        Value::UnitStruct(val) => v.visit_unit_struct(val),
+        Value::TupleStruct(val) => v.visit_tuple_struct(val),
+        Value::NamedStruct(val) => v.visit_named_struct(val),
+        Value::UnitVariant(val) => v.visit_unit_variant(val),
+        Value::TupleVariant(val) => v.visit_tuple_variant(val),
+        Value::NamedVariant(val) => v.visit_named_variant(val),
+    }
+}
+
+pub fn visit_seq<'v, V: Visit<'v> + ?Sized>(v: &mut V, values: &'v [Value]) {
+    for (index, value) in values.iter().enumerate() {
+        v.visit_element(index, value);
+    }
+}
+
+pub fn visit_map<'v, V: Visit<'v> + ?Sized>(v: &mut V, map: &'v BTreeMap<Value, Value>) {
+    for (key, value) in map {
+        v.visit_entry(key, value);
+    }
+}
+
+pub fn visit_tuple<'v, V: Visit<'v> + ?Sized>(v: &mut V, values: &'v [Value]) {
+    visit_seq(v, values)
+}
+
+pub fn visit_tuple_struct<'v, V: Visit<'v> + ?Sized>(v: &mut V, value: &'v TupleStruct) {
+    visit_seq(v, &value.values)
+}
+
+pub fn visit_named_struct<'v, V: Visit<'v> + ?Sized>(v: &mut V, value: &'v NamedStruct) {
+    for (name, value) in &value.fields {
+        v.visit_field(name, value);
+    }
+}
+
+pub fn visit_tuple_variant<'v, V: Visit<'v> + ?Sized>(v: &mut V, value: &'v TupleVariant) {
+    visit_seq(v, &value.values)
+}
+
+pub fn visit_named_variant<'v, V: Visit<'v> + ?Sized>(v: &mut V, value: &'v NamedVariant) {
+    for (name, value) in &value.fields {
+        v.visit_field(name, value);
+    }
+}
+
+pub fn visit_entry<'v, V: Visit<'v> + ?Sized>(v: &mut V, key: &'v Value, value: &'v Value) {
+    v.visit_value(key);
+    v.visit_value(value);
+}
+
+pub fn visit_value_mut<V: VisitMut + ?Sized>(v: &mut V, value: &mut Value) {
+    match value {
+        Value::Unit => v.visit_unit_mut(),
+        Value::Bool(val) => v.visit_bool_mut(val),
+        Value::Char(val) => v.visit_char_mut(val),
+        Value::Number(val) => v.visit_number_mut(val),
+        Value::String(val) => v.visit_string_mut(val),
+        Value::Seq(values) => v.visit_seq_mut(values),
+        Value::Map(map) => v.visit_map_mut(map),
+        Value::Tuple(values) => v.visit_tuple_mut(values),
+        Value::UnitStruct(val) => v.visit_unit_struct_mut(val),
+        Value::TupleStruct(val) => v.visit_tuple_struct_mut(val),
+        Value::NamedStruct(val) => v.visit_named_struct_mut(val),
+        Value::UnitVariant(val) => v.visit_unit_variant_mut(val),
+        Value::TupleVariant(val) => v.visit_tuple_variant_mut(val),
+        Value::NamedVariant(val) => v.visit_named_variant_mut(val),
+    }
+}
+
+pub fn visit_seq_mut<V: VisitMut + ?Sized>(v: &mut V, values: &mut [Value]) {
+    for (index, value) in values.iter_mut().enumerate() {
+        v.visit_element_mut(index, value);
+    }
+}
+
+pub fn visit_map_mut<V: VisitMut + ?Sized>(v: &mut V, map: &mut BTreeMap<Value, Value>) {
+    for (key, value) in map.iter_mut() {
+        v.visit_entry_mut(key, value);
+    }
+}
+
+pub fn visit_tuple_mut<V: VisitMut + ?Sized>(v: &mut V, values: &mut [Value]) {
+    visit_seq_mut(v, values)
+}
+
+pub fn visit_tuple_struct_mut<V: VisitMut + ?Sized>(v: &mut V, value: &mut TupleStruct) {
+    visit_seq_mut(v, &mut value.values)
+}
+
+pub fn visit_named_struct_mut<V: VisitMut + ?Sized>(v: &mut V, value: &mut NamedStruct) {
+    for (name, value) in value.fields.iter_mut() {
+        v.visit_field_mut(name, value);
+    }
+}
+
+pub fn visit_tuple_variant_mut<V: VisitMut + ?Sized>(v: &mut V, value: &mut TupleVariant) {
+    visit_seq_mut(v, &mut value.values)
+}
+
+pub fn visit_named_variant_mut<V: VisitMut + ?Sized>(v: &mut V, value: &mut NamedVariant) {
+    for (name, value) in value.fields.iter_mut() {
+        v.visit_field_mut(name, value);
+    }
+}
+
+/// Calls `f` on every node of `value` in pre-order, along with its path from `value`.
+///
+/// Map keys are not visited as nodes of their own, only the map values.
+///
+/// ```
+/// use serde_value::Value;
+/// use serde_value::visit::walk;
+///
+/// let value = Value::from(vec![vec![1u8], vec![2, 3]]);
+/// let mut numbers = Vec::new();
+/// walk(&value, |path, node| if node.is_number() { numbers.push(path.to_string()) });
+/// assert_eq!(numbers, ["/0/0", "/1/0", "/1/1"]);
+/// ```
+pub fn walk<'v, F: FnMut(&Path, &'v Value)>(value: &'v Value, f: F) {
+    let mut walker = Walker { path: Path::root(), f };
+    walker.visit_value(value);
+}
+
+/// Mutable version of [`walk`].
+///
+/// `f` is called on a node before its children, so the children visited are those of the
+/// node as `f` left it.
+pub fn walk_mut<F: FnMut(&Path, &mut Value)>(value: &mut Value, f: F) {
+    let mut walker = Walker { path: Path::root(), f };
+    walker.visit_value_mut(value);
+}
+
+struct Walker<F> {
+    path: Path,
+    f: F,
+}
+
+impl<'v, F: FnMut(&Path, &'v Value)> Visit<'v> for Walker<F> {
+    fn visit_value(&mut self, value: &'v Value) {
+        (self.f)(&self.path, value);
+        visit_value(self, value);
+    }
+
+    fn visit_element(&mut self, index: usize, value: &'v Value) {
+        self.path.push(Segment::Index(index));
+        self.visit_value(value);
+        self.path.pop();
+    }
+
+    fn visit_field(&mut self, name: &'static str, value: &'v Value) {
+        self.path.push(Segment::Field(name.to_string()));
+        self.visit_value(value);
+        self.path.pop();
+    }
+
+    fn visit_entry(&mut self, key: &'v Value, value: &'v Value) {
+        self.path.push(key_segment(key));
+        self.visit_value(value);
+        self.path.pop();
+    }
+}
+
+impl<F: FnMut(&Path, &mut Value)> VisitMut for Walker<F> {
+    fn visit_value_mut(&mut self, value: &mut Value) {
+        (self.f)(&self.path, value);
+        visit_value_mut(self, value);
+    }
+
+    fn visit_element_mut(&mut self, index: usize, value: &mut Value) {
+        self.path.push(Segment::Index(index));
+        self.visit_value_mut(value);
+        self.path.pop();
+    }
+
+    fn visit_field_mut(&mut self, name: &'static str, value: &mut Value) {
+        self.path.push(Segment::Field(name.to_string()));
+        self.visit_value_mut(value);
+        self.path.pop();
+    }
+
+    fn visit_entry_mut(&mut self, key: &Value, value: &mut Value) {
+        self.path.push(key_segment(key));
+        self.visit_value_mut(value);
+        self.path.pop();
+    }
+}
+
+#[test]
+fn test_visit() {
+    use crate::value::TupleVariant;
+
+    #[derive(Default)]
+    struct Counter {
+        numbers: usize,
+        strings: Vec<&'static str>,
+    }
+
+    impl<'v> Visit<'v> for Counter {
+        fn visit_number(&mut self, _value: &'v Number) {
+            self.numbers += 1;
+        }
+
+        fn visit_field(&mut self, name: &'static str, value: &'v Value) {
+            self.strings.push(name);
+            self.visit_value(value);
+        }
+    }
+
+    struct Doubler;
+
+    impl VisitMut for Doubler {
+        fn visit_number_mut(&mut self, value: &mut Number) {
+            if let Some(v) = value.as_u32() {
+                *value = Number::U32(v * 2);
+            }
+        }
+
+        fn visit_seq_mut(&mut self, values: &mut Vec<Value>) {
+            values.push(Value::from(1u32));
+            visit_seq_mut(self, values);
+        }
+    }
+
+    let mut fields = BTreeMap::new();
+    fields.insert("a", Value::from(vec![1u32, 2]));
+    fields.insert("b", Value::TupleVariant(TupleVariant { name: "E", variant: "V", values: vec![Value::from(3u32)] }));
+    let mut map = BTreeMap::new();
+    map.insert(Value::from(7u8), Value::NamedStruct(NamedStruct { name: "S", fields }));
+    let mut value = Value::Map(map);
+
+    let mut counter = Counter::default();
+    counter.visit_value(&value);
+    assert_eq!(counter.numbers, 4);
+    assert_eq!(counter.strings, ["a", "b"]);
+
+    Doubler.visit_value_mut(&mut value);
+    assert_eq!(value[7]["a"], Value::from(vec![2u32, 4, 2]));
+    assert_eq!(value[7]["b"][0], Value::from(6u32));
+
+    let mut paths = Vec::new();
+    walk(&value, |path, _| paths.push(path.to_string()));
+    assert_eq!(paths, ["", "/{7u8}", "/{7u8}/a", "/{7u8}/a/0", "/{7u8}/a/1", "/{7u8}/a/2", "/{7u8}/b", "/{7u8}/b/0"]);
+
+    walk_mut(&mut value, |path, node| if path.len() == 3 { *node = Value::Unit });
+    assert_eq!(value.pointer("/{7u8}/a"), Some(&Value::Seq(vec![Value::Unit; 3])));
+}